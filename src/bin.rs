@@ -16,6 +16,23 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use std::io::{self, Write};
+
+use hum::hum_process::hum_check::Severity;
+
+const MIDI_EXTENSIONS: [&str; 2] = ["mid", "midi"];
+const MUSICXML_EXTENSIONS: [&str; 2] = ["musicxml", "xml"];
+const ABC_EXTENSIONS: [&str; 1] = ["abc"];
+
+// Prints to stdout. A reader that stops reading early, like `head`,
+// isn't an error.
+fn print_output(output: impl std::fmt::Display) -> io::Result<()> {
+    match write!(io::stdout().lock(), "{}", output) {
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse command line arguments.
     let matches = clap::Command::new("hum")
@@ -27,16 +44,114 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .about("Opens the Hum editor")
                 .arg(clap::Arg::new("FILE").help("The file to edit").index(1)),
         )
+        .subcommand(
+            clap::Command::new("fmt")
+                .about("Formats Hum scores in place, aligning their notes into columns")
+                .arg(
+                    clap::Arg::new("FILES")
+                        .help("The files to format, or - to format stdin to stdout")
+                        .required(true)
+                        .num_args(1..),
+                )
+                .arg(
+                    clap::Arg::new("check")
+                        .long("check")
+                        .action(clap::ArgAction::SetTrue)
+                        .help(
+                            "Prints a diff of each file that isn't formatted, without changing \
+                             it, and exits with an error if there are any",
+                        ),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("check")
+                .about("Checks Hum scores for problems without rendering them")
+                .arg(
+                    clap::Arg::new("FILES")
+                        .help("The files to check, or - to check stdin")
+                        .required(true)
+                        .num_args(1..),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .value_parser(["human", "json"])
+                        .default_value("human")
+                        .help("Prints the problems as human-readable lines or a JSON array"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("info")
+                .about("Prints the length, tempos, voices, and block timings of a Hum score")
+                .arg(
+                    clap::Arg::new("FILE")
+                        .help("The hum notation file, or - to read stdin")
+                        .required(true)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("to-abc")
+                .about("Converts a Hum score to an ABC tune")
+                .arg(
+                    clap::Arg::new("INPUT")
+                        .help("The hum notation file")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("OUTPUT")
+                        .help("The ABC file to write")
+                        .required(true)
+                        .index(2),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("from-abc")
+                .about("Converts the first tune of an ABC file to a Hum score")
+                .arg(
+                    clap::Arg::new("INPUT")
+                        .help("The ABC file")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("OUTPUT")
+                        .help("The hum notation file to write")
+                        .required(true)
+                        .index(2),
+                ),
+        )
         .arg(
             clap::Arg::new("INPUT")
-                .help("Sets the path of the hum notation file.")
+                .help(
+                    "Sets the path of the hum notation file (or a MIDI, MusicXML, or ABC file to \
+                     import). Use - to read the score from stdin.",
+                )
                 .index(1),
         )
         .arg(
             clap::Arg::new("OUTPUT")
-                .help("Sets the path of the output WAV file.")
+                .help(
+                    "Sets the path of the output WAV file (or MIDI file, if it ends in .mid, \
+                     MusicXML file, if it ends in .musicxml, ABC file, if it ends in .abc, or Hum \
+                     file, if the input is a MIDI, MusicXML, or ABC file). Use - to stream the \
+                     audio to stdout as it is rendered.",
+                )
                 .index(2),
         )
+        .arg(
+            clap::Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .value_parser(hum::hum_io::AudioFormat::NAMES)
+                .help(
+                    "Sets the audio format of the output: wav, flac, or raw s16le or f32le \
+                     samples. Defaults to the format of the output's extension, or WAV. An \
+                     OUTPUT of - streams the audio to stdout.",
+                ),
+        )
         .get_matches();
 
     if let Some(matches) = matches.subcommand_matches("edit") {
@@ -45,21 +160,147 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
+    if let Some(matches) = matches.subcommand_matches("fmt") {
+        let files = matches.get_many::<String>("FILES").into_iter().flatten();
+        if !matches.get_flag("check") {
+            for file in files {
+                hum::format_file(file)?;
+            }
+            return Ok(());
+        }
+
+        // Like `rustfmt --check`, report every file before failing:
+        let mut unformatted = 0;
+        for file in files {
+            let diff = hum::check_file_format(file)?;
+            if !diff.is_empty() {
+                print_output(diff)?;
+                unformatted += 1;
+            }
+        }
+        if unformatted > 0 {
+            eprintln!("Error: {} file(s) are not formatted.", unformatted);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("check") {
+        let mut diagnostics = Vec::new();
+        for file in matches.get_many::<String>("FILES").into_iter().flatten() {
+            for diagnostic in hum::check_file(file)? {
+                diagnostics.push((file, diagnostic));
+            }
+        }
+
+        let errors = diagnostics
+            .iter()
+            .filter(|(_, diagnostic)| diagnostic.severity == Severity::Error)
+            .count();
+        if matches.get_one::<String>("format").map(String::as_str) == Some("json") {
+            let diagnostics: Vec<_> = diagnostics.into_iter().map(|(_, d)| d).collect();
+            print_output(hum::hum_process::hum_check::to_json(&diagnostics))?;
+        } else {
+            let mut report = String::new();
+            for (file, diagnostic) in &diagnostics {
+                // Problems with no location still say which file they are in:
+                match diagnostic.location {
+                    Some(_) => report.push_str(&format!("{}\n", diagnostic)),
+                    None => report.push_str(&format!("{}: {}\n", file, diagnostic)),
+                }
+            }
+            print_output(report)?;
+            eprintln!(
+                "{} error(s), {} warning(s)",
+                errors,
+                diagnostics.len() - errors
+            );
+        }
+        if errors > 0 {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("info") {
+        // The argument is required, so clap has already checked it.
+        let file = matches
+            .get_one::<String>("FILE")
+            .cloned()
+            .unwrap_or_default();
+        print_output(hum::describe_file(&file)?)?;
+        return Ok(());
+    }
+
+    for (name, convert) in [
+        ("to-abc", hum::convert_file_to_abc as fn(&str, &str) -> _),
+        ("from-abc", hum::convert_abc_to_hum),
+    ] {
+        if let Some(matches) = matches.subcommand_matches(name) {
+            // Both arguments are required, so clap has already checked them.
+            let input = matches
+                .get_one::<String>("INPUT")
+                .cloned()
+                .unwrap_or_default();
+            let output = matches
+                .get_one::<String>("OUTPUT")
+                .cloned()
+                .unwrap_or_default();
+            convert(&input, &output)?;
+            return Ok(());
+        }
+    }
+
     if let (Some(input), Some(output)) = (
         matches.get_one::<String>("INPUT"),
         matches.get_one::<String>("OUTPUT"),
     ) {
-        // Read the contents of the input file.
-        let score_contents = hum::hum_io::read(input)?;
-
-        // Run the program.
-        hum::convert_to_wav(&score_contents, output)?;
+        // Run the program, picking the output format from the file extension
+        // unless an audio format is given.
+        let format = matches
+            .get_one::<String>("format")
+            .and_then(|name| hum::hum_io::AudioFormat::from_name(name));
+        if let Some(format) = format {
+            hum::convert_file_to_audio(input, output, format)?;
+        } else if has_extension(input, &MIDI_EXTENSIONS) {
+            hum::convert_midi_to_hum(input, output)?;
+        } else if has_extension(input, &MUSICXML_EXTENSIONS) {
+            hum::convert_musicxml_to_hum(input, output)?;
+        } else if has_extension(input, &ABC_EXTENSIONS) {
+            hum::convert_abc_to_hum(input, output)?;
+        } else if has_extension(output, &MIDI_EXTENSIONS) {
+            hum::convert_file_to_midi(input, output)?;
+        } else if has_extension(output, &MUSICXML_EXTENSIONS) {
+            hum::convert_file_to_musicxml(input, output)?;
+        } else if has_extension(output, &ABC_EXTENSIONS) {
+            hum::convert_file_to_abc(input, output)?;
+        } else {
+            let format = hum::hum_io::AudioFormat::from_filename(output);
+            hum::convert_file_to_audio(input, output, format)?;
+        }
     } else {
         eprintln!("Error: Missing INPUT and OUTPUT arguments for conversion.");
-        eprintln!("Usage: hum <INPUT> <OUTPUT>");
+        eprintln!("Usage: hum <INPUT> <OUTPUT> [--format <FORMAT>]");
         eprintln!("       hum edit");
+        eprintln!("       hum fmt [--check] <FILES>...");
+        eprintln!("       hum check [--format <human|json>] <FILES>...");
+        eprintln!("       hum info <FILE>");
+        eprintln!("       hum to-abc <INPUT> <OUTPUT>");
+        eprintln!("       hum from-abc <INPUT> <OUTPUT>");
         std::process::exit(1);
     }
 
     Ok(())
 }
+
+// Checks if a path ends in one of the given file extensions.
+fn has_extension(filename: &str, extensions: &[&str]) -> bool {
+    std::path::Path::new(filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extensions
+                .iter()
+                .any(|expected| extension.eq_ignore_ascii_case(expected))
+        })
+}
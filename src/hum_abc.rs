@@ -0,0 +1,1133 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::hum_error::GenerateError;
+use crate::hum_export::{WrittenMeasure, WrittenNote, WrittenPart, WrittenScore, read_score};
+use crate::hum_import::{ImportedNote, ImportedPart, ImportedScore, UNITS_PER_WHOLE};
+use crate::hum_parse::LocatedCommand;
+
+const DEFAULT_TIME_SIGNATURE: (u32, u32) = (4, 4);
+const DEFAULT_VOICE: &str = "sine";
+const UNIT_LENGTH: u32 = 8; // Exported tunes count lengths in eighth notes (L:1/8)
+const QUARTERS_PER_WHOLE: u32 = 4;
+const MIDDLE_OCTAVE: i32 = 4; // The octave of the uppercase letters, from C to B
+const HYPHEN: char = '-';
+const STACCATO_MARK: char = '.';
+const ACCENT_MARK: char = '>';
+const TENUTO_MARK: char = '_';
+const FERMATA_MARK: char = '^';
+const GLIDE_MARK: &str = "/>";
+const TRILL_MARK: &str = "tr";
+const MORDENT_MARK: &str = "mord";
+const TURN_MARK: &str = "turn";
+const SHARPS: &str = "FCGDAEB"; // The order of sharps in key signatures
+const FLATS: &str = "BEADGCF"; // The order of flats in key signatures
+
+/// Encodes a score as an ABC tune.
+///
+/// The header holds the title (from the first comment of the score), the
+/// time signature, a unit note length of an eighth note, the tempo, and a
+/// `V:` voice for each line of music (counted from the top of each checkpoint
+/// block), named after the voice that plays it. The tune is written in C
+/// major, with accidentals on the notes that need them. Each checkpoint
+/// block becomes a line of the tune for every voice, with repeats, endings,
+/// and signs kept as they are written. Lyrics in `~` lines go in `w:` lines.
+/// Cents offsets can't be written in ABC, so they are left out.
+///
+/// # Arguments
+///
+/// * `commands` - The located commands of the score, with includes and motifs
+///   already expanded.
+///
+/// # Returns
+///
+/// A `Result` containing the ABC text or a `GenerateError`.
+pub fn encode(commands: &[LocatedCommand]) -> Result<String, GenerateError> {
+    let score = read_score(commands)?;
+    Ok(write_tune(&score))
+}
+
+fn write_tune(score: &WrittenScore) -> String {
+    // Notes written before the first bar line belong to a measure, too:
+    let mut measures = score.measures.clone();
+    let measure_count = score
+        .parts
+        .iter()
+        .filter_map(|part| part.measures.keys().last())
+        .map(|index| index + 1)
+        .max()
+        .unwrap_or(0);
+    while measures.len() < measure_count {
+        measures.push(WrittenMeasure {
+            time_signature: measures
+                .last()
+                .map_or(DEFAULT_TIME_SIGNATURE, |measure| measure.time_signature),
+            ..WrittenMeasure::default()
+        });
+    }
+
+    let first = measures.first();
+    let (numerator, denominator) =
+        first.map_or(DEFAULT_TIME_SIGNATURE, |measure| measure.time_signature);
+    let mut abc = String::from("X:1\n");
+    if let Some(ref title) = score.title {
+        let _ = writeln!(abc, "T:{}", title);
+    }
+    let _ = writeln!(abc, "M:{}/{}", numerator, denominator);
+    let _ = writeln!(abc, "L:1/{}", UNIT_LENGTH);
+    if let Some(bpm) = first.and_then(|measure| measure.tempo) {
+        let _ = writeln!(abc, "Q:1/{}={}", denominator, bpm);
+    }
+    for (index, part) in score.parts.iter().enumerate() {
+        let voice = part.voice.as_deref().unwrap_or(DEFAULT_VOICE);
+        let _ = writeln!(abc, "V:{} name=\"{}\"", index + 1, voice);
+    }
+    abc.push_str("K:C\n");
+
+    // Each checkpoint block is a line of the tune for every voice:
+    let mut block_starts: Vec<usize> = (0..measures.len())
+        .filter(|&index| index == 0 || measures[index].new_system)
+        .collect();
+    block_starts.push(measures.len());
+    for block in block_starts.windows(2) {
+        for (part_index, part) in score.parts.iter().enumerate() {
+            write_line(&mut abc, &measures, part, part_index, block[0]..block[1]);
+        }
+    }
+
+    abc
+}
+
+// Writes the measures of one line of music as a line of the tune, with a
+// `w:` line under it if any of its notes have lyrics.
+fn write_line(
+    abc: &mut String,
+    measures: &[WrittenMeasure],
+    part: &WrittenPart,
+    part_index: usize,
+    block: std::ops::Range<usize>,
+) {
+    let mut line = format!("[V:{}] ", part_index + 1);
+    let mut syllables = Vec::new();
+
+    for index in block.clone() {
+        let measure = &measures[index];
+        let previous = index.checked_sub(1).map(|index| &measures[index]);
+
+        // The bar line before the measure ends the previous one:
+        let mut bar = match previous {
+            Some(previous) if index > block.start => {
+                match (previous.repeat_end, measure.repeat_start) {
+                    (true, true) => ":|:",
+                    (true, false) => ":|",
+                    (false, true) => "|:",
+                    (false, false) => "|",
+                }
+            }
+            _ if measure.repeat_start => "|:",
+            _ => "",
+        }
+        .to_string();
+        if let Some(number) = measure.ending {
+            if bar.is_empty() {
+                bar.push('[');
+            }
+            bar.push_str(&number.to_string());
+        }
+        if !bar.is_empty() {
+            line.push_str(&bar);
+            line.push(' ');
+        }
+
+        if previous.is_some_and(|previous| previous.time_signature != measure.time_signature) {
+            let (numerator, denominator) = measure.time_signature;
+            let _ = write!(line, "[M:{}/{}] ", numerator, denominator);
+        }
+        // Tempo marks and signs are written once, in the top voice:
+        let mut decorations = String::new();
+        if part_index == 0 {
+            if let Some(bpm) = measure.tempo.filter(|_| index > 0) {
+                let _ = write!(line, "[Q:1/{}={}] ", measure.time_signature.1, bpm);
+            }
+            for sign in &measure.signs {
+                decorations.push_str(&sign_decoration(sign));
+            }
+        }
+
+        let notes = part.measures.get(&index).map_or(&[][..], Vec::as_slice);
+        if notes.is_empty() {
+            let (numerator, denominator) = measure.time_signature;
+            let length = format_length(numerator * UNIT_LENGTH, denominator);
+            let _ = write!(line, "{}z{} ", decorations, length);
+            continue;
+        }
+
+        let mut accidentals = HashMap::new(); // Accidentals in effect in the measure
+        let mut graces = String::new();
+        for note in notes {
+            let mut text = decorations.clone();
+            decorations.clear();
+            write_note(&mut text, note, &mut accidentals);
+            if note.grace {
+                graces.push_str(&text);
+                continue;
+            }
+            if !graces.is_empty() {
+                let _ = write!(line, "{{{}}}", graces);
+                graces.clear();
+            }
+            line.push_str(&text);
+            line.push(' ');
+
+            if note.pitch.is_some() {
+                syllables.push(note.lyric.as_ref().map(|lyric| {
+                    let text = lyric.text.replace(HYPHEN, "\\-").replace(' ', "~");
+                    match lyric.continues_into {
+                        true => format!("{}{}", text, HYPHEN),
+                        false => format!("{} ", text),
+                    }
+                }));
+            }
+        }
+    }
+
+    let last = &measures[block.end - 1];
+    line.push_str(match last.repeat_end {
+        true => ":|",
+        false if block.end == measures.len() => "|]",
+        false => "|",
+    });
+    abc.push_str(&line);
+    abc.push('\n');
+
+    if syllables.iter().any(Option::is_some) {
+        let lyrics: String = syllables
+            .iter()
+            .map(|syllable| syllable.clone().unwrap_or_else(|| "* ".to_string()))
+            .collect();
+        let _ = writeln!(abc, "w:{}", lyrics.trim_end_matches(['*', ' ']));
+    }
+}
+
+// Writes a note with its decorations, accidentals, octave, and length.
+fn write_note(text: &mut String, note: &WrittenNote, accidentals: &mut HashMap<(char, i32), i32>) {
+    let marks = note.marks.replace(GLIDE_MARK, "");
+    for (mark, decoration) in [
+        (TURN_MARK, "!turn!"),
+        (MORDENT_MARK, "!mordent!"),
+        (TRILL_MARK, "!trill!"),
+    ] {
+        if marks.contains(mark) {
+            text.push_str(decoration);
+            break;
+        }
+    }
+    for (mark, decoration) in [
+        (FERMATA_MARK, "!fermata!"),
+        (ACCENT_MARK, "!accent!"),
+        (TENUTO_MARK, "!tenuto!"),
+        (STACCATO_MARK, "."),
+    ] {
+        if marks.contains(mark) {
+            text.push_str(decoration);
+        }
+    }
+
+    match note.pitch {
+        None => text.push('z'),
+        Some(pitch) => {
+            // Accidentals last until the end of the measure:
+            let key = (pitch.letter, pitch.octave);
+            if accidentals.get(&key).copied().unwrap_or(0) != pitch.accidentals {
+                text.push_str(&match pitch.accidentals {
+                    0 => "=".to_string(),
+                    sharps if sharps > 0 => "^".repeat(sharps as usize),
+                    flats => "_".repeat(flats.unsigned_abs() as usize),
+                });
+                accidentals.insert(key, pitch.accidentals);
+            }
+
+            if pitch.octave > MIDDLE_OCTAVE {
+                text.push(pitch.letter.to_ascii_lowercase());
+                text.push_str(&"'".repeat((pitch.octave - MIDDLE_OCTAVE - 1) as usize));
+            } else {
+                text.push(pitch.letter.to_ascii_uppercase());
+                text.push_str(&",".repeat((MIDDLE_OCTAVE - pitch.octave) as usize));
+            }
+        }
+    }
+
+    // A dotted length is 2 - 1/2^dots times as long as the written length:
+    let (numerator, denominator) = note.length;
+    let dotted = (1 << (note.dots + 1)) - 1;
+    text.push_str(&format_length(
+        numerator * UNIT_LENGTH * dotted,
+        denominator << note.dots,
+    ));
+}
+
+// Writes a sign as an ABC decoration, or as text above the staff.
+fn sign_decoration(sign: &str) -> String {
+    match sign {
+        "segno" | "coda" | "fine" | "D.C." | "D.S." => format!("!{}!", sign),
+        _ => format!("\"^{}\"", sign.replace('"', "'")),
+    }
+}
+
+// Formats a number of unit note lengths, e.g. `3`, `/`, or `3/2`.
+fn format_length(numerator: u32, denominator: u32) -> String {
+    let divisor = gcd(numerator, denominator);
+    match (numerator / divisor, denominator / divisor) {
+        (1, 1) => String::new(),
+        (numerator, 1) => numerator.to_string(),
+        (1, 2) => "/".to_string(),
+        (1, denominator) => format!("/{}", denominator),
+        (numerator, denominator) => format!("{}/{}", numerator, denominator),
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a.max(1) } else { gcd(b, a % b) }
+}
+
+/// Reads the first tune of an ABC file into a score that can be written as
+/// Hum text.
+///
+/// The `T:` title, `M:` meter, `L:` unit note length, `Q:` tempo, and `K:`
+/// key are read from the header, and can change in the body or in inline
+/// fields. Notes are spelled with the key signature and the accidentals
+/// earlier in their measure. Each `V:` voice becomes a part named after it,
+/// and each line of the first voice starts a new checkpoint block. Repeats
+/// and numbered endings are kept, tied notes are joined, chords become
+/// overlapping notes, and the first `w:` line under a line of music holds its
+/// lyrics. Decorations, chord symbols, and grace notes are left out.
+///
+/// # Arguments
+///
+/// * `text` - The contents of the ABC file.
+///
+/// # Returns
+///
+/// A `Result` containing the `ImportedScore` or an error message.
+pub fn decode(text: &str) -> Result<ImportedScore, String> {
+    let mut reader = TuneReader::default();
+    let mut in_body = false;
+
+    for (index, line) in text.lines().enumerate() {
+        let with_line = |message: String| format!("{} (line {})", message, index + 1);
+        if line.trim().is_empty() && in_body {
+            break; // A blank line ends the tune
+        }
+        // Comments (and stylesheet directives) run to the end of the line:
+        let line = line.split('%').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match field(line) {
+            Some(('X', _)) if in_body => break,
+            Some(('K', value)) => {
+                reader.read_key(value).map_err(with_line)?;
+                // The unit note length defaults to one that suits the meter
+                // of the header:
+                reader.unit_length = Some(reader.unit_length());
+                in_body = true;
+            }
+            Some(('w', value)) if in_body => reader.read_lyrics(value),
+            Some((name, value)) => reader.read_field(name, value).map_err(with_line)?,
+            None if in_body => reader.read_music(line).map_err(with_line)?,
+            None => {} // Free text in the header
+        }
+    }
+
+    if !in_body {
+        return Err("The ABC tune has no K: field".to_string());
+    }
+    Ok(reader.into_score())
+}
+
+// Splits a field line like `T:Title` into its name and value.
+fn field(line: &str) -> Option<(char, &str)> {
+    let mut chars = line.chars();
+    let name = chars.next().filter(char::is_ascii_alphabetic)?;
+    let value = chars.as_str().strip_prefix(':')?;
+    Some((name, value.trim()))
+}
+
+// A voice of the tune being read.
+#[derive(Default)]
+struct VoiceReader {
+    id: String,
+    name: String,
+    notes: Vec<ImportedNote>,
+    measure_start: u32,
+    measure_index: usize,
+    position: u32,                          // Units from the start of the measure
+    accidentals: HashMap<(char, i32), i32>, // Accidentals in effect in the measure
+    ties: Vec<usize>,                       // Notes tied to the next note
+    line_notes: Vec<usize>,                 // Notes of the last line that take lyrics
+}
+
+// The notes that a note, rest, or chord started, and how long it is.
+#[derive(Default)]
+struct Event {
+    notes: Vec<usize>,
+    length: u32,
+}
+
+// A pitch written in a note, before the key signature is applied.
+struct WrittenPitch {
+    letter: char,
+    octave: i32,
+    accidentals: Option<i32>,
+}
+
+#[derive(Default)]
+struct TuneReader {
+    score: ImportedScore,
+    voices: Vec<VoiceReader>,
+    voice: usize,
+    time_signature: Option<(u32, u32)>,
+    unit_length: Option<u32>,
+    key: [i32; 7], // The accidentals of the key signature, from A to G
+    last_event: Option<Event>,
+    broken_rhythm: Option<(u32, u32)>, // The factor of the next note's length
+    tuplet: Option<(u32, u32, u32)>,   // Notes left, and the factor of their lengths
+}
+
+impl TuneReader {
+    fn read_field(&mut self, name: char, value: &str) -> Result<(), String> {
+        match name {
+            'T' if self.score.title.is_none() => self.score.title = Some(value.to_string()),
+            'M' => {
+                let time_signature = parse_meter(value)?;
+                let time = self.voices.get(self.voice).map_or(0, |v| v.measure_start);
+                self.score.time_signatures.push((time, time_signature));
+                self.time_signature = Some(time_signature);
+            }
+            'L' => {
+                let (numerator, denominator) = parse_fraction(value)
+                    .ok_or_else(|| format!("Invalid ABC unit note length: {}", value))?;
+                self.unit_length = Some(UNITS_PER_WHOLE * numerator / denominator);
+            }
+            'Q' => {
+                let tempo = self.parse_tempo(value)?;
+                let time = self
+                    .voices
+                    .get(self.voice)
+                    .map_or(0, |v| v.measure_start + v.position);
+                self.score.tempos.push((time, tempo));
+            }
+            'V' => self.select_voice(value),
+            'K' => self.read_key(value)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn time_signature(&self) -> (u32, u32) {
+        self.time_signature.unwrap_or(DEFAULT_TIME_SIGNATURE)
+    }
+
+    fn measure_length(&self) -> u32 {
+        let (numerator, denominator) = self.time_signature();
+        (numerator * UNITS_PER_WHOLE / denominator).max(1)
+    }
+
+    // The unit note length defaults to a sixteenth note in meters shorter
+    // than 3/4, and to an eighth note otherwise.
+    fn unit_length(&self) -> u32 {
+        let (numerator, denominator) = self.time_signature();
+        self.unit_length
+            .unwrap_or(match 4 * numerator < 3 * denominator {
+                true => UNITS_PER_WHOLE / 16,
+                false => UNITS_PER_WHOLE / 8,
+            })
+    }
+
+    // Reads a tempo like `1/4=120`, `"Allegro" 3/8=60`, or `120` (in unit
+    // note lengths per minute) as quarter notes per minute.
+    fn parse_tempo(&self, value: &str) -> Result<f32, String> {
+        let invalid = || format!("Invalid ABC tempo: {}", value);
+        let text: String = value.split('"').step_by(2).collect();
+        let (beat, bpm) = match text.split_once('=') {
+            Some((beat, bpm)) => {
+                let beat = beat
+                    .split_whitespace()
+                    .map(|fraction| parse_fraction(fraction).map(|(n, d)| n as f32 / d as f32))
+                    .sum::<Option<f32>>()
+                    .ok_or_else(invalid)?;
+                (beat, bpm)
+            }
+            None => (
+                self.unit_length() as f32 / UNITS_PER_WHOLE as f32,
+                &text[..],
+            ),
+        };
+        let bpm = bpm.trim().parse::<f32>().map_err(|_| invalid())?;
+        Some(bpm * beat * QUARTERS_PER_WHOLE as f32)
+            .filter(|tempo| *tempo > 0.0)
+            .ok_or_else(invalid)
+    }
+
+    // Reads a key like `G`, `F#m`, `Bb mix`, or `D exp ^f`.
+    fn read_key(&mut self, value: &str) -> Result<(), String> {
+        let invalid = || format!("Invalid ABC key: {}", value);
+        let mut words = value.split_whitespace().peekable();
+        self.key = [0; 7];
+
+        let tonic = words.next().unwrap_or("none");
+        if !tonic.eq_ignore_ascii_case("none") && tonic != "HP" && !tonic.contains('=') {
+            let mut chars = tonic.chars();
+            let letter = chars.next().ok_or_else(invalid)?;
+            let mut fifths = match letter.to_ascii_uppercase() {
+                'F' => -1,
+                'C' => 0,
+                'G' => 1,
+                'D' => 2,
+                'A' => 3,
+                'E' => 4,
+                'B' => 5,
+                'H' if tonic == "Hp" => 2, // Highland bagpipe music
+                _ => return Err(invalid()),
+            };
+            let mut mode = chars.as_str();
+            if let Some(rest) = mode.strip_prefix('#') {
+                fifths += 7;
+                mode = rest;
+            } else if let Some(rest) = mode.strip_prefix('b') {
+                fifths -= 7;
+                mode = rest;
+            }
+            // The mode may be written apart from the tonic:
+            if mode.is_empty() {
+                let next = words.peek().filter(|word| !word.contains('='));
+                if let Some(word) = next.filter(|word| word.starts_with(char::is_alphabetic)) {
+                    mode = word;
+                    words.next();
+                }
+            }
+            let mode = mode.to_ascii_lowercase();
+            fifths += match mode.get(..3).unwrap_or(&mode) {
+                "" | "maj" | "ion" | "exp" => 0,
+                "m" | "min" | "aeo" => -3,
+                "mix" => -1,
+                "dor" => -2,
+                "phr" => -4,
+                "lyd" => 1,
+                "loc" => -5,
+                _ if tonic.starts_with('H') => 0,
+                _ => return Err(invalid()),
+            };
+
+            let fifths: i32 = fifths.clamp(-7, 7);
+            let (order, accidental) = if fifths > 0 { (SHARPS, 1) } else { (FLATS, -1) };
+            for letter in order.chars().take(fifths.unsigned_abs() as usize) {
+                self.key[letter_index(letter)] = accidental;
+            }
+        }
+
+        // Accidentals can be added to the key signature, as in `D exp ^f`:
+        for word in words {
+            let chars: Vec<char> = word.chars().collect();
+            let mut index = 0;
+            let pitch = read_pitch(&chars, &mut index);
+            if let Some(pitch) = pitch.filter(|pitch| pitch.accidentals.is_some()) {
+                self.key[letter_index(pitch.letter)] = pitch.accidentals.unwrap_or(0);
+            }
+        }
+        Ok(())
+    }
+
+    // Switches to a voice, adding it if it is new. Its name comes from a
+    // `name=` or `nm=` attribute, and otherwise from its ID.
+    fn select_voice(&mut self, value: &str) {
+        let id = value.split_whitespace().next().unwrap_or_default();
+        let name = ["name=", "nm="].iter().find_map(|attribute| {
+            let start = value.find(attribute)? + attribute.len();
+            let rest = &value[start..];
+            Some(match rest.strip_prefix('"') {
+                Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+                None => rest.split_whitespace().next().unwrap_or_default(),
+            })
+        });
+
+        self.voice = match self.voices.iter().position(|voice| voice.id == id) {
+            Some(index) => index,
+            // Notes written before the first voice belong to it:
+            None if self.voices.len() == 1
+                && self.voices[0].id.is_empty()
+                && (self.voices[0].notes.is_empty() || id == "1") =>
+            {
+                self.voices[0].id = id.to_string();
+                0
+            }
+            None => {
+                self.voices.push(VoiceReader {
+                    id: id.to_string(),
+                    name: id.to_string(),
+                    ..VoiceReader::default()
+                });
+                self.voices.len() - 1
+            }
+        };
+        if let Some(name) = name {
+            self.voices[self.voice].name = name.to_string();
+        }
+        self.last_event = None;
+    }
+
+    fn current_voice(&mut self) -> &mut VoiceReader {
+        if self.voices.is_empty() {
+            self.voices.push(VoiceReader::default());
+        }
+        &mut self.voices[self.voice]
+    }
+
+    // Reads a line of music, ending the block at the end of the line.
+    fn read_music(&mut self, line: &str) -> Result<(), String> {
+        for voice in &mut self.voices {
+            voice.line_notes.clear();
+        }
+
+        let (line, continues) = match line.strip_suffix('\\') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        let chars: Vec<char> = line.chars().collect();
+        let mut index = 0;
+
+        while let Some(&c) = chars.get(index) {
+            let next = chars.get(index + 1).copied();
+            match c {
+                '"' => index = skip_past(&chars, index + 1, '"'),
+                '!' | '+' => index = skip_past(&chars, index + 1, c),
+                '{' => index = skip_past(&chars, index + 1, '}'),
+                '[' if next.is_some_and(|c| c.is_ascii_alphabetic())
+                    && chars.get(index + 2) == Some(&':') =>
+                {
+                    let end = skip_past(&chars, index, ']');
+                    let text: String = chars[index + 1..end - 1].iter().collect();
+                    if let Some((name, value)) = field(&text) {
+                        self.read_field(name, value)?;
+                    }
+                    index = end;
+                }
+                '[' if next.is_some_and(|c| c.is_ascii_digit()) => {
+                    index += 1;
+                    let ending = read_number(&chars, &mut index);
+                    self.read_bar("", ending);
+                }
+                '|' | ':' | '[' if c != '[' || next == Some('|') => {
+                    let start = index;
+                    while chars.get(index).is_some_and(|c| "|:]".contains(*c))
+                        || (chars.get(index) == Some(&'[') && chars.get(index + 1) == Some(&'|'))
+                    {
+                        index += 1;
+                    }
+                    let bar: String = chars[start..index].iter().collect();
+                    if chars.get(index) == Some(&'[') {
+                        index += 1;
+                    }
+                    let ending = read_number(&chars, &mut index);
+                    self.read_bar(&bar, ending);
+                }
+                '[' => {
+                    index += 1;
+                    self.read_chord(&chars, &mut index)?;
+                }
+                '(' if next.is_some_and(|c| c.is_ascii_digit()) => {
+                    index += 1;
+                    self.read_tuplet(&chars, &mut index);
+                }
+                '-' => {
+                    index += 1;
+                    let tied = self.last_event.as_ref().map(|event| event.notes.clone());
+                    self.current_voice().ties = tied.unwrap_or_default();
+                }
+                '>' | '<' => {
+                    let start = index;
+                    while chars.get(index) == Some(&c) {
+                        index += 1;
+                    }
+                    self.read_broken_rhythm(c == '>', (index - start) as u32);
+                }
+                '^' | '_' | '=' | 'A'..='G' | 'a'..='g' => {
+                    let pitch = read_pitch(&chars, &mut index)
+                        .ok_or_else(|| format!("Invalid ABC note at column {}", index + 1))?;
+                    let length = read_length(&chars, &mut index);
+                    let pitch = self.spell(pitch);
+                    self.add_event(&[pitch], length);
+                }
+                'z' | 'x' => {
+                    index += 1;
+                    let length = read_length(&chars, &mut index);
+                    self.add_event(&[], length);
+                }
+                'Z' | 'X' => {
+                    // A multi-measure rest
+                    index += 1;
+                    let measures = read_number(&chars, &mut index).unwrap_or(1);
+                    let length = (self.measure_length() * measures, self.unit_length());
+                    self.add_event(&[], length);
+                }
+                _ => index += 1, // Spaces, slurs, and decorations like `.` and `~`
+            }
+        }
+
+        let voice = self.voice;
+        let measure_index = self.current_voice().measure_index;
+        let at_bar = self.current_voice().position == 0;
+        if voice == 0 && !continues && at_bar && measure_index > 0 {
+            self.score.system_breaks.push(measure_index);
+        }
+        Ok(())
+    }
+
+    // Reads the notes of a chord after its `[`, and its length after the `]`.
+    fn read_chord(&mut self, chars: &[char], index: &mut usize) -> Result<(), String> {
+        let mut pitches = Vec::new();
+        let mut length = None;
+        let mut tied = false;
+
+        while let Some(&c) = chars.get(*index) {
+            match c {
+                ']' => break,
+                '-' => {
+                    tied = true;
+                    *index += 1;
+                }
+                '^' | '_' | '=' | 'A'..='G' | 'a'..='g' => {
+                    let pitch = read_pitch(chars, index)
+                        .ok_or_else(|| format!("Invalid ABC note at column {}", *index + 1))?;
+                    let note_length = read_length(chars, index);
+                    length.get_or_insert(note_length); // The first note sets the length
+                    pitches.push(self.spell(pitch));
+                }
+                _ => *index += 1,
+            }
+        }
+
+        *index += 1;
+        let (numerator, denominator) = length.unwrap_or((1, 1));
+        let (outer_numerator, outer_denominator) = read_length(chars, index);
+        self.add_event(
+            &pitches,
+            (numerator * outer_numerator, denominator * outer_denominator),
+        );
+        if tied {
+            let tied = self.last_event.as_ref().map(|event| event.notes.clone());
+            self.current_voice().ties = tied.unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    // Reads a tuplet like `(3` or `(3:2:3` after its `(`. `(p` plays p notes
+    // in the time of 3 if p is 2, 4, or 8, in the time of 2 if p is 3 or 6,
+    // and otherwise in the time of 3 in compound meters and 2 in others.
+    fn read_tuplet(&mut self, chars: &[char], index: &mut usize) {
+        let notes = read_number(chars, index).unwrap_or(3).max(1);
+        let mut time = None;
+        let mut count = None;
+        if chars.get(*index) == Some(&':') {
+            *index += 1;
+            time = read_number(chars, index);
+            if chars.get(*index) == Some(&':') {
+                *index += 1;
+                count = read_number(chars, index);
+            }
+        }
+
+        let (numerator, _) = self.time_signature();
+        let compound = numerator % 3 == 0 && numerator > 3;
+        let time = time.unwrap_or(match notes {
+            2 | 4 | 8 => 3,
+            3 | 6 => 2,
+            _ if compound => 3,
+            _ => 2,
+        });
+        self.tuplet = Some((count.unwrap_or(notes), time, notes));
+    }
+
+    // Reads `>` (or `<`) between two notes, which lengthens the first by half
+    // (or a quarter for `>>`, and so on) and shortens the second to match.
+    fn read_broken_rhythm(&mut self, first_longer: bool, count: u32) {
+        let denominator = 1 << count;
+        let longer = (2 * denominator - 1, denominator);
+        let shorter = (1, denominator);
+        let (first, second) = match first_longer {
+            true => (longer, shorter),
+            false => (shorter, longer),
+        };
+
+        if let Some(event) = self.last_event.take() {
+            let length = event.length * first.0 / first.1;
+            let voice = self.current_voice();
+            for &note in &event.notes {
+                voice.notes[note].length = voice.notes[note].length + length - event.length;
+            }
+            voice.position = voice.position + length - event.length;
+            self.last_event = Some(Event { length, ..event });
+        }
+        self.broken_rhythm = Some(second);
+    }
+
+    // Spells a written pitch with the accidentals of the measure and the key.
+    fn spell(&mut self, pitch: WrittenPitch) -> String {
+        let key_accidentals = self.key[letter_index(pitch.letter)];
+        let voice = self.current_voice();
+        let key = (pitch.letter, pitch.octave);
+        let accidentals = match pitch.accidentals {
+            Some(accidentals) => {
+                voice.accidentals.insert(key, accidentals);
+                accidentals
+            }
+            None => voice
+                .accidentals
+                .get(&key)
+                .copied()
+                .unwrap_or(key_accidentals),
+        };
+
+        let spelling = match accidentals {
+            0 => "n".to_string(),
+            sharps if sharps > 0 => "s".repeat(sharps as usize),
+            flats => "f".repeat(flats.unsigned_abs() as usize),
+        };
+        format!("{}{}_{}", pitch.letter, spelling, pitch.octave)
+    }
+
+    // Adds a note, rest (with no pitches), or chord to the current voice.
+    fn add_event(&mut self, pitches: &[String], (numerator, denominator): (u32, u32)) {
+        let mut length = self.unit_length() as f32 * numerator as f32 / denominator.max(1) as f32;
+        if let Some((factor_numerator, factor_denominator)) = self.broken_rhythm.take() {
+            length = length * factor_numerator as f32 / factor_denominator as f32;
+        }
+        if let Some((notes, time, count)) = self.tuplet {
+            length = length * time as f32 / count as f32;
+            self.tuplet = (notes > 1).then_some((notes - 1, time, count));
+        }
+        let length = (length.round() as u32).max(1);
+
+        let voice = self.current_voice();
+        let start = voice.measure_start + voice.position;
+        let ties = std::mem::take(&mut voice.ties);
+        let mut event = Event {
+            notes: Vec::new(),
+            length,
+        };
+
+        for (index, pitch) in pitches.iter().enumerate() {
+            // A tied note lengthens the note that it continues:
+            let continued = ties.iter().copied().find(|&note| {
+                voice.notes[note].pitch == *pitch
+                    && voice.notes[note].start + voice.notes[note].length == start
+            });
+            match continued {
+                Some(note) => {
+                    voice.notes[note].length += length;
+                    event.notes.push(note);
+                }
+                None => {
+                    voice.notes.push(ImportedNote {
+                        start,
+                        length,
+                        pitch: pitch.clone(),
+                        lyric: None,
+                    });
+                    event.notes.push(voice.notes.len() - 1);
+                    // Lyrics are sung on the first note of a chord:
+                    if index == 0 {
+                        voice.line_notes.push(voice.notes.len() - 1);
+                    }
+                }
+            }
+        }
+
+        voice.position += length;
+        self.last_event = Some(event);
+    }
+
+    // Ends the current measure at a bar line, unless it is empty, and keeps
+    // the repeats and endings of the first voice.
+    fn read_bar(&mut self, bar: &str, ending: Option<u32>) {
+        let measure_length = self.measure_length();
+        let first_voice = self.voice == 0;
+        let voice = self.current_voice();
+
+        if voice.position > 0 {
+            // A short first measure is a pickup, filled with rests before its notes:
+            if voice.measure_start == 0 && voice.position < measure_length {
+                let pickup = voice.position;
+                for note in &mut voice.notes {
+                    note.start += measure_length - pickup;
+                }
+                voice.position = measure_length;
+            }
+            // Multi-measure rests (and overfull measures) take several measures:
+            let measures = voice.position.div_ceil(measure_length);
+            voice.measure_start += measures * measure_length;
+            voice.measure_index += measures as usize;
+            voice.position = 0;
+        }
+        voice.accidentals.clear();
+        let measure_index = voice.measure_index;
+        self.last_event = None;
+
+        if !first_voice {
+            return;
+        }
+        let end = bar.find(['|', ']']).unwrap_or(bar.len());
+        let repeat_end = measure_index
+            .checked_sub(1)
+            .filter(|_| bar[..end].contains(':'));
+        if let Some(index) = repeat_end {
+            self.score.repeat_ends.push(index);
+        }
+        if bar[end..].contains(':') || bar == "::" {
+            self.score.repeat_starts.push(measure_index);
+        }
+        if let Some(number) = ending {
+            self.score.endings.push((measure_index, number));
+        }
+    }
+
+    // Reads a `w:` line, whose syllables are sung on the notes of the line of
+    // music above it. Only the first `w:` line under a line is kept.
+    fn read_lyrics(&mut self, value: &str) {
+        let voice = self.current_voice();
+        let syllables = split_syllables(value);
+        for (&note, syllable) in voice.line_notes.iter().zip(syllables) {
+            if let Some(syllable) = syllable {
+                voice.notes[note].lyric.get_or_insert(syllable);
+            }
+        }
+    }
+
+    fn into_score(self) -> ImportedScore {
+        let mut score = self.score;
+        score.parts = self
+            .voices
+            .into_iter()
+            .map(|voice| ImportedPart {
+                name: voice.name,
+                notes: voice.notes,
+            })
+            .collect();
+
+        score.tempos.sort_by_key(|(time, _)| *time);
+        score.tempos.dedup_by_key(|(time, _)| *time);
+        score.time_signatures.sort_by_key(|(time, _)| *time);
+        score.time_signatures.dedup_by_key(|(time, _)| *time);
+        score.system_breaks.dedup();
+        score
+    }
+}
+
+// Splits the syllables of a `w:` line, with `None` for each note that has no
+// syllable. Syllables are split by spaces and hyphens (a syllable that is
+// followed by a hyphen keeps it), `_` and `*` skip a note, and `~` joins
+// words on one note.
+fn split_syllables(text: &str) -> Vec<Option<String>> {
+    let mut syllables = Vec::new();
+    let mut syllable = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&HYPHEN) => {
+                syllable.push(HYPHEN);
+                chars.next();
+            }
+            '~' => syllable.push(' '),
+            ' ' | '\t' | '|' | HYPHEN | '_' | '*' => {
+                if !syllable.is_empty() {
+                    let hyphen = if c == HYPHEN { "-" } else { "" };
+                    syllables.push(Some(std::mem::take(&mut syllable) + hyphen));
+                } else if c == HYPHEN {
+                    syllables.push(None);
+                }
+                if c == '_' || c == '*' {
+                    syllables.push(None);
+                }
+            }
+            _ => syllable.push(c),
+        }
+    }
+
+    if !syllable.is_empty() {
+        syllables.push(Some(syllable));
+    }
+    syllables
+}
+
+// Reads a written pitch: its accidentals, letter, and octave marks.
+fn read_pitch(chars: &[char], index: &mut usize) -> Option<WrittenPitch> {
+    let mut accidentals = None;
+    while let Some(&c) = chars.get(*index) {
+        let change = match c {
+            '^' => 1,
+            '_' => -1,
+            '=' => 0,
+            _ => break,
+        };
+        accidentals = Some(accidentals.unwrap_or(0) + change);
+        *index += 1;
+    }
+
+    let letter = chars
+        .get(*index)
+        .copied()
+        .filter(|c| matches!(c, 'A'..='G' | 'a'..='g'))?;
+    *index += 1;
+    let mut octave = match letter.is_ascii_uppercase() {
+        true => MIDDLE_OCTAVE,
+        false => MIDDLE_OCTAVE + 1,
+    };
+    while let Some(&c) = chars.get(*index) {
+        match c {
+            '\'' => octave += 1,
+            ',' => octave -= 1,
+            _ => break,
+        }
+        *index += 1;
+    }
+
+    Some(WrittenPitch {
+        letter: letter.to_ascii_uppercase(),
+        octave,
+        accidentals,
+    })
+}
+
+// Reads a length like `2`, `/`, `//`, `3/2`, or `/4`, in unit note lengths.
+fn read_length(chars: &[char], index: &mut usize) -> (u32, u32) {
+    let numerator = read_number(chars, index).unwrap_or(1);
+    let mut denominator = 1;
+    while chars.get(*index) == Some(&'/') {
+        *index += 1;
+        denominator *= read_number(chars, index).unwrap_or(2).max(1);
+    }
+    (numerator, denominator)
+}
+
+fn read_number(chars: &[char], index: &mut usize) -> Option<u32> {
+    let start = *index;
+    while chars.get(*index).is_some_and(char::is_ascii_digit) {
+        *index += 1;
+    }
+    chars[start..*index].iter().collect::<String>().parse().ok()
+}
+
+// Returns the index just past the next `end` character, or the end of the line.
+fn skip_past(chars: &[char], start: usize, end: char) -> usize {
+    chars[start.min(chars.len())..]
+        .iter()
+        .position(|&c| c == end)
+        .map_or(chars.len(), |offset| start + offset + 1)
+}
+
+fn parse_fraction(text: &str) -> Option<(u32, u32)> {
+    let (numerator, denominator) = text.trim().split_once('/')?;
+    let numerator = numerator.trim().parse::<u32>().ok()?;
+    let denominator = denominator.trim().parse::<u32>().ok().filter(|d| *d > 0)?;
+    Some((numerator, denominator))
+}
+
+// Reads a meter like `3/4`, `C` (common time), `C|` (cut time), or `2+3/8`.
+fn parse_meter(value: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("Invalid ABC meter: {}", value);
+    match value.split_whitespace().next().unwrap_or_default() {
+        "C" | "none" => Ok((4, 4)),
+        "C|" => Ok((2, 2)),
+        meter => {
+            let (beats, beat_type) = meter.split_once('/').ok_or_else(invalid)?;
+            let beats = beats
+                .trim_matches(['(', ')'])
+                .split('+')
+                .map(|beats| beats.parse::<u32>())
+                .sum::<Result<u32, _>>()
+                .map_err(|_| invalid())?;
+            let beat_type = beat_type
+                .parse::<u32>()
+                .ok()
+                .filter(|beat_type| *beat_type > 0)
+                .ok_or_else(invalid)?;
+            Ok((beats, beat_type))
+        }
+    }
+}
+
+fn letter_index(letter: char) -> usize {
+    (letter.to_ascii_uppercase() as u8)
+        .saturating_sub(b'A')
+        .min(6) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_length() {
+        assert_eq!(format_length(1, 1), "");
+        assert_eq!(format_length(6, 1), "6");
+        assert_eq!(format_length(1, 2), "/");
+        assert_eq!(format_length(2, 8), "/4");
+        assert_eq!(format_length(3, 2), "3/2");
+        assert_eq!(format_length(8, 12), "2/3");
+    }
+
+    #[test]
+    fn test_read_length() {
+        let read = |text: &str| read_length(&text.chars().collect::<Vec<_>>(), &mut 0);
+        assert_eq!(read(""), (1, 1));
+        assert_eq!(read("3"), (3, 1));
+        assert_eq!(read("/"), (1, 2));
+        assert_eq!(read("//"), (1, 4));
+        assert_eq!(read("3/2"), (3, 2));
+        assert_eq!(read("/8"), (1, 8));
+    }
+
+    #[test]
+    fn test_split_syllables() {
+        assert_eq!(
+            split_syllables("Dai-sy, * give~me_ your\\-self"),
+            vec![
+                Some("Dai-".to_string()),
+                Some("sy,".to_string()),
+                None,
+                Some("give me".to_string()),
+                None,
+                Some("your-self".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_meter() {
+        assert_eq!(parse_meter("C"), Ok((4, 4)));
+        assert_eq!(parse_meter("C|"), Ok((2, 2)));
+        assert_eq!(parse_meter("2+3/8"), Ok((5, 8)));
+        assert!(parse_meter("3/0").is_err());
+        assert!(parse_meter("waltz").is_err());
+    }
+}
@@ -21,6 +21,7 @@ use super::utils::{
     CHECKPOINT_CHAR, RESET_CHAR, VOICE_PREFIX, construct_note_text, get_note_range_at_cursor,
     get_pitch_range_at_cursor, get_word_range_at_cursor, split_note_name_and_octave,
 };
+use crate::hum_process::hum_transpose::calculate_transposition;
 
 /// Add a character at the current cursor position and move the cursor forward.
 pub fn insert_char(state: &mut EditorState, c: char) {
@@ -166,7 +167,7 @@ pub fn insert_note(state: &mut EditorState, note_char: char) {
     let note_name = format!("{}n", note_char.to_uppercase());
     let note = format!(
         "{}  ",
-        construct_note_text(&note_name, state.current_octave.into(), &fraction, &dots)
+        construct_note_text(&note_name, state.current_octave, &fraction, &dots)
     );
 
     insert_snippet(state, &note);
@@ -225,53 +226,6 @@ pub fn transpose_note(state: &mut EditorState, delta: i32) {
     }
 }
 
-/// Calculates the new note name and octave after transposition.
-fn calculate_transposition(
-    note_name: &str,
-    octave: i32,
-    delta: i32,
-) -> Result<(String, i32), String> {
-    let sharps = crate::hum_process::hum_math::NOTES_SHARPS;
-    let flats = crate::hum_process::hum_math::NOTES_FLATS;
-
-    let (note_idx, use_sharps) = if let Some(idx) = sharps.iter().position(|&n| n == note_name) {
-        (idx as i32, true)
-    } else if let Some(idx) = flats.iter().position(|&n| n == note_name) {
-        (idx as i32, false)
-    } else {
-        return Err("Invalid note name".to_string());
-    };
-
-    let notes_per_octave = sharps.len() as i32;
-    if sharps.len() != flats.len() {
-        return Err("Inconsistent note name arrays".to_string());
-    }
-
-    let current_pitch_idx = octave * notes_per_octave + note_idx;
-    let new_pitch_idx = current_pitch_idx + delta;
-
-    if new_pitch_idx < 0 {
-        return Err("Transposition too low".to_string());
-    }
-
-    let new_octave = new_pitch_idx / notes_per_octave;
-    let lowest_octave = crate::hum_process::hum_math::LOWEST_OCTAVE as i32;
-    let highest_octave = crate::hum_process::hum_math::HIGHEST_OCTAVE as i32;
-
-    if new_octave < lowest_octave || new_octave > highest_octave {
-        return Err("Transposition out of octave range".to_string());
-    }
-
-    let new_note_idx = (new_pitch_idx % notes_per_octave) as usize;
-    let new_note_name = if use_sharps {
-        sharps[new_note_idx]
-    } else {
-        flats[new_note_idx]
-    };
-
-    Ok((format!("{}_{}", new_note_name, new_octave), new_octave))
-}
-
 /// Replaces the note text in the editor and updates the message.
 fn replace_note_at_cursor(state: &mut EditorState, start: usize, end: usize, new_note: &str) {
     state.text.remove(start..end);
@@ -321,55 +275,3 @@ pub fn insert_voice_command(state: &mut EditorState) {
     state.mode = super::Mode::Insert;
     state.message = "Enter voice name".to_string();
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_calculate_transposition_sharps() {
-        // Cn_4 + 1 semitone -> Cs_4
-        let (note, octave) = calculate_transposition("Cn", 4, 1).unwrap();
-        assert_eq!(note, "Cs_4");
-        assert_eq!(octave, 4);
-
-        // Cs_4 + 1 semitone -> Dn_4
-        let (note, octave) = calculate_transposition("Cs", 4, 1).unwrap();
-        assert_eq!(note, "Dn_4");
-        assert_eq!(octave, 4);
-    }
-
-    #[test]
-    fn test_calculate_transposition_flats() {
-        // Df_4 - 1 semitone -> Cn_4
-        let (note, octave) = calculate_transposition("Df", 4, -1).unwrap();
-        assert_eq!(note, "Cn_4");
-        assert_eq!(octave, 4);
-    }
-
-    #[test]
-    fn test_calculate_transposition_octave_crossing() {
-        // Bn_4 + 1 semitone -> Cn_5
-        let (note, octave) = calculate_transposition("Bn", 4, 1).unwrap();
-        assert_eq!(note, "Cn_5");
-        assert_eq!(octave, 5);
-
-        // Cn_4 - 1 semitone -> Bn_3
-        let (note, octave) = calculate_transposition("Cn", 4, -1).unwrap();
-        assert_eq!(note, "Bn_3");
-        assert_eq!(octave, 3);
-    }
-
-    #[test]
-    fn test_calculate_transposition_limits() {
-        // Test upper limit (assuming HIGHEST_OCTAVE is 7)
-        // Bn_7 + 1 -> Error
-        let result = calculate_transposition("Bn", 7, 1);
-        assert!(result.is_err());
-
-        // Test lower limit (assuming LOWEST_OCTAVE is 0)
-        // Cn_0 - 1 -> Error
-        let result = calculate_transposition("Cn", 0, -1);
-        assert!(result.is_err());
-    }
-}
@@ -16,8 +16,12 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-use crate::hum_editor::editor_state::utils::{CHECKPOINT_CHAR, COMMENT_CHAR, MEASURE_CHAR};
-use crate::hum_parse::hum_grammar;
+use crate::hum_editor::editor_state::utils::{
+    CHECKPOINT_CHAR, COMMENT_CHAR, MEASURE_CHAR, get_section_name,
+};
+use crate::hum_parse::{
+    GRACE_MARK, hum_grammar, is_grace_note, is_reserved_command, split_note_noun,
+};
 use ropey::Rope;
 
 const FLOAT_COMPARISON_TOLERANCE: f64 = 1e-6;
@@ -25,6 +29,7 @@ const MIN_CHECKPOINT_LINE_LENGTH: usize = 79;
 const MIN_NOTE_PADDING: usize = 2;
 const QUARTERS_PER_WHOLE_NOTE: f64 = 4.0;
 const SPACES_AROUND_DASHES: usize = 2;
+const SECTION_NAME_INDENT: usize = 3; // Asterisks before the name of a section
 
 /// Main entry point for formatting a text buffer.
 ///
@@ -95,6 +100,8 @@ fn identify_blocks(text: &Rope) -> Vec<(usize, usize)> {
 }
 
 /// Calculates the max line length and expands checkpoint lines to match.
+///
+/// Named checkpoints keep their section name, e.g. `*** verse *****...`.
 fn apply_checkpoint_expansion(lines: Vec<String>) -> String {
     // Calculate max line length (ignoring checkpoints)
     let max_len = lines
@@ -111,7 +118,10 @@ fn apply_checkpoint_expansion(lines: Vec<String>) -> String {
 
     for line in lines {
         if line.trim().starts_with(CHECKPOINT_CHAR) {
-            result.push_str(&checkpoint_line);
+            match get_section_name(&line) {
+                Some(name) => result.push_str(&format_section_line(&name, checkpoint_len)),
+                None => result.push_str(&checkpoint_line),
+            }
             result.push('\n');
         } else {
             result.push_str(&line);
@@ -121,6 +131,14 @@ fn apply_checkpoint_expansion(lines: Vec<String>) -> String {
     result
 }
 
+/// Formats a named checkpoint line, filling it with asterisks up to `length`.
+fn format_section_line(name: &str, length: usize) -> String {
+    let indent = CHECKPOINT_CHAR.to_string().repeat(SECTION_NAME_INDENT);
+    let label = format!("{} {} ", indent, name);
+    let fill = length.saturating_sub(label.len()).max(SECTION_NAME_INDENT);
+    format!("{}{}", label, CHECKPOINT_CHAR.to_string().repeat(fill))
+}
+
 // --- Layout Calculation ---
 
 #[derive(Debug, Clone)]
@@ -162,17 +180,28 @@ fn collect_block_events(text: &Rope, start: usize, end: usize) -> Vec<Vec<(f64,
         if let Ok(commands) = hum_grammar::score(&line) {
             let mut measure_idx: i32 = -1;
             let mut current_time = 0.0;
+            let mut grace_width = 0.0;
+            let mut in_motif = false;
 
             for (verb, noun) in commands {
-                if verb == "measure" {
+                if verb == "motif" || verb == "motif_end" {
+                    // Motif definitions are not part of the music in the block
+                    in_motif = verb == "motif";
+                } else if in_motif {
+                    continue;
+                } else if opens_measure(&verb) {
                     measure_idx += 1;
                     current_time = 0.0;
+                } else if is_grace_note(&noun) {
+                    // Grace notes are drawn in front of the note that they lead into
+                    grace_width += (format_note_token(&verb, &noun).len() + 1) as f64;
                 } else if !is_reserved_command(&verb) {
                     // It's a note
                     let quarters = parse_duration(&noun);
 
                     if quarters > 0.0 {
-                        let min_width = calculate_note_min_width(&verb, &noun);
+                        let min_width = calculate_note_min_width(&verb, &noun) + grace_width;
+                        grace_width = 0.0;
 
                         let idx = if measure_idx < 0 {
                             0
@@ -301,12 +330,24 @@ fn reconstruct_line(commands: Vec<(String, String)>, layouts: &[MeasureLayout])
     let mut result = String::new();
     let mut measure_idx: i32 = -1;
     let mut current_time = 0.0;
+    let mut grace_width = 0;
+    let mut in_motif = false;
     let cmd_len = commands.len();
 
     for (i, (verb, noun)) in commands.iter().enumerate() {
-        if is_reserved_command(verb) {
+        if verb == "motif" || verb == "motif_end" {
+            in_motif = verb == "motif";
+            result.push_str(&format_reserved_command(verb, noun));
+        } else if in_motif {
+            // The notes of a motif definition are written without alignment
+            if is_reserved_command(verb) {
+                result.push_str(&format_reserved_command(verb, noun));
+            } else {
+                result.push_str(&format!("{} ", format_note_token(verb, noun)));
+            }
+        } else if is_reserved_command(verb) {
             result.push_str(&format_reserved_command(verb, noun));
-            if verb == "measure" {
+            if opens_measure(verb) {
                 measure_idx += 1;
                 current_time = 0.0;
             }
@@ -316,6 +357,7 @@ fn reconstruct_line(commands: Vec<(String, String)>, layouts: &[MeasureLayout])
                 noun,
                 measure_idx,
                 &mut current_time,
+                &mut grace_width,
                 layouts,
                 i < cmd_len - 1,
             ));
@@ -329,6 +371,9 @@ fn reconstruct_line(commands: Vec<(String, String)>, layouts: &[MeasureLayout])
 fn format_reserved_command(verb: &str, noun: &str) -> String {
     match verb {
         "measure" => format!("{} ", MEASURE_CHAR),
+        "repeat_start" => format!("{}: ", MEASURE_CHAR),
+        "ending" => format!("{}{} ", MEASURE_CHAR, noun),
+        "repeat_end" => format!(":{} ", MEASURE_CHAR),
         "reset" => {
             if noun.is_empty() {
                 ";".to_string()
@@ -339,8 +384,18 @@ fn format_reserved_command(verb: &str, noun: &str) -> String {
         "voice" => format!("% {} ", noun),
         "tempo" => format!("[ {}_bpm ] ", noun),
         "time" => format!("[ {} ] ", noun),
+        "tuning" => format!("[ tuning {} ] ", noun),
+        "swing" => format!("[ swing {} ] ", noun),
+        "humanize" => format!("[ humanize {} ] ", noun),
+        "include" => format!("[ include \"{}\" ] ", noun),
+        "motif" => format!("{{ motif {} = ", noun),
+        "motif_end" => "} ".to_string(),
+        "motif_call" => format!("@{} ", noun),
+        "form" => format!("[ form {} ] ", noun),
+        "sign" => format!("[ {} ] ", noun),
         "comment" => format!("{} {}", COMMENT_CHAR, noun),
-        "checkpoint" => CHECKPOINT_CHAR.to_string(),
+        "checkpoint" if noun.is_empty() => CHECKPOINT_CHAR.to_string(),
+        "checkpoint" => format!("{0}{0}{0} {1} {0}{0}{0}", CHECKPOINT_CHAR, noun),
         _ => String::new(),
     }
 }
@@ -351,6 +406,7 @@ fn format_note_command(
     noun: &str,
     measure_idx: i32,
     current_time: &mut f64,
+    grace_width: &mut usize,
     layouts: &[MeasureLayout],
     has_next_command: bool,
 ) -> String {
@@ -358,11 +414,16 @@ fn format_note_command(
     let note_str = format_note_token(verb, noun);
 
     if quarters <= 0.0 {
+        // Grace notes take up space in front of the next note's column
+        if is_grace_note(noun) {
+            *grace_width += note_str.len() + 1;
+        }
         return format!("{} ", note_str);
     }
 
     let target_len =
-        calculate_target_length(measure_idx, *current_time, quarters, layouts, &note_str);
+        calculate_target_length(measure_idx, *current_time, quarters, layouts, &note_str)
+            .saturating_sub(std::mem::take(grace_width));
     *current_time += quarters;
 
     apply_padding(&note_str, target_len, has_next_command)
@@ -427,16 +488,20 @@ fn apply_padding(note_str: &str, target_len: usize, has_next_command: bool) -> S
 
 // --- Helpers ---
 
-/// Checks if a command verb is a reserved keyword (not a note).
-fn is_reserved_command(verb: &str) -> bool {
-    matches!(
-        verb,
-        "comment" | "tempo" | "time" | "checkpoint" | "voice" | "measure" | "reset"
-    )
+/// Checks if a command verb starts a new measure (a bar line).
+fn opens_measure(verb: &str) -> bool {
+    matches!(verb, "measure" | "repeat_start" | "ending")
 }
 
 /// Parses a duration string (e.g., "1/4+") into quarter notes.
+///
+/// Any marks that follow the duration in a note noun (e.g., "1/4+ />") are ignored.
+/// Grace notes take no time, so their duration is zero.
 fn parse_duration(duration_str: &str) -> f64 {
+    if is_grace_note(duration_str) {
+        return 0.0;
+    }
+    let (duration_str, _) = split_note_noun(duration_str);
     let pluses = duration_str.matches('+').count();
     let clean_duration = duration_str.replace("+", "");
 
@@ -472,12 +537,20 @@ fn calculate_note_min_width(verb: &str, noun: &str) -> f64 {
 
 /// Formats a note token string: `(Verb Noun)`.
 ///
-/// Handles the placement of dots/pluses outside the parentheses.
+/// Handles the placement of dots/pluses and marks (e.g., glides) outside the
+/// parentheses, and the "g" in front of grace notes.
 fn format_note_token(verb: &str, noun: &str) -> String {
-    let pluses = noun.matches('+').count();
-    let clean_noun = noun.replace("+", "");
+    let (duration, marks) = split_note_noun(noun);
+    let pluses = duration.matches('+').count();
+    let clean_duration = duration.replace("+", "");
     let plus_str = "+".repeat(pluses);
-    format!("({} {}){}", verb, clean_noun, plus_str)
+    match marks.strip_prefix(GRACE_MARK) {
+        Some(marks) => format!(
+            "{}({} {}){}{}",
+            GRACE_MARK, verb, clean_duration, plus_str, marks
+        ),
+        None => format!("({} {}){}{}", verb, clean_duration, plus_str, marks),
+    }
 }
 
 #[cfg(test)]
@@ -498,4 +571,62 @@ mod tests {
         // 1/4+++ = 1.875 quarter notes
         assert!((parse_duration("1/4+++") - 1.875).abs() < FLOAT_COMPARISON_TOLERANCE);
     }
+
+    #[test]
+    fn test_format_note_marks() {
+        assert_eq!(format_note_token("Cn_4", "1/4+ />"), "(Cn_4 1/4)+/>");
+        assert!((parse_duration("1/4+ />") - 1.5).abs() < FLOAT_COMPARISON_TOLERANCE);
+        assert_eq!(format_note_token("Cn_4", "1/8 .>^"), "(Cn_4 1/8).>^");
+        assert!((parse_duration("1/8 .>^") - 0.5).abs() < FLOAT_COMPARISON_TOLERANCE);
+        assert_eq!(format_note_token("Cn_4", "1/4 tr"), "(Cn_4 1/4)tr");
+    }
+
+    #[test]
+    fn test_format_form_signs() {
+        let mut text = Rope::from_str("|:(Cn_4 1/4) :||1 (Dn_4 1/4)[segno] ;\n");
+        format_buffer(&mut text);
+        assert_eq!(
+            text.to_string(),
+            "|: (Cn_4 1/4)  :| |1 (Dn_4 1/4)  [ segno ] ;\n"
+        );
+    }
+
+    #[test]
+    fn test_format_named_checkpoints() {
+        let mut text = Rope::from_str("* verse *\n| (Cn_4 1/4) ;\n*\n");
+        format_buffer(&mut text);
+        let formatted = text.to_string();
+        let lines: Vec<&str> = formatted.lines().collect();
+        assert!(lines[0].starts_with("*** verse ****"));
+        assert_eq!(lines[0].len(), MIN_CHECKPOINT_LINE_LENGTH);
+        assert_eq!(lines[2], "*".repeat(MIN_CHECKPOINT_LINE_LENGTH));
+    }
+
+    #[test]
+    fn test_format_motifs() {
+        // Motif definitions are kept compact and don't affect the alignment:
+        let mut text = Rope::from_str(
+            "{motif riff=(Cn_4 1/8)(En_4 1/8)}\n| (Cn_4 1/4) (Dn_4 1/4)\n| @riff+5 (Dn_4 1/4)\n",
+        );
+        format_buffer(&mut text);
+        let formatted = text.to_string();
+        let lines: Vec<&str> = formatted.lines().collect();
+        assert_eq!(lines[0], "{ motif riff = (Cn_4 1/8) (En_4 1/8) }");
+        assert!(lines[2].starts_with("| @riff+5 (Dn_4 1/4)"));
+    }
+
+    #[test]
+    fn test_format_grace_notes() {
+        assert_eq!(format_note_token("Dn_5", "1/32 g"), "g(Dn_5 1/32)");
+        assert_eq!(parse_duration("1/32 g"), 0.0);
+
+        // Grace notes sit in front of the next note without shifting the columns:
+        let mut text =
+            Rope::from_str("| (Cn_4 1/4) (Dn_4 1/4)\n| g(Bn_3 1/32) (Cn_4 1/4) (Dn_4 1/4)\n");
+        format_buffer(&mut text);
+        let formatted = text.to_string();
+        let lines: Vec<&str> = formatted.lines().collect();
+        assert_eq!(lines[0].find("(Dn_4"), lines[1].find("(Dn_4"));
+        assert!(lines[1].starts_with("| g(Bn_3 1/32) (Cn_4 1/4)"));
+    }
 }
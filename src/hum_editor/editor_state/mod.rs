@@ -16,7 +16,6 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-use crate::hum_process::hum_math::{HIGHEST_OCTAVE, LOWEST_OCTAVE};
 use ropey::Rope;
 use std::io;
 
@@ -29,7 +28,7 @@ pub mod utils;
 const PLAYER_ENV_VAR_NAME: &str = "HUM_PLAYER";
 const MACOS_DEFAULT_PLAYER: &str = "afplay";
 const LINUX_DEFAULT_PLAYER: &str = "aplay";
-const DEFAULT_OCTAVE: u8 = 4;
+const DEFAULT_OCTAVE: i32 = 4;
 const DEFAULT_DURATION: &str = "1/4";
 const TAB_STRING: &str = "    ";
 const MAX_UNDO_HISTORY: usize = 1000;
@@ -59,7 +58,7 @@ pub struct EditorState {
     /// A status message to display to the user.
     pub message: String,
     /// The current octave for note insertion.
-    pub current_octave: u8,
+    pub current_octave: i32,
     /// The current duration for note insertion.
     pub current_duration: String,
     /// The command used for audio playback.
@@ -321,20 +320,16 @@ impl EditorState {
         editing::append_reset_and_newline(self);
     }
 
-    /// Increments the current octave, clamping at HIGHEST_OCTAVE.
+    /// Increments the current octave.
     pub fn increment_octave(&mut self) {
-        if self.current_octave < HIGHEST_OCTAVE {
-            self.current_octave += 1;
-            self.message = format!("Octave: {}", self.current_octave);
-        }
+        self.current_octave += 1;
+        self.message = format!("Octave: {}", self.current_octave);
     }
 
-    /// Decrements the current octave, clamping at LOWEST_OCTAVE.
+    /// Decrements the current octave.
     pub fn decrement_octave(&mut self) {
-        if self.current_octave > LOWEST_OCTAVE {
-            self.current_octave -= 1;
-            self.message = format!("Octave: {}", self.current_octave);
-        }
+        self.current_octave -= 1;
+        self.message = format!("Octave: {}", self.current_octave);
     }
 
     /// Sets the current duration.
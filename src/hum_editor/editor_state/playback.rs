@@ -67,6 +67,9 @@ pub fn play_file(state: &mut EditorState) {
 }
 
 /// Plays the section starting from the last checkpoint before the cursor.
+///
+/// Playback stops at the end of the named section (e.g., `*** verse ***`) that
+/// the cursor is in, or at the end of the file if there are no later sections.
 pub fn play_from_cursor(state: &mut EditorState) {
     let checkpoint_line = find_last_checkpoint_line(state);
     let score_contents = if let Some(line_idx) = checkpoint_line {
@@ -77,12 +80,12 @@ pub fn play_from_cursor(state: &mut EditorState) {
         state.text.to_string()
     };
 
-    play_score_content(
-        state,
-        &score_contents,
-        SECTION_PREVIEW_FILENAME,
-        MSG_PLAYING_SECTION,
-    );
+    let message = match find_section_name(state) {
+        Some(name) => format!("Playing section {}...", name),
+        None => MSG_PLAYING_SECTION.to_string(),
+    };
+
+    play_score_content(state, &score_contents, SECTION_PREVIEW_FILENAME, &message);
 }
 
 /// Plays a single note for preview.
@@ -120,7 +123,13 @@ fn play_score_content(
     let wav_path = temp_dir.join(temp_filename);
     let wav_filename = wav_path.to_string_lossy().to_string();
 
-    match crate::convert_to_wav(score_contents, &wav_filename) {
+    // Resolve includes relative to the file being edited, if it has a name.
+    let result = match &state.filename {
+        Some(filename) => crate::convert_score_to_wav(score_contents, filename, &wav_filename),
+        None => crate::convert_to_wav(score_contents, &wav_filename),
+    };
+
+    match result {
         Ok(_) => {
             stop_playback(state);
             state.message = message.to_string();
@@ -150,20 +159,21 @@ fn find_last_checkpoint_line(state: &EditorState) -> Option<usize> {
     None
 }
 
-/// Extracts context (BPM, Time, Voice) from the start of the file up to `end_line`.
+/// Extracts context (BPM, Time, Voice, Tuning, Swing, Humanize, Motifs) from the
+/// start of the file up to `end_line`.
 ///
-/// Searches backwards from `end_line` to find the most recent definitions.
+/// Searches backwards from `end_line` to find the most recent definitions. All
+/// tuning lines are kept because each one only changes part of the tuning, and
+/// all motif definitions are kept because the section may call them.
 fn extract_context_header(state: &EditorState, end_line: usize) -> String {
     let mut context_lines = Vec::new();
     let mut found_bpm = false;
     let mut found_time = false;
     let mut found_voice = false;
+    let mut found_swing = false;
+    let mut found_humanize = false;
 
     for i in (0..end_line).rev() {
-        if found_bpm && found_time && found_voice {
-            break;
-        }
-
         let line = state.text.line(i).to_string();
         let trimmed = line.trim();
         let mut useful = false;
@@ -180,6 +190,17 @@ fn extract_context_header(state: &EditorState, end_line: usize) -> String {
             found_voice = true;
             useful = true;
         }
+        if !found_swing && utils::is_swing_line(trimmed) {
+            found_swing = true;
+            useful = true;
+        }
+        if !found_humanize && utils::is_humanize_line(trimmed) {
+            found_humanize = true;
+            useful = true;
+        }
+        if utils::is_tuning_line(trimmed) {
+            useful = true;
+        }
 
         if useful {
             context_lines.push(line);
@@ -187,11 +208,46 @@ fn extract_context_header(state: &EditorState, end_line: usize) -> String {
     }
 
     context_lines.reverse();
-    context_lines.join("")
+    format!(
+        "{}{}",
+        extract_motif_definitions(state, end_line),
+        context_lines.join("")
+    )
+}
+
+/// Extracts the lines of every motif definition before `end_line`.
+fn extract_motif_definitions(state: &EditorState, end_line: usize) -> String {
+    let mut definitions = String::new();
+    let mut in_motif = false;
+
+    for i in 0..end_line {
+        let line = state.text.line(i).to_string();
+        if utils::is_motif_start_line(&line) {
+            in_motif = true;
+        }
+        if in_motif {
+            definitions.push_str(&line);
+            in_motif = !line.contains(utils::MOTIF_END_CHAR);
+        }
+    }
+
+    definitions
+}
+
+/// Finds the name of the section that the cursor is in, if it is in one.
+fn find_section_name(state: &EditorState) -> Option<String> {
+    let line_idx = state.text.char_to_line(state.cursor_pos);
+    (0..=line_idx)
+        .rev()
+        .find_map(|i| utils::get_section_name(&state.text.line(i).to_string()))
 }
 
-/// Extracts the content of the section starting at `start_line`.
+/// Extracts the content of the section starting at `start_line`, up to the
+/// next named checkpoint.
 fn extract_section_content(state: &EditorState, start_line: usize) -> String {
     let start_char = state.text.line_to_char(start_line);
-    state.text.slice(start_char..).to_string()
+    let end_char = (start_line + 1..state.text.len_lines())
+        .find(|&i| utils::get_section_name(&state.text.line(i).to_string()).is_some())
+        .map_or(state.text.len_chars(), |i| state.text.line_to_char(i));
+    state.text.slice(start_char..end_char).to_string()
 }
@@ -17,6 +17,7 @@ along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
 use super::EditorState;
+use crate::hum_parse::hum_grammar;
 
 const PARSE_LOOKBACK_LIMIT: usize = 100;
 const DEFAULT_VOICE: &str = "sine";
@@ -28,11 +29,19 @@ pub const RESET_CHAR: char = ';';
 pub const NOTE_START_CHAR: char = '(';
 pub const NOTE_END_CHAR: char = ')';
 pub const NOTE_DOT_CHAR: char = '+';
+pub const NOTE_MARK_CHARS: &[char] = &['/', '>', '.', '_', '^'];
+pub const NOTE_ORNAMENTS: &[&str] = &["turn", "tr", "mord"];
+pub const GRACE_NOTE_CHAR: char = 'g';
 pub const NOTE_OCTAVE_SEPARATOR: char = '_';
 pub const TEMPO_SUFFIX: &str = "_bpm";
 pub const TIME_SIG_START: char = '[';
 pub const TIME_SIG_SEPARATOR: char = '/';
 pub const TIME_SIG_END: char = ']';
+pub const TUNING_KEYWORD: &str = "tuning";
+pub const SWING_KEYWORD: &str = "swing";
+pub const HUMANIZE_KEYWORD: &str = "humanize";
+pub const MOTIF_START_CHAR: char = '{';
+pub const MOTIF_END_CHAR: char = '}';
 pub const VOICE_PREFIX: &str = "% ";
 pub const DEFAULT_CHECKPOINT_LENGTH: usize = 71;
 
@@ -89,6 +98,16 @@ pub fn is_checkpoint_line(line: &str) -> bool {
     line.trim_start().starts_with(CHECKPOINT_CHAR)
 }
 
+/// Gets the section name of a checkpoint line like `*** verse ***`.
+///
+/// Returns `None` if the line is not a checkpoint or the checkpoint is unnamed.
+pub fn get_section_name(line: &str) -> Option<String> {
+    match hum_grammar::checkpoint(line.trim()) {
+        Ok((_, name)) if !name.is_empty() => Some(name),
+        _ => None,
+    }
+}
+
 /// Checks if a line is a tempo command (contains `_bpm`).
 pub fn is_tempo_line(line: &str) -> bool {
     line.contains(TEMPO_SUFFIX)
@@ -101,6 +120,33 @@ pub fn is_time_signature_line(line: &str) -> bool {
         && line.contains(TIME_SIG_END)
 }
 
+/// Checks if a line is a tuning command (e.g., `[ tuning A4=432 ]`).
+pub fn is_tuning_line(line: &str) -> bool {
+    is_keyword_line(line, TUNING_KEYWORD)
+}
+
+/// Checks if a line is a swing command (e.g., `[ swing 60% ]`).
+pub fn is_swing_line(line: &str) -> bool {
+    is_keyword_line(line, SWING_KEYWORD)
+}
+
+/// Checks if a line is a humanize command (e.g., `[ humanize timing=10ms ]`).
+pub fn is_humanize_line(line: &str) -> bool {
+    is_keyword_line(line, HUMANIZE_KEYWORD)
+}
+
+/// Checks if a line is a bracketed command starting with the given keyword.
+fn is_keyword_line(line: &str, keyword: &str) -> bool {
+    line.trim_start()
+        .strip_prefix(TIME_SIG_START)
+        .is_some_and(|rest| rest.trim_start().starts_with(keyword))
+}
+
+/// Checks if a line starts a motif definition (e.g., `{ motif riff = ...`).
+pub fn is_motif_start_line(line: &str) -> bool {
+    line.trim_start().starts_with(MOTIF_START_CHAR)
+}
+
 /// Checks if a line is a voice command (starts with `%`).
 pub fn is_voice_line(line: &str) -> bool {
     line.trim().starts_with(VOICE_PREFIX.trim())
@@ -117,7 +163,7 @@ pub fn construct_note_text(note_name: &str, octave: i32, duration: &str, dots: &
 /// Gets the range (start, end) of the note at the current cursor position.
 ///
 /// A note is defined as a sequence enclosed in parentheses `(...)`, potentially
-/// followed by dots `+`.
+/// followed by dots `+` and marks such as the glide `/>`.
 ///
 /// Returns `None` if the cursor is not currently inside or on a note.
 pub fn get_note_range_at_cursor(state: &EditorState) -> Option<(usize, usize)> {
@@ -157,8 +203,14 @@ pub fn get_note_range_at_cursor(state: &EditorState) -> Option<(usize, usize)> {
         return None;
     }
 
+    // Include the "g" in front of a grace note
+    let open = start;
+    if start > 0 && state.text.char(start - 1) == GRACE_NOTE_CHAR {
+        start -= 1;
+    }
+
     // Scan forwards for the end char of the note
-    let mut end = start;
+    let mut end = open;
     let mut found_close = false;
     i = 0;
 
@@ -176,12 +228,17 @@ pub fn get_note_range_at_cursor(state: &EditorState) -> Option<(usize, usize)> {
     }
 
     if found_open && found_close {
-        // Check for trailing dots
+        // Check for trailing dots and marks
         while end < len {
             let c = state.text.char(end);
 
-            if c == NOTE_DOT_CHAR {
+            if c == NOTE_DOT_CHAR || NOTE_MARK_CHARS.contains(&c) {
                 end += 1;
+            } else if let Some(ornament) = NOTE_ORNAMENTS
+                .iter()
+                .find(|ornament| text_starts_with(state, end, ornament))
+            {
+                end += ornament.chars().count();
             } else {
                 break;
             }
@@ -233,6 +290,15 @@ pub fn get_word_range_at_cursor(state: &EditorState) -> Option<(usize, usize)> {
     Some((start, end))
 }
 
+// Checks if the text at a character position starts with a pattern.
+fn text_starts_with(state: &EditorState, pos: usize, pattern: &str) -> bool {
+    let len = state.text.len_chars();
+    pattern
+        .chars()
+        .enumerate()
+        .all(|(i, c)| pos + i < len && state.text.char(pos + i) == c)
+}
+
 /// Gets the range (start, end) of the pitch part of a note at the cursor.
 ///
 /// The pitch part is the text inside the parentheses before the first space.
@@ -271,9 +337,10 @@ pub fn split_note_name_and_octave(note: &str) -> Option<(&str, i32)> {
     Some((note_part, octave))
 }
 
-/// Gets the name of the active voice at the current cursor position.
+/// Gets the active voice at the current cursor position.
 ///
-/// Scans backwards for the nearest `% voice_name` command. Defaults to "sine".
+/// Scans backwards for the nearest `% voice_name` command, keeping any voice
+/// parameters such as vibrato. Defaults to "sine".
 pub fn get_voice_at_cursor(state: &EditorState) -> String {
     let line_idx = state.text.char_to_line(state.cursor_pos);
 
@@ -282,10 +349,10 @@ pub fn get_voice_at_cursor(state: &EditorState) -> String {
         let line = state.text.line(i).to_string();
         let trimmed = line.trim();
         if trimmed.starts_with(VOICE_PREFIX.trim()) {
-            // Extract voice name: "% voice_name"
-            let parts: Vec<&str> = trimmed.split_whitespace().collect();
-            if parts.len() >= 2 {
-                return parts[1].to_string();
+            // Extract voice name and parameters: "% voice_name param=value"
+            let parts: Vec<&str> = trimmed.split_whitespace().skip(1).collect();
+            if !parts.is_empty() {
+                return parts.join(" ");
             }
         }
     }
@@ -16,32 +16,120 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use peg::str::LineCol;
 use std::error;
 use std::fmt;
+use std::io;
+use std::path::Path;
+
+// Where a command starts in a score: the file (if the score was read from one
+// or included), and the line and column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl SourceLocation {
+    pub fn new(file: Option<&str>, line_col: LineCol) -> SourceLocation {
+        SourceLocation {
+            file: file.map(String::from),
+            line: line_col.line,
+            column: line_col.column,
+        }
+    }
+
+    // Returns the directory that files named at this location are resolved
+    // relative to: the directory of the file, or the current directory.
+    pub(crate) fn dir(&self) -> &Path {
+        self.file
+            .as_deref()
+            .and_then(|file| Path::new(file).parent())
+            .unwrap_or(Path::new(""))
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.file {
+            Some(ref file) => write!(f, "{}:{}:{}", file, self.line, self.column),
+            None => write!(f, "{}:{}", self.line, self.column),
+        }
+    }
+}
 
 // Custom type for general Hum processing errors
 #[derive(Debug)]
 pub struct GenerateError {
     pub message: String,
+    // Where the command that caused the error starts in the score, if known.
+    pub location: Option<SourceLocation>,
 }
 
 impl fmt::Display for GenerateError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.message)
+        match self.location {
+            Some(ref location) => write!(f, "{} at {}", self.message, location),
+            None => write!(f, "{}", self.message),
+        }
     }
 }
 
 impl error::Error for GenerateError {}
 
+// Custom type for errors while resolving `[ include "..." ]` directives
+#[derive(Debug)]
+pub struct IncludeError {
+    pub message: String,
+    // Where the include directive (or the problem in the included file) is.
+    pub location: Option<SourceLocation>,
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.location {
+            Some(ref location) => write!(f, "{} at {}", self.message, location),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl error::Error for IncludeError {}
+
+// Custom type for errors while reading scores written in other formats
+#[derive(Debug)]
+pub struct ImportError {
+    pub message: String,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl error::Error for ImportError {}
+
 // Declaration of Peg ParseError (type specification required)
-pub type ParseError = peg::error::ParseError<peg::str::LineCol>;
+pub type ParseError = peg::error::ParseError<LineCol>;
 
 // Custom type for catch-all Hum errors.
 #[derive(Debug)]
 pub enum HumError {
+    FileReadError(io::Error),
     FileSaveError(hound::Error),
+    FileWriteError(io::Error),
     GenerateError(GenerateError),
     HumParseError(ParseError),
+    ImportError(ImportError),
+    IncludeError(IncludeError),
+}
+
+impl From<io::Error> for HumError {
+    fn from(err: io::Error) -> HumError {
+        HumError::FileReadError(err)
+    }
 }
 
 impl From<hound::Error> for HumError {
@@ -56,6 +144,18 @@ impl From<GenerateError> for HumError {
     }
 }
 
+impl From<ImportError> for HumError {
+    fn from(err: ImportError) -> HumError {
+        HumError::ImportError(err)
+    }
+}
+
+impl From<IncludeError> for HumError {
+    fn from(err: IncludeError) -> HumError {
+        HumError::IncludeError(err)
+    }
+}
+
 impl From<ParseError> for HumError {
     fn from(err: ParseError) -> HumError {
         HumError::HumParseError(err)
@@ -65,9 +165,13 @@ impl From<ParseError> for HumError {
 impl fmt::Display for HumError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
+            HumError::FileReadError(ref err) => write!(f, "FileReadError: {}", err),
             HumError::FileSaveError(ref err) => write!(f, "FileSaveError: {}", err),
+            HumError::FileWriteError(ref err) => write!(f, "FileWriteError: {}", err),
             HumError::GenerateError(ref err) => write!(f, "GenerateError: {}", err),
             HumError::HumParseError(ref err) => write!(f, "HumParseError: {}", err),
+            HumError::ImportError(ref err) => write!(f, "ImportError: {}", err),
+            HumError::IncludeError(ref err) => write!(f, "IncludeError: {}", err),
         }
     }
 }
@@ -75,9 +179,13 @@ impl fmt::Display for HumError {
 impl error::Error for HumError {
     fn cause(&self) -> Option<&dyn error::Error> {
         match *self {
+            HumError::FileReadError(ref err) => Some(err),
             HumError::FileSaveError(ref err) => Some(err),
+            HumError::FileWriteError(ref err) => Some(err),
             HumError::GenerateError(ref err) => Some(err),
             HumError::HumParseError(ref err) => Some(err),
+            HumError::ImportError(ref err) => Some(err),
+            HumError::IncludeError(ref err) => Some(err),
         }
     }
 }
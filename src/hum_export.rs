@@ -0,0 +1,357 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::BTreeMap;
+
+use crate::hum_error::{GenerateError, SourceLocation};
+use crate::hum_import::key_name;
+use crate::hum_parse::{GRACE_MARK, LocatedCommand, is_grace_note, split_note_noun};
+use crate::hum_process::hum_math::{Pitch, SEMITONES_PER_OCTAVE};
+use crate::hum_process::hum_tuning::{Tuning, split_cents_offset};
+
+const DEFAULT_TIME_SIGNATURE: (u32, u32) = (4, 4);
+const DEFAULT_BPM: u32 = 60;
+const DEFAULT_VOICE: &str = "sine";
+const REST_NAME: &str = "Rest";
+const COMMENT_CHAR: char = '~';
+const HYPHEN: char = '-';
+const DOT_CHAR: char = '+';
+const CONCERT_PITCH_KEY: f32 = 69.0; // MIDI key of An_4
+
+/// A note as it is written in a score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WrittenNote {
+    /// The spelled pitch, or `None` for rests.
+    pub pitch: Option<Pitch>,
+    /// The cents offset of the pitch.
+    pub cents: f32,
+    /// The written fraction of a whole note, without dots.
+    pub length: (u32, u32),
+    pub dots: u32,
+    /// The marks after the note, e.g. ">" for an accent.
+    pub marks: String,
+    pub grace: bool,
+    pub lyric: Option<Syllable>,
+    /// Where the note is written in the score.
+    pub location: SourceLocation,
+}
+
+/// A syllable of lyrics, and whether it continues from the previous syllable
+/// or into the next one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Syllable {
+    pub text: String,
+    pub continues_from: bool,
+    pub continues_into: bool,
+}
+
+/// A measure as it is written in a score.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WrittenMeasure {
+    pub time_signature: (u32, u32),
+    /// The tempo in beats per minute, if it is marked at the start of the
+    /// measure. Hum counts beats of the time signature's denominator.
+    pub tempo: Option<u32>,
+    /// Whether the measure starts a checkpoint block.
+    pub new_system: bool,
+    pub repeat_start: bool,
+    pub repeat_end: bool,
+    /// The number of the ending that starts with the measure, if any.
+    pub ending: Option<u32>,
+    /// Signs like "segno" and "D.C. al fine" in the measure.
+    pub signs: Vec<String>,
+}
+
+/// The notes of one line of music (counted from the top of each checkpoint
+/// block) in every block, by measure.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WrittenPart {
+    /// The voice that plays the first note of the line.
+    pub voice: Option<String>,
+    pub measures: BTreeMap<usize, Vec<WrittenNote>>,
+    lyric_continues: bool, // Whether the last syllable continues into the next one
+}
+
+/// A score as it is written, for engraving it in another format.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WrittenScore {
+    /// The first comment of the score, if it starts with one.
+    pub title: Option<String>,
+    pub measures: Vec<WrittenMeasure>,
+    pub parts: Vec<WrittenPart>,
+}
+
+/// Reads the commands of a score in the order that they are written, keeping
+/// track of measures the same way that rendering does.
+///
+/// Repeats, endings, and signs are kept as they are written rather than
+/// played out. A `~` comment line directly under a line of notes holds its
+/// lyrics: each `~` starts a syllable under the note that it lines up with,
+/// and a syllable that ends in `-` continues into the next one.
+///
+/// # Arguments
+///
+/// * `commands` - The located commands of the score, with includes and motifs
+///   already expanded.
+///
+/// # Returns
+///
+/// A `Result` containing the `WrittenScore` or a `GenerateError`.
+pub fn read_score(commands: &[LocatedCommand]) -> Result<WrittenScore, GenerateError> {
+    let mut score = WrittenScore::default();
+    let mut tuning = Tuning::default();
+    let mut time_signature = DEFAULT_TIME_SIGNATURE;
+    let mut bpm = DEFAULT_BPM;
+    let mut mark_tempo = true; // Whether the next new measure shows the tempo
+    let mut voice = DEFAULT_VOICE.to_string();
+    let mut measure_index: i32 = -1;
+    let mut measure_greatest: i32 = -1;
+    let mut checkpoint_index: i32 = 0;
+    let mut line_index = 0;
+    let mut previous_verb = "";
+
+    for (index, (location, (verb, noun))) in commands.iter().enumerate() {
+        let error = |message: String| GenerateError {
+            message,
+            location: Some(location.clone()),
+        };
+
+        match verb.as_str() {
+            "comment" if index == 0 => score.title = Some(noun.clone()),
+            "comment" if previous_verb == "reset" => {
+                if let Some(part) = score.parts.get_mut(line_index.max(1) - 1) {
+                    add_lyrics(part, location, noun);
+                }
+            }
+            "tempo" => {
+                bpm = noun
+                    .parse::<u32>()
+                    .map_err(|_| error(format!("Invalid tempo value: {}", noun)))?;
+                mark_tempo = true;
+            }
+            "time" => {
+                time_signature = parse_time_signature(noun).map_err(error)?;
+                mark_tempo = true; // The tempo counts beats of the new time signature
+            }
+            "tuning" => tuning
+                .apply_directive(noun, location.dir())
+                .map_err(error)?,
+            "voice" => {
+                voice = noun
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or(DEFAULT_VOICE)
+                    .to_string();
+            }
+            "checkpoint" => {
+                checkpoint_index = measure_greatest + 1;
+                measure_index = measure_greatest;
+                line_index = 0;
+            }
+            "reset" => {
+                measure_index = checkpoint_index - 1;
+                line_index += 1;
+            }
+            "measure" | "repeat_start" | "ending" => {
+                measure_index += 1;
+                measure_greatest = measure_greatest.max(measure_index);
+                if measure_index as usize == score.measures.len() {
+                    score.measures.push(WrittenMeasure {
+                        time_signature,
+                        tempo: mark_tempo.then_some(bpm),
+                        new_system: measure_index > 0 && measure_index == checkpoint_index,
+                        ..WrittenMeasure::default()
+                    });
+                    mark_tempo = false;
+                }
+
+                let measure = &mut score.measures[measure_index as usize];
+                match verb.as_str() {
+                    "repeat_start" => measure.repeat_start = true,
+                    "ending" => {
+                        let number = noun
+                            .parse::<u32>()
+                            .map_err(|_| error(format!("Invalid ending number: {}", noun)))?;
+                        measure.ending = Some(number);
+                    }
+                    _ => {}
+                }
+            }
+            "repeat_end" | "sign" => {
+                let measure = usize::try_from(measure_index)
+                    .ok()
+                    .and_then(|index| score.measures.get_mut(index))
+                    .ok_or_else(|| {
+                        error("Form signs must be written inside a measure".to_string())
+                    })?;
+                if verb == "repeat_end" {
+                    measure.repeat_end = true;
+                } else {
+                    measure.signs.push(noun.clone());
+                }
+            }
+            "include" | "motif" | "motif_end" | "motif_call" => {
+                return Err(error(
+                    "Includes and motifs must be expanded before exporting".to_string(),
+                ));
+            }
+            "comment" | "form" | "swing" | "humanize" => {}
+            _ => {
+                let note = read_note(&tuning, verb, noun, location).map_err(error)?;
+                while score.parts.len() <= line_index {
+                    score.parts.push(WrittenPart::default());
+                }
+                let part = &mut score.parts[line_index];
+                part.voice.get_or_insert_with(|| voice.clone());
+                part.measures
+                    .entry(measure_index.max(0) as usize)
+                    .or_default()
+                    .push(note);
+            }
+        }
+
+        previous_verb = verb;
+    }
+
+    Ok(score)
+}
+
+fn parse_time_signature(noun: &str) -> Result<(u32, u32), String> {
+    let (numerator, denominator) = noun
+        .split_once('/')
+        .ok_or_else(|| format!("Invalid time signature format: {}", noun))?;
+    let numerator = numerator
+        .parse::<u32>()
+        .map_err(|_| format!("Invalid time signature numerator: {}", numerator))?;
+    let denominator = denominator
+        .parse::<u32>()
+        .ok()
+        .filter(|denominator| *denominator > 0)
+        .ok_or_else(|| format!("Invalid time signature denominator: {}", denominator))?;
+    Ok((numerator, denominator))
+}
+
+fn read_note(
+    tuning: &Tuning,
+    name: &str,
+    noun: &str,
+    location: &SourceLocation,
+) -> Result<WrittenNote, String> {
+    let grace = is_grace_note(noun);
+    let (length, marks) = split_note_noun(noun);
+
+    let (pitch, cents) = if name == REST_NAME {
+        (None, 0.0)
+    } else {
+        let (pitch, cents) =
+            read_pitch(tuning, name).ok_or_else(|| format!("There is no note named {}", name))?;
+        (Some(pitch), cents)
+    };
+
+    let dots = length.matches(DOT_CHAR).count() as u32;
+    let length = length.trim_end_matches(DOT_CHAR);
+    let invalid_length = || format!("Invalid note length format: {}", length);
+    let (numerator, denominator) = length.split_once('/').ok_or_else(invalid_length)?;
+    let numerator = numerator.parse::<u32>().map_err(|_| invalid_length())?;
+    let denominator = denominator
+        .parse::<u32>()
+        .ok()
+        .filter(|denominator| *denominator > 0)
+        .ok_or_else(invalid_length)?;
+
+    Ok(WrittenNote {
+        pitch,
+        cents,
+        length: (numerator, denominator),
+        dots,
+        marks: marks.trim_start_matches(GRACE_MARK).to_string(),
+        grace,
+        lyric: None,
+        location: location.clone(),
+    })
+}
+
+// Spells a note name as a pitch and its cents offset. Frequencies and scale
+// steps are spelled as the nearest key of a twelve-tone keyboard.
+fn read_pitch(tuning: &Tuning, name: &str) -> Option<(Pitch, f32)> {
+    let (spelling, cents) = split_cents_offset(name)?;
+    if let Some(pitch) = Pitch::parse(spelling) {
+        return Some((pitch, cents));
+    }
+
+    let frequency = tuning.note_frequency(name).filter(|f| f.is_finite())?;
+    let key =
+        CONCERT_PITCH_KEY + SEMITONES_PER_OCTAVE as f32 * (frequency / tuning.concert_pitch).log2();
+    Some((Pitch::parse(&key_name(key.round() as i32, false))?, 0.0))
+}
+
+// Attaches the syllables of a `~` line to the notes of the line above it.
+//
+// Comment commands hold their text after the first `~`, which is assumed to
+// be followed by one space, as the formatter writes it.
+fn add_lyrics(part: &mut WrittenPart, location: &SourceLocation, text: &str) {
+    let mut syllables = Vec::new();
+    let mut column = location.column;
+    let mut offset = location.column + 2; // The column where the text starts
+    for (index, piece) in text.split(COMMENT_CHAR).enumerate() {
+        if index > 0 {
+            column = offset - 1;
+        }
+        if !piece.trim().is_empty() {
+            syllables.push((column, piece.trim()));
+        }
+        offset += piece.chars().count() + 1;
+    }
+
+    let mut notes: Vec<&mut WrittenNote> = part
+        .measures
+        .values_mut()
+        .flatten()
+        .filter(|note| {
+            note.location.file == location.file && note.location.line + 1 == location.line
+        })
+        .filter(|note| note.pitch.is_some() && !note.grace)
+        .collect();
+
+    for (column, syllable) in syllables {
+        let nearest = notes
+            .iter_mut()
+            .min_by_key(|note| note.location.column.abs_diff(column));
+        let Some(note) = nearest else {
+            return;
+        };
+
+        let continues_into = syllable.ends_with(HYPHEN);
+        let text = syllable.trim_end_matches(HYPHEN).trim().to_string();
+        match note.lyric {
+            // Words that line up with the same note are sung on it together:
+            Some(ref mut lyric) => {
+                lyric.text = format!("{} {}", lyric.text, text);
+                lyric.continues_into = continues_into;
+            }
+            None => {
+                note.lyric = Some(Syllable {
+                    text,
+                    continues_from: part.lyric_continues,
+                    continues_into,
+                });
+            }
+        }
+        part.lyric_continues = continues_into;
+    }
+}
@@ -0,0 +1,439 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+const BLOCK_SIZE: usize = 4096; // Samples per frame
+const BITS_PER_SAMPLE: u32 = 16;
+const MAX_FIXED_ORDER: usize = 4;
+const MAX_PARTITION_ORDER: u32 = 8;
+const MAX_RICE_PARAMETER: u32 = 14; // 15 is reserved as an escape code
+const STREAMINFO_LENGTH: u64 = 34;
+const FRAME_SYNC_CODE: u64 = 0b11_1111_1111_1110;
+
+// Subframe types:
+const SUBFRAME_CONSTANT: u64 = 0b00_0000;
+const SUBFRAME_VERBATIM: u64 = 0b00_0001;
+const SUBFRAME_FIXED: u64 = 0b00_1000; // Plus the order of the predictor
+
+/// Encodes 16-bit mono samples as a FLAC stream.
+///
+/// Each block of samples is compressed with the fixed linear predictor (of
+/// order 0 to 4) whose residual takes the fewest bits to Rice-code, or stored
+/// verbatim if prediction doesn't help. The stream info holds the MD5
+/// signature of the samples, so decoders can verify them.
+///
+/// # Arguments
+///
+/// * `samples` - The samples to encode.
+/// * `sample_rate` - The number of samples per second.
+///
+/// # Returns
+///
+/// The bytes of the FLAC stream.
+pub fn encode(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+    let mut frames = Vec::new();
+    let mut frame_sizes = Vec::new();
+    for (number, block) in samples.chunks(BLOCK_SIZE).enumerate() {
+        let frame = encode_frame(block, number as u64);
+        frame_sizes.push(frame.len() as u64);
+        frames.extend(frame);
+    }
+
+    let bytes: Vec<u8> = samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect();
+
+    let mut info = BitWriter::default();
+    info.write(1, 1); // This is the last metadata block
+    info.write(0, 7); // STREAMINFO
+    info.write(STREAMINFO_LENGTH, 24);
+    info.write(BLOCK_SIZE as u64, 16); // The smallest block, besides the last one
+    info.write(BLOCK_SIZE as u64, 16); // The largest block
+    info.write(frame_sizes.iter().copied().min().unwrap_or(0), 24);
+    info.write(frame_sizes.iter().copied().max().unwrap_or(0), 24);
+    info.write(sample_rate as u64, 20);
+    info.write(0, 3); // One channel
+    info.write((BITS_PER_SAMPLE - 1) as u64, 5);
+    info.write(samples.len() as u64, 36);
+    for byte in md5(&bytes) {
+        info.write(byte as u64, 8);
+    }
+
+    let mut stream = b"fLaC".to_vec();
+    stream.extend(info.into_bytes());
+    stream.extend(frames);
+    stream
+}
+
+fn encode_frame(block: &[i16], number: u64) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    writer.write(FRAME_SYNC_CODE, 14);
+    writer.write(0, 1); // Reserved
+    writer.write(0, 1); // Every block has the same size
+    writer.write(0b0111, 4); // The block size follows the header, as 16 bits
+    writer.write(0b0000, 4); // The sample rate is in the stream info
+    writer.write(0b0000, 4); // One channel
+    writer.write(0b100, 3); // 16 bits per sample
+    writer.write(0, 1); // Reserved
+    write_utf8(&mut writer, number);
+    writer.write(block.len() as u64 - 1, 16);
+    let crc = crc8(writer.bytes());
+    writer.write(crc as u64, 8);
+
+    let samples: Vec<i32> = block.iter().map(|&sample| sample as i32).collect();
+    write_subframe(&mut writer, &samples);
+
+    writer.align();
+    let crc = crc16(writer.bytes());
+    writer.write(crc as u64, 16);
+    writer.into_bytes()
+}
+
+// The residual of a fixed predictor, and how it is split into partitions
+// that each have their own Rice parameter.
+struct Prediction {
+    order: usize,
+    residual: Vec<i32>,
+    partition_order: u32,
+    parameters: Vec<u32>,
+    bits: u64,
+}
+
+fn write_subframe(writer: &mut BitWriter, samples: &[i32]) {
+    if samples.iter().all(|&sample| sample == samples[0]) {
+        writer.write(0, 1); // Padding
+        writer.write(SUBFRAME_CONSTANT, 6);
+        writer.write(0, 1); // No wasted bits
+        writer.write_signed(samples[0], BITS_PER_SAMPLE);
+        return;
+    }
+
+    let best = (0..=MAX_FIXED_ORDER.min(samples.len() - 1))
+        .filter_map(|order| predict(samples, order))
+        .min_by_key(|prediction| prediction.bits);
+    let verbatim_bits = BITS_PER_SAMPLE as u64 * samples.len() as u64;
+
+    match best {
+        Some(prediction) if prediction.bits < verbatim_bits => {
+            writer.write(0, 1);
+            writer.write(SUBFRAME_FIXED | prediction.order as u64, 6);
+            writer.write(0, 1);
+            for &sample in &samples[..prediction.order] {
+                writer.write_signed(sample, BITS_PER_SAMPLE);
+            }
+            write_residual(writer, &prediction);
+        }
+        _ => {
+            writer.write(0, 1);
+            writer.write(SUBFRAME_VERBATIM, 6);
+            writer.write(0, 1);
+            for &sample in samples {
+                writer.write_signed(sample, BITS_PER_SAMPLE);
+            }
+        }
+    }
+}
+
+// Predicts each sample from the ones before it with the fixed predictor of
+// the given order, which is the same as taking differences `order` times.
+fn predict(samples: &[i32], order: usize) -> Option<Prediction> {
+    let mut residual = samples.to_vec();
+    for _ in 0..order {
+        residual = residual.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    }
+
+    // Running totals of the residual, as Rice codes count it:
+    let mut totals = vec![0_u64];
+    for &value in &residual {
+        totals.push(totals[totals.len() - 1] + zigzag(value) as u64);
+    }
+
+    // Try each way of splitting the block into partitions:
+    let mut best: Option<(u64, u32, Vec<u32>)> = None;
+    for partition_order in 0..=MAX_PARTITION_ORDER {
+        let partitions = 1 << partition_order;
+        let partition_size = samples.len() / partitions;
+        if !samples.len().is_multiple_of(partitions) || partition_size <= order {
+            break;
+        }
+
+        let mut bits = 2 + 4; // The coding method and the partition order
+        let mut parameters = Vec::with_capacity(partitions);
+        for partition in 0..partitions {
+            // The first partition starts after the warm-up samples:
+            let start = (partition * partition_size).saturating_sub(order);
+            let end = (partition + 1) * partition_size - order;
+            let (parameter, partition_bits) =
+                rice_parameter(totals[end] - totals[start], (end - start) as u64);
+            parameters.push(parameter);
+            bits += 4 + partition_bits;
+        }
+
+        if best
+            .as_ref()
+            .is_none_or(|(best_bits, _, _)| bits < *best_bits)
+        {
+            best = Some((bits, partition_order, parameters));
+        }
+    }
+
+    let (bits, partition_order, parameters) = best?;
+    Some(Prediction {
+        order,
+        residual,
+        partition_order,
+        parameters,
+        bits: bits + BITS_PER_SAMPLE as u64 * order as u64,
+    })
+}
+
+// Picks the Rice parameter for a partition from the total of its zigzagged
+// values. Returns the parameter and (an upper bound on) the bits it takes.
+fn rice_parameter(total: u64, count: u64) -> (u32, u64) {
+    (0..=MAX_RICE_PARAMETER)
+        .map(|parameter| {
+            (
+                parameter,
+                count * (parameter as u64 + 1) + (total >> parameter),
+            )
+        })
+        .min_by_key(|(_, bits)| *bits)
+        .unwrap_or((0, 0))
+}
+
+fn write_residual(writer: &mut BitWriter, prediction: &Prediction) {
+    writer.write(0b00, 2); // Rice coding with 4-bit parameters
+    writer.write(prediction.partition_order as u64, 4);
+
+    let partition_size =
+        (prediction.residual.len() + prediction.order) >> prediction.partition_order;
+    let mut values = prediction.residual.iter();
+    for (partition, &parameter) in prediction.parameters.iter().enumerate() {
+        writer.write(parameter as u64, 4);
+        let count = match partition {
+            0 => partition_size - prediction.order,
+            _ => partition_size,
+        };
+        for &value in values.by_ref().take(count) {
+            let value = zigzag(value);
+            writer.write_zeros(value >> parameter);
+            writer.write(1, 1);
+            writer.write(value as u64, parameter);
+        }
+    }
+}
+
+// Folds signed values into unsigned ones: 0, -1, 1, -2, 2, ...
+fn zigzag(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+// Writes a frame number the way UTF-8 writes a character, extended to 36 bits.
+fn write_utf8(writer: &mut BitWriter, value: u64) {
+    if value < 0x80 {
+        writer.write(value, 8);
+        return;
+    }
+
+    // A sequence of n bytes holds 5n + 1 bits:
+    let length = (2..=7).find(|n| value < 1 << (5 * n + 1)).unwrap_or(7);
+    let prefix = (0xFF_u64 << (8 - length)) & 0xFF;
+    writer.write(prefix | (value >> (6 * (length - 1))), 8);
+    for index in (0..length - 1).rev() {
+        writer.write(0x80 | ((value >> (6 * index)) & 0x3F), 8);
+    }
+}
+
+// Writes values most significant bit first, as FLAC stores them.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    bits: u32, // Bits in the buffer that aren't in `bytes` yet
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, count: u32) {
+        let mut count = count;
+        while count > 0 {
+            let take = count.min(32);
+            let part = (value >> (count - take)) & ((1 << take) - 1);
+            self.buffer = (self.buffer << take) | part;
+            self.bits += take;
+            count -= take;
+
+            while self.bits >= 8 {
+                self.bits -= 8;
+                self.bytes.push((self.buffer >> self.bits) as u8);
+            }
+        }
+    }
+
+    fn write_signed(&mut self, value: i32, count: u32) {
+        self.write(value as u64, count);
+    }
+
+    fn write_zeros(&mut self, count: u32) {
+        let mut count = count;
+        while count > 0 {
+            let take = count.min(32);
+            self.write(0, take);
+            count -= take;
+        }
+    }
+
+    // Pads the last byte with zeros.
+    fn align(&mut self) {
+        if self.bits > 0 {
+            self.write(0, 8 - self.bits);
+        }
+    }
+
+    // The whole bytes written so far.
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn into_bytes(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+// The CRC-8 of frame headers (polynomial x^8 + x^2 + x + 1).
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| match crc & 0x80 {
+            0 => crc << 1,
+            _ => (crc << 1) ^ 0x07,
+        })
+    })
+}
+
+// The CRC-16 of frames (polynomial x^16 + x^15 + x^2 + 1).
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ ((byte as u16) << 8), |crc, _| match crc & 0x8000 {
+            0 => crc << 1,
+            _ => (crc << 1) ^ 0x8005,
+        })
+    })
+}
+
+// The MD5 digest of the samples, which FLAC keeps in the stream info.
+fn md5(data: &[u8]) -> [u8; 16] {
+    const SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+    let constants: Vec<u32> = (0..64)
+        .map(|index| ((index as f64 + 1.0).sin().abs() * 4_294_967_296.0) as u32)
+        .collect();
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend(((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+    for chunk in message.chunks(64) {
+        let words: Vec<u32> = chunk
+            .chunks(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+
+        for index in 0..64 {
+            let (mix, word) = match index / 16 {
+                0 => ((b & c) | (!b & d), index),
+                1 => ((d & b) | (!d & c), (5 * index + 1) % 16),
+                2 => (b ^ c ^ d, (3 * index + 5) % 16),
+                _ => (c ^ (b | !d), (7 * index) % 16),
+            };
+            let shift = SHIFTS[(index / 16) * 4 + index % 4];
+            let sum = a
+                .wrapping_add(mix)
+                .wrapping_add(constants[index])
+                .wrapping_add(words[word]);
+            (a, d, c) = (d, c, b);
+            b = b.wrapping_add(sum.rotate_left(shift));
+        }
+
+        for (value, added) in state.iter_mut().zip([a, b, c, d]) {
+            *value = value.wrapping_add(added);
+        }
+    }
+
+    let mut digest = [0; 16];
+    for (bytes, value) in digest.chunks_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_le_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn test_md5() {
+        assert_eq!(hex(&md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex(&md5(b"abc")), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            hex(&md5(b"The quick brown fox jumps over the lazy dog")),
+            "9e107d9d372bb6826bd81d3542a419d6"
+        );
+        assert_eq!(hex(&md5(&[b'a'; 100])), "36a92cc94a9e0fa21f625f8bfb007adf");
+    }
+
+    #[test]
+    fn test_crcs() {
+        assert_eq!(crc8(b"123456789"), 0xF4);
+        assert_eq!(crc16(b"123456789"), 0xFEE8);
+    }
+
+    #[test]
+    fn test_write_utf8() {
+        let encoded = |value| {
+            let mut writer = BitWriter::default();
+            write_utf8(&mut writer, value);
+            writer.into_bytes()
+        };
+        assert_eq!(encoded(0x41), vec![0x41]);
+        assert_eq!(encoded(0xE9), "é".as_bytes());
+        assert_eq!(encoded(0x20AC), "€".as_bytes());
+        assert_eq!(encoded(0x1F3B5), "🎵".as_bytes());
+    }
+
+    #[test]
+    fn test_bit_writer() {
+        let mut writer = BitWriter::default();
+        writer.write(0b101, 3);
+        writer.write_signed(-1, 4);
+        writer.write_zeros(9);
+        writer.write(1, 1);
+        writer.write(0xABCD_EF01_2345, 48);
+        assert_eq!(
+            writer.into_bytes(),
+            vec![0xBE, 0x00, 0xD5, 0xE6, 0xF7, 0x80, 0x91, 0xA2, 0x80]
+        );
+    }
+}
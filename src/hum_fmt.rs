@@ -0,0 +1,196 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::hum_editor::editor_state::formatting;
+use ropey::Rope;
+
+const CONTEXT_LINES: usize = 3; // Unchanged lines shown around each change
+
+/// Formats a score the way the editor does, aligning the notes of each
+/// checkpoint block into columns.
+///
+/// # Arguments
+///
+/// * `score_contents` - The contents of the score.
+///
+/// # Returns
+///
+/// The formatted score. Formatting a formatted score doesn't change it.
+pub fn format_score(score_contents: &str) -> String {
+    let mut text = Rope::from_str(score_contents);
+    formatting::format_buffer(&mut text);
+    text.to_string()
+}
+
+/// Describes the changes from one version of a file to another as a unified
+/// diff, with a few unchanged lines around each change.
+///
+/// # Arguments
+///
+/// * `filename` - The name of the file, for the diff's header.
+/// * `original` - The contents of the file.
+/// * `changed` - The new contents of the file.
+///
+/// # Returns
+///
+/// The diff, which is empty if the contents are the same.
+pub fn diff(filename: &str, original: &str, changed: &str) -> String {
+    let old: Vec<&str> = original.lines().collect();
+    let new: Vec<&str> = changed.lines().collect();
+    let edits = edit_script(&old, &new);
+    if edits.iter().all(|edit| matches!(edit, Edit::Keep(..))) {
+        return String::new();
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", filename, filename);
+    for hunk in hunks(&edits) {
+        let edits = &edits[hunk.0..hunk.1];
+        let (old_start, new_start) = match edits[0] {
+            Edit::Keep(old_line, new_line) => (old_line, new_line),
+            Edit::Remove(old_line, new_line) => (old_line, new_line),
+            Edit::Insert(old_line, new_line) => (old_line, new_line),
+        };
+        let old_count = edits
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Insert(..)))
+            .count();
+        let new_count = edits
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Remove(..)))
+            .count();
+        output.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+        for edit in edits {
+            match *edit {
+                Edit::Keep(old_line, _) => output.push_str(&format!(" {}\n", old[old_line])),
+                Edit::Remove(old_line, _) => output.push_str(&format!("-{}\n", old[old_line])),
+                Edit::Insert(_, new_line) => output.push_str(&format!("+{}\n", new[new_line])),
+            }
+        }
+    }
+    output
+}
+
+// One step in turning the old lines into the new ones. Each step holds the
+// indices of the old and new lines that it is at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Keep(usize, usize),
+    Remove(usize, usize),
+    Insert(usize, usize),
+}
+
+// Finds the shortest list of edits from the old lines to the new ones, by
+// keeping their longest common subsequence.
+fn edit_script(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    // common[i][j] is the length of the longest common subsequence of
+    // old[i..] and new[j..]:
+    let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            edits.push(Edit::Keep(i, j));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+            edits.push(Edit::Remove(i, j));
+            i += 1;
+        } else {
+            edits.push(Edit::Insert(i, j));
+            j += 1;
+        }
+    }
+    edits
+}
+
+// Groups the edits into hunks: the ranges of edits around each run of changes,
+// merged when their context overlaps.
+fn hunks(edits: &[Edit]) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (index, edit) in edits.iter().enumerate() {
+        if matches!(edit, Edit::Keep(..)) {
+            continue;
+        }
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + 1 + CONTEXT_LINES).min(edits.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    hunks
+}
+
+// Writes the start and length of a hunk's lines, counting from 1. An empty
+// range starts at the line before it, as `diff -u` does.
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_contents_have_no_diff() {
+        assert_eq!(diff("a.hum", "one\ntwo\n", "one\ntwo\n"), "");
+        assert_eq!(diff("a.hum", "", ""), "");
+    }
+
+    #[test]
+    fn test_diff_has_context_around_changes() {
+        let original = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let changed = "1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n";
+        assert_eq!(
+            diff("a.hum", original, changed),
+            "--- a.hum\n+++ a.hum\n\
+             @@ -1,6 +1,6 @@\n 1\n 2\n-3\n+three\n 4\n 5\n 6\n\
+             @@ -10,3 +10,4 @@\n 10\n 11\n 12\n+13\n"
+        );
+    }
+
+    #[test]
+    fn test_nearby_changes_share_a_hunk() {
+        assert_eq!(
+            diff("a.hum", "a\nb\nc\nd\n", "b\nc\nD\n"),
+            "--- a.hum\n+++ a.hum\n@@ -1,4 +1,3 @@\n-a\n b\n c\n-d\n+D\n"
+        );
+        assert_eq!(
+            diff("a.hum", "", "new\n"),
+            "--- a.hum\n+++ a.hum\n@@ -0,0 +1 @@\n+new\n"
+        );
+    }
+}
@@ -0,0 +1,458 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use ropey::Rope;
+
+use crate::hum_editor::editor_state::formatting::format_buffer;
+use crate::hum_process::hum_math::{NOTES_FLATS, NOTES_SHARPS, SEMITONES_PER_OCTAVE};
+
+/// The number of time units in a whole note. Times and lengths in an imported
+/// score are measured in these units, which can represent notes down to 1/256
+/// as well as triplets and quintuplets.
+pub const UNITS_PER_WHOLE: u32 = 3840;
+
+const MEASURES_PER_BLOCK: usize = 4; // Measures per checkpoint block without system breaks
+const DEFAULT_TIME_SIGNATURE: (u32, u32) = (4, 4);
+const DEFAULT_QUARTER_BPM: f32 = 60.0;
+const DEFAULT_VOICE: &str = "sine";
+const VOICE_NAMES: [&str; 3] = ["sine", "square", "sawtooth"]; // The waveforms that Hum plays
+const REST_NAME: &str = "Rest";
+const MIDI_KEY_OF_C0: i32 = 12;
+const QUARTERS_PER_WHOLE: f32 = 4.0;
+const COMMENT_CHAR: char = '~';
+const NOTE_START_CHAR: char = '(';
+const CHECKPOINT_LINE: &str = "*";
+
+/// A note (or a syllable of lyrics on a note) read from another format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedNote {
+    /// When the note starts, in units from the start of the score.
+    pub start: u32,
+    /// How long the note lasts, in units.
+    pub length: u32,
+    /// The Hum name of the pitch, e.g. `Cs_4`.
+    pub pitch: String,
+    /// The syllable of lyrics sung on the note, if any.
+    pub lyric: Option<String>,
+}
+
+/// A part (an instrument or a staff) read from another format. Its notes may
+/// overlap, in which case they are spread over several lines of music.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportedPart {
+    pub name: String,
+    pub notes: Vec<ImportedNote>,
+}
+
+/// A score read from another format, ready to be written as Hum text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportedScore {
+    pub title: Option<String>,
+    /// Tempo changes: when they happen, in units, and the number of quarter
+    /// notes per minute.
+    pub tempos: Vec<(u32, f32)>,
+    /// Time signature changes: when they happen, in units, and the time
+    /// signature. Changes should fall on bar lines.
+    pub time_signatures: Vec<(u32, (u32, u32))>,
+    /// The measures (counted from 0) that start a new line of the printed
+    /// score. Without them, every few measures start a new block.
+    pub system_breaks: Vec<usize>,
+    /// The measures that start with a `|:` repeat sign.
+    pub repeat_starts: Vec<usize>,
+    /// The measures that end with a `:|` repeat sign.
+    pub repeat_ends: Vec<usize>,
+    /// The measures that start a numbered ending, and its number.
+    pub endings: Vec<(usize, u32)>,
+    pub parts: Vec<ImportedPart>,
+}
+
+/// Returns the Hum name of a MIDI key, e.g. `Cn_4` for key 60.
+///
+/// Black keys are spelled with flats if `flats` is true, and with sharps
+/// otherwise.
+pub fn key_name(key: i32, flats: bool) -> String {
+    let index = key - MIDI_KEY_OF_C0;
+    let names = if flats { NOTES_FLATS } else { NOTES_SHARPS };
+    format!(
+        "{}_{}",
+        names[index.rem_euclid(SEMITONES_PER_OCTAVE) as usize],
+        index.div_euclid(SEMITONES_PER_OCTAVE)
+    )
+}
+
+/// Formats a length in units as a Hum note length.
+///
+/// Lengths are written with dots where they can be (`1/4+` rather than
+/// `3/8`), tuplets come out as their fraction of a whole note (e.g., `1/12`
+/// for an eighth note triplet), and any other length is written as a fraction.
+pub fn format_length(units: u32) -> String {
+    let divisor = gcd(units, UNITS_PER_WHOLE);
+    let (numerator, denominator) = (units / divisor, UNITS_PER_WHOLE / divisor);
+
+    match numerator {
+        1 => format!("1/{}", denominator),
+        3 if denominator % 2 == 0 => format!("1/{}+", denominator / 2),
+        7 if denominator % 4 == 0 => format!("1/{}++", denominator / 4),
+        _ => format!("{}/{}", numerator, denominator),
+    }
+}
+
+/// Writes an imported score as formatted Hum text.
+///
+/// The tempo and time signature go at the top. The score is split into
+/// measures by the time signature, and into checkpoint blocks at system breaks
+/// (or every few measures), so that each block holds a line of music for every
+/// line of every part. Notes that cross a bar line are split in two, since
+/// Hum has no ties. Repeat signs and numbered endings are written on every
+/// line. Each part is named in a `~` comment, unless its name is a
+/// voice that it can be played with. Lyrics are written in `~` comments under
+/// their notes.
+pub fn write_score(score: &ImportedScore) -> String {
+    let measures = split_measures(score);
+    let blocks = split_blocks(score, &measures);
+    let parts: Vec<(&str, Vec<Vec<&ImportedNote>>)> = score
+        .parts
+        .iter()
+        .map(|part| (part.name.as_str(), split_lines(&part.notes)))
+        .collect();
+
+    // Each line of text, with the lyrics of its notes if it has any:
+    let mut lines: Vec<(String, Option<Vec<Option<String>>>)> = Vec::new();
+    let push_line = |lines: &mut Vec<_>, line: String| lines.push((line, None));
+
+    if let Some(ref title) = score.title {
+        push_line(&mut lines, format!("{} {}", COMMENT_CHAR, title));
+    }
+    let first = &measures[0];
+    push_line(
+        &mut lines,
+        format!(
+            "[ {}_bpm ] [ {}/{} ]",
+            format_bpm(first.tempo, first.time_signature),
+            first.time_signature.0,
+            first.time_signature.1
+        ),
+    );
+
+    for (block_index, block) in blocks.iter().enumerate() {
+        push_line(&mut lines, String::new());
+        push_line(&mut lines, CHECKPOINT_LINE.to_string());
+
+        // Tempo and time signature changes go at the top of their block:
+        let block_start = &measures[block.start];
+        if block_index > 0 {
+            let previous = &measures[block.start - 1];
+            let mut directives = Vec::new();
+            if previous.tempo != block_start.tempo
+                || previous.time_signature != block_start.time_signature
+            {
+                directives.push(format!(
+                    "[ {}_bpm ]",
+                    format_bpm(block_start.tempo, block_start.time_signature)
+                ));
+            }
+            if previous.time_signature != block_start.time_signature {
+                let (numerator, denominator) = block_start.time_signature;
+                directives.push(format!("[ {}/{} ]", numerator, denominator));
+            }
+            if !directives.is_empty() {
+                push_line(&mut lines, directives.join(" "));
+            }
+        }
+
+        for (part_index, (name, part_lines)) in parts.iter().enumerate() {
+            if part_index > 0 {
+                push_line(&mut lines, String::new());
+            }
+            // Parts named after a voice (as exported parts are) play with it:
+            let voice = VOICE_NAMES
+                .iter()
+                .find(|voice| voice.eq_ignore_ascii_case(name.trim()));
+            if block_index == 0 && voice.is_none() && !name.is_empty() {
+                push_line(&mut lines, format!("{} {}", COMMENT_CHAR, name));
+            }
+            push_line(&mut lines, format!("% {}", voice.unwrap_or(&DEFAULT_VOICE)));
+
+            for notes in part_lines {
+                let (text, lyrics) = write_line(notes, &measures[block.clone()]);
+                let lyrics = Some(lyrics).filter(|l| l.iter().any(Option::is_some));
+                lines.push((text, lyrics));
+            }
+        }
+    }
+
+    let (lines, lyrics): (Vec<String>, Vec<_>) = lines.into_iter().unzip();
+    let mut text = Rope::from_str(&(lines.join("\n") + "\n"));
+    format_buffer(&mut text);
+
+    add_lyrics(&text.to_string(), &lyrics)
+}
+
+// A measure of the imported score.
+struct ImportedMeasure {
+    start: u32,
+    end: u32,
+    tempo: f32,
+    time_signature: (u32, u32),
+    repeat_start: bool,
+    repeat_end: bool,
+    ending: Option<u32>,
+}
+
+// Splits the length of the score into measures.
+fn split_measures(score: &ImportedScore) -> Vec<ImportedMeasure> {
+    let end = score
+        .parts
+        .iter()
+        .flat_map(|part| &part.notes)
+        .map(|note| note.start + note.length)
+        .max()
+        .unwrap_or(0);
+
+    let mut measures = Vec::new();
+    let mut start = 0;
+    loop {
+        let time_signature = latest_change(&score.time_signatures, start, DEFAULT_TIME_SIGNATURE);
+        let length = (time_signature.0 * UNITS_PER_WHOLE / time_signature.1.max(1)).max(1);
+        // Tempo changes in the middle of a measure take effect at its start:
+        let end_of_measure = start.saturating_add(length); // The last measure may not fit
+        let tempo = latest_change(&score.tempos, end_of_measure - 1, DEFAULT_QUARTER_BPM);
+
+        let index = measures.len();
+        measures.push(ImportedMeasure {
+            start,
+            end: end_of_measure,
+            tempo,
+            time_signature,
+            repeat_start: score.repeat_starts.contains(&index),
+            repeat_end: score.repeat_ends.contains(&index),
+            ending: score
+                .endings
+                .iter()
+                .find(|(measure, _)| *measure == index)
+                .map(|(_, number)| *number),
+        });
+        start = end_of_measure;
+
+        if start >= end {
+            return measures;
+        }
+    }
+}
+
+// Finds the value of the last change at or before the given time.
+fn latest_change<T: Copy>(changes: &[(u32, T)], time: u32, default: T) -> T {
+    changes
+        .iter()
+        .filter(|(start, _)| *start <= time)
+        .max_by_key(|(start, _)| *start)
+        .map_or(default, |(_, value)| *value)
+}
+
+// Groups the measures into checkpoint blocks, starting a new block at every
+// system break, tempo change, and time signature change.
+fn split_blocks(
+    score: &ImportedScore,
+    measures: &[ImportedMeasure],
+) -> Vec<std::ops::Range<usize>> {
+    let mut blocks = Vec::new();
+    let mut start = 0;
+
+    for index in 1..measures.len() {
+        let changes = measures[index].tempo != measures[index - 1].tempo
+            || measures[index].time_signature != measures[index - 1].time_signature;
+        let full = if score.system_breaks.is_empty() {
+            index - start >= MEASURES_PER_BLOCK
+        } else {
+            score.system_breaks.contains(&index)
+        };
+
+        if changes || full {
+            blocks.push(start..index);
+            start = index;
+        }
+    }
+
+    blocks.push(start..measures.len());
+    blocks
+}
+
+// Spreads the notes of a part over as few lines of music as possible, so that
+// the notes in each line don't overlap.
+fn split_lines(notes: &[ImportedNote]) -> Vec<Vec<&ImportedNote>> {
+    let mut sorted: Vec<&ImportedNote> = notes.iter().collect();
+    sorted.sort_by_key(|note| (note.start, note.length));
+
+    let mut lines: Vec<Vec<&ImportedNote>> = Vec::new();
+    for note in sorted {
+        let free_line = lines.iter_mut().find(|line| {
+            line.last()
+                .is_none_or(|last| last.start + last.length <= note.start)
+        });
+        match free_line {
+            Some(line) => line.push(note),
+            None => lines.push(vec![note]),
+        }
+    }
+
+    if lines.is_empty() {
+        lines.push(Vec::new());
+    }
+    lines
+}
+
+// Writes the measures of one line of music, filling the gaps between notes
+// with rests. Returns the text and the lyrics of each note in it.
+fn write_line(
+    notes: &[&ImportedNote],
+    measures: &[ImportedMeasure],
+) -> (String, Vec<Option<String>>) {
+    let mut text = String::new();
+    let mut lyrics = Vec::new();
+
+    for measure in measures {
+        match measure.ending {
+            _ if measure.repeat_start => text.push_str("|: "),
+            Some(number) => text.push_str(&format!("|{} ", number)),
+            None => text.push_str("| "),
+        }
+        let mut position = measure.start;
+
+        let in_measure = notes
+            .iter()
+            .filter(|note| note.start < measure.end && note.start + note.length > measure.start);
+        for note in in_measure {
+            let start = note.start.max(measure.start);
+            let end = (note.start + note.length).min(measure.end);
+
+            if start > position {
+                text.push_str(&format!(
+                    "({} {}) ",
+                    REST_NAME,
+                    format_length(start - position)
+                ));
+                lyrics.push(None);
+            }
+            text.push_str(&format!("({} {}) ", note.pitch, format_length(end - start)));
+            // The lyric belongs to the first part of a note split by a bar line:
+            lyrics.push(note.lyric.clone().filter(|_| start == note.start));
+            position = end;
+        }
+
+        if measure.end > position {
+            text.push_str(&format!(
+                "({} {}) ",
+                REST_NAME,
+                format_length(measure.end - position)
+            ));
+            lyrics.push(None);
+        }
+        if measure.repeat_end {
+            text.push_str(":| ");
+        }
+    }
+
+    text.push(';');
+    (text, lyrics)
+}
+
+// Writes the tempo in beats of the time signature per minute, as Hum counts it.
+fn format_bpm(quarter_bpm: f32, (_, denominator): (u32, u32)) -> u32 {
+    (quarter_bpm * denominator as f32 / QUARTERS_PER_WHOLE)
+        .round()
+        .max(1.0) as u32
+}
+
+// Adds a line of lyrics under each formatted line that has them. Each syllable
+// is written as a `~` comment that starts in the same column as its note.
+fn add_lyrics(text: &str, lyrics: &[Option<Vec<Option<String>>>]) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for (index, line) in text.lines().enumerate() {
+        result.push_str(line);
+        result.push('\n');
+
+        let Some(Some(syllables)) = lyrics.get(index) else {
+            continue;
+        };
+
+        let mut lyric_line = String::new();
+        let columns = line
+            .match_indices(NOTE_START_CHAR)
+            .map(|(column, _)| column);
+        for (column, syllable) in columns.zip(syllables) {
+            if let Some(syllable) = syllable {
+                let padding = match lyric_line.len() {
+                    0 => column,
+                    length => column.saturating_sub(length).max(1),
+                };
+                lyric_line.push_str(&" ".repeat(padding));
+                lyric_line.push(COMMENT_CHAR);
+                lyric_line.push(' ');
+                lyric_line.push_str(syllable);
+            }
+        }
+        result.push_str(&lyric_line);
+        result.push('\n');
+    }
+
+    result
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_length() {
+        let quarter = UNITS_PER_WHOLE / 4;
+        assert_eq!(format_length(quarter), "1/4");
+        assert_eq!(format_length(quarter * 3 / 2), "1/4+");
+        assert_eq!(format_length(quarter * 7 / 4), "1/4++");
+        assert_eq!(format_length(UNITS_PER_WHOLE / 12), "1/12");
+        assert_eq!(format_length(quarter * 5 / 2), "5/8");
+        assert_eq!(format_length(UNITS_PER_WHOLE * 2), "2/1");
+    }
+
+    #[test]
+    fn test_key_name() {
+        assert_eq!(key_name(60, false), "Cn_4");
+        assert_eq!(key_name(61, false), "Cs_4");
+        assert_eq!(key_name(61, true), "Df_4");
+        assert_eq!(key_name(11, false), "Bn_-1");
+    }
+
+    #[test]
+    fn test_split_lines() {
+        let note = |start, length| ImportedNote {
+            start,
+            length,
+            pitch: "Cn_4".to_string(),
+            lyric: None,
+        };
+        let notes = vec![note(0, 2), note(0, 1), note(1, 1), note(2, 2)];
+        let lines = split_lines(&notes);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), 3);
+        assert_eq!(lines[1].len(), 1);
+    }
+}
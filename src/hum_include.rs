@@ -0,0 +1,108 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::hum_error::{HumError, IncludeError, SourceLocation};
+use crate::hum_parse::{self, LocatedCommand};
+
+const INCLUDE_COMMAND: &str = "include";
+
+/// Parses a score and splices the commands of every `[ include "..." ]`
+/// directive into it, recursively.
+///
+/// Included paths are resolved relative to the directory of the file that
+/// includes them. Commands from included files are located in those files, so
+/// errors point at the right place.
+///
+/// # Arguments
+///
+/// * `score_contents` - The contents of the score.
+/// * `score_path` - The path the score was read from, if any. Without a path,
+///   includes are resolved relative to the current directory.
+///
+/// # Returns
+///
+/// A `Result` containing the located commands or a `HumError`.
+pub fn parse_with_includes(
+    score_contents: &str,
+    score_path: Option<&Path>,
+) -> Result<Vec<LocatedCommand>, HumError> {
+    let file = score_path.map(|path| path.display().to_string());
+    let commands = hum_parse::parse_located_file(score_contents, file.as_deref())?;
+
+    // The score itself counts as being included, so it can't include itself.
+    let mut stack: Vec<PathBuf> = score_path
+        .and_then(|path| fs::canonicalize(path).ok())
+        .into_iter()
+        .collect();
+
+    let base_dir = score_path
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    let mut resolved = Vec::new();
+    splice_includes(commands, &base_dir, &mut stack, &mut resolved)?;
+    Ok(resolved)
+}
+
+// Copies commands into `resolved`, replacing each include with the commands
+// of the included file. `stack` holds the files currently being included.
+fn splice_includes(
+    commands: Vec<LocatedCommand>,
+    base_dir: &Path,
+    stack: &mut Vec<PathBuf>,
+    resolved: &mut Vec<LocatedCommand>,
+) -> Result<(), IncludeError> {
+    for (location, (verb, noun)) in commands {
+        if verb != INCLUDE_COMMAND {
+            resolved.push((location, (verb, noun)));
+            continue;
+        }
+
+        let path = base_dir.join(&noun);
+        let error = |message: String| IncludeError {
+            message,
+            location: Some(location.clone()),
+        };
+
+        let canonical = fs::canonicalize(&path)
+            .map_err(|err| error(format!("Failed to include \"{}\": {}", noun, err)))?;
+        if stack.contains(&canonical) {
+            return Err(error(format!("Include cycle through \"{}\"", noun)));
+        }
+
+        let contents = fs::read_to_string(&path)
+            .map_err(|err| error(format!("Failed to include \"{}\": {}", noun, err)))?;
+        let file = path.display().to_string();
+        let included =
+            hum_parse::parse_located_file(&contents, Some(&file)).map_err(|err| IncludeError {
+                message: format!("Failed to parse included file: expected {}", err.expected),
+                location: Some(SourceLocation::new(Some(&file), err.location)),
+            })?;
+
+        stack.push(canonical);
+        let included_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        splice_includes(included, &included_dir, stack, resolved)?;
+        stack.pop();
+    }
+
+    Ok(())
+}
@@ -17,23 +17,79 @@ along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
 use std::fs;
-use std::io;
+use std::io::{self, Write};
+use std::path::Path;
 
 use crate::SAMPLE_RATE;
+use crate::hum_error::HumError;
+use crate::hum_flac;
 
 static NUM_CHANNELS: u16 = 1;
 static BIT_DEPTH: u16 = 16;
 
-/// Reads the contents of a file into a String.
+/// The filename that stands for standard output.
+pub const STDOUT_FILENAME: &str = "-";
+
+/// The filename that stands for standard input.
+pub const STDIN_FILENAME: &str = "-";
+
+/// The name given to standard input in error messages.
+pub const STDIN_NAME: &str = "<stdin>";
+
+/// A format that a waveform can be saved in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    /// A 16-bit WAV file.
+    Wav,
+    /// A 16-bit FLAC file.
+    Flac,
+    /// Raw signed 16-bit little-endian samples.
+    S16le,
+    /// Raw 32-bit floating-point little-endian samples.
+    F32le,
+}
+
+impl AudioFormat {
+    /// The names of the formats, as they are given to `from_name`.
+    pub const NAMES: [&str; 4] = ["wav", "flac", "s16le", "f32le"];
+
+    /// Returns the format with the given name (e.g. `flac` or `s16le`),
+    /// ignoring case.
+    pub fn from_name(name: &str) -> Option<AudioFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "wav" | "wave" => Some(AudioFormat::Wav),
+            "flac" => Some(AudioFormat::Flac),
+            "s16le" | "pcm" | "raw" => Some(AudioFormat::S16le),
+            "f32le" => Some(AudioFormat::F32le),
+            _ => None,
+        }
+    }
+
+    /// Picks a format from the extension of a filename, e.g. FLAC for
+    /// `song.flac` and raw 16-bit samples for `song.pcm`. Other files are WAV.
+    pub fn from_filename(filename: &str) -> AudioFormat {
+        Path::new(filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(AudioFormat::from_name)
+            .unwrap_or(AudioFormat::Wav)
+    }
+}
+
+/// Reads the contents of a file into a String. If the filename is `-`,
+/// standard input is read to the end instead.
 ///
 /// # Arguments
 ///
-/// * `filename` - The path to the file to read.
+/// * `filename` - The path to the file to read, or `-`.
 ///
 /// # Returns
 ///
 /// A `Result` containing the file contents as a `String` or an `io::Error`.
 pub fn read(filename: &str) -> Result<String, io::Error> {
+    if filename == STDIN_FILENAME {
+        return io::read_to_string(io::stdin().lock());
+    }
     fs::read_to_string(filename)
 }
 
@@ -58,9 +114,152 @@ pub fn save(waveform: Vec<f32>, filename: &str) -> Result<(), hound::Error> {
     let mut writer = hound::WavWriter::create(filename, spec)?;
 
     for sample in waveform {
-        let amplitude = i16::MAX as f32;
-        writer.write_sample((sample * amplitude) as i16)?;
+        writer.write_sample(to_i16(sample))?;
     }
 
     writer.finalize()
 }
+
+/// Saves a waveform to a file in the given format. If the filename is `-`,
+/// the waveform is written to standard output instead.
+///
+/// # Arguments
+///
+/// * `waveform` - A slice of floating-point samples representing the audio.
+/// * `filename` - The path where the file should be saved, or `-`.
+/// * `format` - The format to save the waveform in.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn save_as(waveform: &[f32], filename: &str, format: AudioFormat) -> Result<(), HumError> {
+    if filename == STDOUT_FILENAME {
+        let mut stdout = io::stdout().lock();
+        write_audio(waveform, format, &mut stdout)?;
+        return stdout.flush().map_err(HumError::FileWriteError);
+    }
+
+    let mut file =
+        io::BufWriter::new(fs::File::create(filename).map_err(HumError::FileWriteError)?);
+    write_audio(waveform, format, &mut file)?;
+    file.flush().map_err(HumError::FileWriteError)
+}
+
+/// Writes a waveform in the given format.
+///
+/// Raw formats hold one sample per frame, since Hum renders in mono.
+///
+/// # Arguments
+///
+/// * `waveform` - A slice of floating-point samples representing the audio.
+/// * `format` - The format to write the waveform in.
+/// * `writer` - Where to write the audio.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn write_audio<W: Write>(
+    waveform: &[f32],
+    format: AudioFormat,
+    writer: &mut W,
+) -> Result<(), HumError> {
+    let samples = || waveform.iter().map(|&sample| to_i16(sample));
+    let bytes = match format {
+        AudioFormat::Wav => {
+            // WAV headers hold the length of the audio, so it is written in memory first:
+            let spec = hound::WavSpec {
+                channels: NUM_CHANNELS,
+                sample_rate: SAMPLE_RATE,
+                bits_per_sample: BIT_DEPTH,
+                sample_format: hound::SampleFormat::Int,
+            };
+            let mut buffer = io::Cursor::new(Vec::new());
+            let mut wav = hound::WavWriter::new(&mut buffer, spec)?;
+            for sample in samples() {
+                wav.write_sample(sample)?;
+            }
+            wav.finalize()?;
+            buffer.into_inner()
+        }
+        AudioFormat::Flac => hum_flac::encode(&samples().collect::<Vec<_>>(), SAMPLE_RATE),
+        AudioFormat::S16le => samples().flat_map(i16::to_le_bytes).collect(),
+        AudioFormat::F32le => waveform.iter().flat_map(|s| s.to_le_bytes()).collect(),
+    };
+    writer.write_all(&bytes).map_err(HumError::FileWriteError)
+}
+
+/// Writes audio in the given format as it is rendered, a chunk at a time.
+///
+/// WAV and raw audio are written (and flushed) as each chunk arrives, so that
+/// whatever reads them can start playing straight away. The WAV header is
+/// written first from `sample_count`, which must be the total number of
+/// samples in the chunks. FLAC frames are encoded together at the end.
+///
+/// # Arguments
+///
+/// * `chunks` - The chunks of floating-point samples, in order.
+/// * `sample_count` - The number of samples in all of the chunks.
+/// * `format` - The format to write the audio in.
+/// * `writer` - Where to write the audio.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn write_audio_stream<W: Write>(
+    chunks: impl IntoIterator<Item = Vec<f32>>,
+    sample_count: usize,
+    format: AudioFormat,
+    writer: &mut W,
+) -> Result<(), HumError> {
+    if format == AudioFormat::Flac {
+        let waveform: Vec<f32> = chunks.into_iter().flatten().collect();
+        write_audio(&waveform, format, writer)?;
+        return writer.flush().map_err(HumError::FileWriteError);
+    }
+
+    if format == AudioFormat::Wav {
+        writer
+            .write_all(&wav_header(sample_count))
+            .map_err(HumError::FileWriteError)?;
+    }
+    for chunk in chunks {
+        let bytes: Vec<u8> = match format {
+            AudioFormat::F32le => chunk.iter().flat_map(|s| s.to_le_bytes()).collect(),
+            _ => chunk
+                .iter()
+                .flat_map(|&s| to_i16(s).to_le_bytes())
+                .collect(),
+        };
+        writer.write_all(&bytes).map_err(HumError::FileWriteError)?;
+        writer.flush().map_err(HumError::FileWriteError)?;
+    }
+    Ok(())
+}
+
+// Builds the header of a 16-bit PCM WAV file holding the given number of
+// samples, for when the samples are written after it.
+fn wav_header(sample_count: usize) -> Vec<u8> {
+    let block_align = NUM_CHANNELS * BIT_DEPTH / 8;
+    let data_length = (sample_count * block_align as usize) as u32;
+
+    let mut header = Vec::with_capacity(44);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(36 + data_length).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes()); // Integer PCM
+    header.extend_from_slice(&NUM_CHANNELS.to_le_bytes());
+    header.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    header.extend_from_slice(&(SAMPLE_RATE * block_align as u32).to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&BIT_DEPTH.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_length.to_le_bytes());
+    header
+}
+
+// Converts a sample from -1.0..1.0 to a 16-bit sample, clipping it if it is
+// out of range.
+fn to_i16(sample: f32) -> i16 {
+    (sample * i16::MAX as f32) as i16
+}
@@ -0,0 +1,618 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+use crate::hum_import::{ImportedNote, ImportedPart, ImportedScore, UNITS_PER_WHOLE, key_name};
+use crate::hum_process::{Performance, PerformedMeasure};
+
+const TICKS_PER_QUARTER: u16 = 480;
+const SMF_FORMAT: u16 = 1; // One conductor track plus one track per line of music
+const DEFAULT_QUARTER_DURATION: f32 = 1.0; // Hum's default tempo of 60 beats per minute
+const MICROSECONDS_PER_SECOND: f32 = 1_000_000.0;
+const CONCERT_PITCH_KEY: f32 = 69.0; // MIDI key of An_4
+const CONCERT_PITCH_FREQ: f32 = 440.0;
+const SEMITONES_PER_OCTAVE: f32 = 12.0;
+const DEFAULT_VELOCITY: f32 = 80.0; // Velocity of a note with a gain of 1
+const MAX_DATA_BYTE: u8 = 127;
+const CHANNEL_COUNT: usize = 16;
+const DRUM_CHANNEL: usize = 9; // General MIDI percussion, skipped for pitched notes
+const CLOCKS_PER_CLICK: u8 = 24;
+const THIRTY_SECONDS_PER_QUARTER: u8 = 8;
+
+const GRID_NOTE: u32 = 32; // Imported notes snap to 1/32 notes...
+const TRIPLET_GRID_NOTE: u32 = 24; // ...or to 1/16 note triplets, whichever is nearer
+const HEADER_LENGTH: usize = 14;
+const CHUNK_HEADER_LENGTH: usize = 8;
+const SMPTE_DIVISION_BIT: u16 = 0x8000;
+const MAX_VARIABLE_LENGTH_BYTES: usize = 4;
+
+const NOTE_OFF: u8 = 0x80;
+const NOTE_ON: u8 = 0x90;
+const PROGRAM_CHANGE: u8 = 0xC0;
+const CHANNEL_PRESSURE: u8 = 0xD0;
+const SYSEX_EVENT: u8 = 0xF0;
+const SYSEX_CONTINUATION: u8 = 0xF7;
+const META_EVENT: u8 = 0xFF;
+const META_LYRIC: u8 = 0x05;
+const META_KEY_SIGNATURE: u8 = 0x59;
+const META_TRACK_NAME: u8 = 0x03;
+const META_END_OF_TRACK: u8 = 0x2F;
+const META_TEMPO: u8 = 0x51;
+const META_TIME_SIGNATURE: u8 = 0x58;
+
+/// Encodes the performance of a score as a Type 1 Standard MIDI File.
+///
+/// The first track carries the tempo and time signature of every measure, and
+/// each line of music gets its own track, named after the voice that plays it.
+///
+/// # Arguments
+///
+/// * `performance` - The timing of the score from `hum_process::perform_located_commands`.
+///
+/// # Returns
+///
+/// The bytes of the MIDI file.
+pub fn encode(performance: &Performance) -> Vec<u8> {
+    let tempo_map = TempoMap::new(&performance.measures);
+
+    let line_count = performance
+        .notes
+        .iter()
+        .map(|note| note.line + 1)
+        .max()
+        .unwrap_or(0);
+
+    let mut tracks = vec![conductor_track(&tempo_map)];
+    for line in 0..line_count {
+        tracks.push(line_track(performance, &tempo_map, line));
+    }
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"MThd");
+    bytes.extend_from_slice(&6u32.to_be_bytes());
+    bytes.extend_from_slice(&SMF_FORMAT.to_be_bytes());
+    bytes.extend_from_slice(&(tracks.len() as u16).to_be_bytes());
+    bytes.extend_from_slice(&TICKS_PER_QUARTER.to_be_bytes());
+
+    for track in tracks {
+        bytes.extend_from_slice(b"MTrk");
+        bytes.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&track);
+    }
+
+    bytes
+}
+
+/// Saves the performance of a score to a Standard MIDI File.
+///
+/// # Arguments
+///
+/// * `performance` - The timing of the score.
+/// * `filename` - The path where the MIDI file should be saved.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing an `io::Error`.
+pub fn save(performance: &Performance, filename: &str) -> Result<(), io::Error> {
+    fs::write(filename, encode(performance))
+}
+
+// Converts times in seconds to ticks. Every measure gets the tempo that makes
+// it last exactly as long as its time signature, so bar lines in the MIDI file
+// line up with the measures of the score (even when a fermata stretches one).
+struct TempoMap {
+    // The start time in seconds, start tick, quarter note duration in seconds,
+    // and time signature of each measure in the order it is played.
+    measures: Vec<(f32, u64, f32, (u32, u32))>,
+}
+
+impl TempoMap {
+    fn new(measures: &[PerformedMeasure]) -> TempoMap {
+        let mut tick = 0;
+        let measures = measures
+            .iter()
+            .filter(|measure| measure.duration > 0.0)
+            .map(|measure| {
+                let quarters = quarters_per_measure(measure.time_signature);
+                let entry = (
+                    measure.start,
+                    tick,
+                    measure.duration / quarters,
+                    measure.time_signature,
+                );
+                tick += (quarters * TICKS_PER_QUARTER as f32).round() as u64;
+                entry
+            })
+            .collect();
+        TempoMap { measures }
+    }
+
+    fn ticks(&self, time: f32) -> u64 {
+        let (start, start_tick, quarter_duration) = self
+            .measures
+            .iter()
+            .rev()
+            .find(|(start, ..)| *start <= time)
+            .or(self.measures.first())
+            .map_or(
+                (0.0, 0, DEFAULT_QUARTER_DURATION),
+                |&(start, tick, quarter, _)| (start, tick, quarter),
+            );
+
+        let quarters = (time - start) / quarter_duration;
+        (start_tick as f32 + quarters * TICKS_PER_QUARTER as f32)
+            .round()
+            .max(0.0) as u64
+    }
+}
+
+// Returns the number of quarter notes in a measure with the given time
+// signature, e.g. 3 for 3/4 and 3 for 6/8.
+fn quarters_per_measure((numerator, denominator): (u32, u32)) -> f32 {
+    numerator as f32 * 4.0 / denominator.max(1) as f32
+}
+
+// Builds the first track, which holds the tempo and time signature changes.
+fn conductor_track(tempo_map: &TempoMap) -> Vec<u8> {
+    let mut events: Vec<(u64, Vec<u8>)> = Vec::new();
+    let mut last_tempo = None;
+    let mut last_time_signature = None;
+
+    if tempo_map.measures.is_empty() {
+        events.push((0, tempo_event(DEFAULT_QUARTER_DURATION)));
+    }
+
+    for &(_, tick, quarter_duration, time_signature) in &tempo_map.measures {
+        let tempo = (quarter_duration * MICROSECONDS_PER_SECOND).round() as u32;
+        if last_tempo != Some(tempo) {
+            events.push((tick, tempo_event(quarter_duration)));
+            last_tempo = Some(tempo);
+        }
+        if last_time_signature != Some(time_signature) {
+            events.push((tick, time_signature_event(time_signature)));
+            last_time_signature = Some(time_signature);
+        }
+    }
+
+    encode_track(events)
+}
+
+// Builds the track of one line of music.
+fn line_track(performance: &Performance, tempo_map: &TempoMap, line: usize) -> Vec<u8> {
+    let channel = line_channel(line);
+    let notes: Vec<_> = performance
+        .notes
+        .iter()
+        .filter(|note| note.line == line)
+        .collect();
+
+    let mut events: Vec<(u64, Vec<u8>)> = Vec::new();
+    if let Some(first) = notes.first() {
+        let mut name = vec![META_EVENT, META_TRACK_NAME];
+        write_variable_length(&mut name, first.voice.len() as u64);
+        name.extend_from_slice(first.voice.as_bytes());
+        events.push((0, name));
+    }
+
+    for note in notes {
+        if note.frequency.is_nan() || note.duration <= 0.0 {
+            continue; // Rests take up time, but aren't events
+        }
+
+        let key = frequency_key(note.frequency);
+        let velocity = (note.gain * DEFAULT_VELOCITY)
+            .round()
+            .clamp(1.0, MAX_DATA_BYTE as f32) as u8;
+        let start = tempo_map.ticks(note.start);
+        let end = tempo_map.ticks(note.start + note.duration).max(start + 1);
+
+        events.push((start, vec![NOTE_ON | channel, key, velocity]));
+        events.push((end, vec![NOTE_OFF | channel, key, 0]));
+    }
+
+    encode_track(events)
+}
+
+// Picks the channel for a line of music, leaving out the percussion channel.
+fn line_channel(line: usize) -> u8 {
+    let channel = line % (CHANNEL_COUNT - 1);
+    if channel >= DRUM_CHANNEL {
+        (channel + 1) as u8
+    } else {
+        channel as u8
+    }
+}
+
+// Finds the nearest MIDI key to a frequency, relative to A4 = 440 Hz.
+fn frequency_key(frequency: f32) -> u8 {
+    let key = CONCERT_PITCH_KEY + SEMITONES_PER_OCTAVE * (frequency / CONCERT_PITCH_FREQ).log2();
+    key.round().clamp(0.0, MAX_DATA_BYTE as f32) as u8
+}
+
+fn tempo_event(quarter_duration: f32) -> Vec<u8> {
+    let tempo = (quarter_duration * MICROSECONDS_PER_SECOND).round() as u32;
+    let mut event = vec![META_EVENT, META_TEMPO, 3];
+    event.extend_from_slice(&tempo.to_be_bytes()[1..]);
+    event
+}
+
+fn time_signature_event((numerator, denominator): (u32, u32)) -> Vec<u8> {
+    vec![
+        META_EVENT,
+        META_TIME_SIGNATURE,
+        4,
+        numerator.min(u8::MAX as u32) as u8,
+        denominator.max(1).ilog2() as u8,
+        CLOCKS_PER_CLICK,
+        THIRTY_SECONDS_PER_QUARTER,
+    ]
+}
+
+// Sorts the events of a track by time (note-offs first when events happen at
+// the same time) and writes them with delta times and an end-of-track event.
+fn encode_track(mut events: Vec<(u64, Vec<u8>)>) -> Vec<u8> {
+    events.sort_by_key(|(tick, event)| (*tick, event[0] & 0xF0 != NOTE_OFF));
+
+    let mut track = Vec::new();
+    let mut last_tick = 0;
+    for (tick, event) in &events {
+        write_variable_length(&mut track, tick - last_tick);
+        track.extend_from_slice(event);
+        last_tick = *tick;
+    }
+
+    track.extend_from_slice(&[0, META_EVENT, META_END_OF_TRACK, 0]);
+    track
+}
+
+// Writes a number as a MIDI variable-length quantity (7 bits per byte, most
+// significant first, with the high bit set on every byte but the last).
+fn write_variable_length(bytes: &mut Vec<u8>, mut value: u64) {
+    let mut buffer = vec![(value & 0x7F) as u8];
+    value >>= 7;
+    while value > 0 {
+        buffer.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    buffer.reverse();
+    bytes.extend_from_slice(&buffer);
+}
+
+/// Reads a Standard MIDI File into a score that can be written as Hum text.
+///
+/// Each note time snaps to the nearer of a 1/32 note grid and a 1/16 note
+/// triplet grid.
+/// Each track (or, in a Type 0 file, each channel) becomes a part, named after
+/// the track, and lyric events are attached to the notes that start with them.
+/// The first key signature decides whether black keys are spelled with sharps
+/// or flats.
+///
+/// # Arguments
+///
+/// * `bytes` - The contents of the MIDI file.
+///
+/// # Returns
+///
+/// A `Result` containing the `ImportedScore` or an error message.
+pub fn decode(bytes: &[u8]) -> Result<ImportedScore, String> {
+    if bytes.len() < HEADER_LENGTH || &bytes[0..4] != b"MThd" {
+        return Err("Not a Standard MIDI File".to_string());
+    }
+    let header_length = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    let division = u16::from_be_bytes([bytes[12], bytes[13]]);
+    if division & SMPTE_DIVISION_BIT != 0 || division == 0 {
+        return Err("MIDI files with SMPTE timing are not supported".to_string());
+    }
+
+    let mut reader = MidiReader::default();
+    let mut index = CHUNK_HEADER_LENGTH + header_length;
+    let mut track_index = 0;
+    while index + CHUNK_HEADER_LENGTH <= bytes.len() {
+        let length = u32::from_be_bytes([
+            bytes[index + 4],
+            bytes[index + 5],
+            bytes[index + 6],
+            bytes[index + 7],
+        ]) as usize;
+        let start = index + CHUNK_HEADER_LENGTH;
+        let end = start + length;
+        if end > bytes.len() {
+            return Err("The MIDI file ends in the middle of a track".to_string());
+        }
+        if &bytes[index..index + 4] == b"MTrk" {
+            reader.read_track(&bytes[start..end], track_index)?;
+            track_index += 1;
+        }
+        index = end;
+    }
+
+    reader.into_score(division as u32)
+}
+
+// A note read from a MIDI track: its start tick, end tick, and key.
+type TrackNote = (u64, u64, u8);
+
+// The notes and meta events collected from the tracks of a MIDI file, with
+// times in ticks.
+#[derive(Default)]
+struct MidiReader {
+    // Notes by track and channel.
+    notes: HashMap<(usize, u8), Vec<TrackNote>>,
+    track_names: HashMap<usize, String>,
+    // Lyrics by track: the tick and the syllable.
+    lyrics: HashMap<usize, Vec<(u64, String)>>,
+    tempos: Vec<(u64, u32)>,
+    time_signatures: Vec<(u64, (u32, u32))>,
+    flats: Option<bool>,
+}
+
+impl MidiReader {
+    fn read_track(&mut self, track: &[u8], track_index: usize) -> Result<(), String> {
+        let truncated = || "A MIDI track ends in the middle of an event".to_string();
+        let byte = |index: usize| track.get(index).copied().ok_or_else(truncated);
+
+        let mut index = 0;
+        let mut tick = 0;
+        let mut running_status = 0;
+        let mut sounding: HashMap<(u8, u8), Vec<u64>> = HashMap::new();
+
+        while index < track.len() {
+            tick += read_variable_length(track, &mut index).ok_or_else(truncated)?;
+
+            let mut status = byte(index)?;
+            if status & 0x80 != 0 {
+                index += 1;
+            } else {
+                status = running_status; // The data bytes follow the last status
+            }
+
+            match status {
+                META_EVENT => {
+                    running_status = 0;
+                    let kind = byte(index)?;
+                    index += 1;
+                    let length =
+                        read_variable_length(track, &mut index).ok_or_else(truncated)? as usize;
+                    let end = index.checked_add(length).ok_or_else(truncated)?;
+                    let data = track.get(index..end).ok_or_else(truncated)?;
+                    index = end;
+                    self.read_meta_event(kind, data, tick, track_index);
+                }
+                SYSEX_EVENT | SYSEX_CONTINUATION => {
+                    running_status = 0;
+                    let length =
+                        read_variable_length(track, &mut index).ok_or_else(truncated)? as usize;
+                    let end = index.checked_add(length).ok_or_else(truncated)?;
+                    track.get(index..end).ok_or_else(truncated)?;
+                    index = end;
+                }
+                _ if status & 0x80 == 0 => {
+                    return Err("A MIDI event is missing its status byte".to_string());
+                }
+                _ => {
+                    running_status = status;
+                    let kind = status & 0xF0;
+                    let channel = status & 0x0F;
+                    let data_length = match kind {
+                        PROGRAM_CHANGE | CHANNEL_PRESSURE => 1,
+                        _ => 2,
+                    };
+                    let key = byte(index)?;
+                    let velocity = if data_length == 2 {
+                        byte(index + 1)?
+                    } else {
+                        0
+                    };
+                    index += data_length;
+
+                    match kind {
+                        NOTE_ON if velocity > 0 => {
+                            sounding.entry((channel, key)).or_default().push(tick);
+                        }
+                        // A note on with velocity 0 ends the note, like a note off:
+                        NOTE_ON | NOTE_OFF => {
+                            let started = sounding.get_mut(&(channel, key)).and_then(Vec::pop);
+                            if let Some(start) = started {
+                                self.notes
+                                    .entry((track_index, channel))
+                                    .or_default()
+                                    .push((start, tick, key));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_meta_event(&mut self, kind: u8, data: &[u8], tick: u64, track_index: usize) {
+        match (kind, data) {
+            (META_TRACK_NAME, _) => {
+                let name = String::from_utf8_lossy(data).trim().to_string();
+                self.track_names.entry(track_index).or_insert(name);
+            }
+            (META_LYRIC, _) => {
+                let syllable = String::from_utf8_lossy(data).trim().to_string();
+                if !syllable.is_empty() {
+                    self.lyrics
+                        .entry(track_index)
+                        .or_default()
+                        .push((tick, syllable));
+                }
+            }
+            // Tempos of zero and empty measures can't be played, so they are ignored:
+            (META_TEMPO, &[a, b, c]) if [a, b, c] != [0, 0, 0] => {
+                self.tempos.push((tick, u32::from_be_bytes([0, a, b, c])));
+            }
+            (META_TIME_SIGNATURE, &[numerator, power, ..]) if numerator > 0 => {
+                let denominator = 1u32.checked_shl(power as u32).unwrap_or(4);
+                self.time_signatures
+                    .push((tick, (numerator as u32, denominator)));
+            }
+            (META_KEY_SIGNATURE, &[sharps, ..]) => {
+                self.flats.get_or_insert((sharps as i8) < 0);
+            }
+            _ => {}
+        }
+    }
+
+    fn into_score(self, division: u32) -> Result<ImportedScore, String> {
+        let units_per_tick = UNITS_PER_WHOLE as f64 / (division as f64 * 4.0);
+        let grid = UNITS_PER_WHOLE / GRID_NOTE;
+        let triplet_grid = UNITS_PER_WHOLE / TRIPLET_GRID_NOTE;
+        let too_long = || "The MIDI file is too long".to_string();
+        // Times are snapped in u64, and must fit in the u32 units of a score:
+        let quantize = |tick: u64| {
+            let units = tick as f64 * units_per_tick;
+            let snap =
+                |grid: u32| ((units / grid as f64).round() as u64).saturating_mul(grid as u64);
+            let (straight, triplet) = (snap(grid), snap(triplet_grid));
+            let nearest = if (straight as f64 - units).abs() <= (triplet as f64 - units).abs() {
+                straight
+            } else {
+                triplet
+            };
+            u32::try_from(nearest).map_err(|_| too_long())
+        };
+        let flats = self.flats.unwrap_or(false);
+
+        let mut keys: Vec<&(usize, u8)> = self.notes.keys().collect();
+        keys.sort();
+        let channels_per_track = |track: usize| keys.iter().filter(|(t, _)| *t == track).count();
+
+        let parts = keys
+            .iter()
+            .map(|&&(track, channel)| {
+                let mut name = self.track_names.get(&track).cloned().unwrap_or_default();
+                if channels_per_track(track) > 1 {
+                    name = format!("{} (channel {})", name, channel + 1)
+                        .trim()
+                        .to_string();
+                }
+
+                let lyrics = self.lyrics.get(&track).map_or(&[][..], Vec::as_slice);
+                let notes = self.notes[&(track, channel)]
+                    .iter()
+                    .map(|&(start, end, key)| {
+                        // A syllable belongs to the notes that start on its tick:
+                        let lyric = lyrics
+                            .iter()
+                            .find(|(tick, _)| *tick == start)
+                            .map(|(_, syllable)| syllable.clone());
+                        let start = quantize(start)?;
+                        let end = quantize(end)?.max(start.checked_add(grid).ok_or_else(too_long)?);
+                        Ok(ImportedNote {
+                            start,
+                            length: end - start,
+                            pitch: key_name(key as i32, flats),
+                            lyric,
+                        })
+                    })
+                    .collect::<Result<_, String>>()?;
+
+                Ok(ImportedPart { name, notes })
+            })
+            .collect::<Result<_, String>>()?;
+
+        let mut tempos: Vec<(u32, f32)> = self
+            .tempos
+            .iter()
+            .map(|&(tick, tempo)| {
+                Ok((
+                    quantize(tick)?,
+                    60.0 * MICROSECONDS_PER_SECOND / tempo as f32,
+                ))
+            })
+            .collect::<Result<_, String>>()?;
+        tempos.sort_by_key(|(time, _)| *time);
+
+        let mut time_signatures: Vec<(u32, (u32, u32))> = self
+            .time_signatures
+            .iter()
+            .map(|&(tick, time_signature)| Ok((quantize(tick)?, time_signature)))
+            .collect::<Result<_, String>>()?;
+        time_signatures.sort_by_key(|(time, _)| *time);
+
+        Ok(ImportedScore {
+            title: None,
+            tempos,
+            time_signatures,
+            parts,
+            ..ImportedScore::default()
+        })
+    }
+}
+
+// Reads a MIDI variable-length quantity, or returns `None` if the bytes end
+// in the middle of it or it is longer than the four bytes that SMF allows.
+fn read_variable_length(bytes: &[u8], index: &mut usize) -> Option<u64> {
+    let mut value = 0;
+    for _ in 0..MAX_VARIABLE_LENGTH_BYTES {
+        let byte = *bytes.get(*index)?;
+        *index += 1;
+        value = (value << 7) | (byte & 0x7F) as u64;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variable_length() {
+        for (value, expected) in [
+            (0, vec![0x00]),
+            (0x40, vec![0x40]),
+            (0x7F, vec![0x7F]),
+            (0x80, vec![0x81, 0x00]),
+            (0x2000, vec![0xC0, 0x00]),
+            (0x0FFF_FFFF, vec![0xFF, 0xFF, 0xFF, 0x7F]),
+        ] {
+            let mut bytes = Vec::new();
+            write_variable_length(&mut bytes, value);
+            assert_eq!(bytes, expected);
+            assert_eq!(read_variable_length(&bytes, &mut 0), Some(value));
+        }
+    }
+
+    #[test]
+    fn test_frequency_key() {
+        assert_eq!(frequency_key(440.0), 69);
+        assert_eq!(frequency_key(261.6256), 60);
+        assert_eq!(frequency_key(0.001), 0);
+    }
+
+    #[test]
+    fn test_line_channel_skips_drums() {
+        assert_eq!(line_channel(0), 0);
+        assert_eq!(line_channel(8), 8);
+        assert_eq!(line_channel(9), 10);
+        assert_eq!(line_channel(14), 15);
+        assert_eq!(line_channel(15), 0);
+    }
+}
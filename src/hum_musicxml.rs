@@ -0,0 +1,1093 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::hum_error::GenerateError;
+use crate::hum_export::{WrittenNote, WrittenPart, WrittenScore, read_score};
+use crate::hum_import::{ImportedNote, ImportedPart, ImportedScore, UNITS_PER_WHOLE};
+use crate::hum_parse::LocatedCommand;
+
+const DEFAULT_TIME_SIGNATURE: (u32, u32) = (4, 4);
+const DEFAULT_VOICE: &str = "sine";
+const HYPHEN: char = '-';
+const QUARTERS_PER_WHOLE: u32 = 4;
+const MIDDLE_C_KEY: i32 = 60;
+const CENTS_PER_SEMITONE: f32 = 100.0;
+const STACCATO_MARK: char = '.';
+const ACCENT_MARK: char = '>';
+const TENUTO_MARK: char = '_';
+const FERMATA_MARK: char = '^';
+const TRILL_MARK: &str = "tr";
+const MORDENT_MARK: &str = "mord";
+const TURN_MARK: &str = "turn";
+const GLIDE_MARK: &str = "/>";
+
+const XML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n\
+    <!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 4.0 Partwise//EN\" \
+    \"http://www.musicxml.org/dtds/partwise.dtd\">\n";
+
+/// Encodes a score as an uncompressed MusicXML (partwise) document.
+///
+/// The score is engraved as it is written, so repeats, endings, and signs are
+/// kept as notation rather than played out. Each line of music (counted from
+/// the top of each checkpoint block) becomes a part named after the voice that
+/// plays it, and each checkpoint block starts a new system. A `~` comment
+/// line directly under a line of notes holds its lyrics: each `~` starts a
+/// syllable under the note that it lines up with, and a syllable that ends in
+/// `-` continues into the next one. If the score starts with a comment, it
+/// becomes the title.
+///
+/// # Arguments
+///
+/// * `commands` - The located commands of the score, with includes and motifs
+///   already expanded.
+///
+/// # Returns
+///
+/// A `Result` containing the MusicXML text or a `GenerateError`.
+pub fn encode(commands: &[LocatedCommand]) -> Result<String, GenerateError> {
+    let score = read_score(commands)?;
+    Ok(write_score(&score))
+}
+
+fn write_score(score: &WrittenScore) -> String {
+    let divisions = score_divisions(score);
+    let mut xml = String::from(XML_HEADER);
+    xml.push_str("<score-partwise version=\"4.0\">\n");
+
+    if let Some(ref title) = score.title {
+        let _ = writeln!(
+            xml,
+            "  <work>\n    <work-title>{}</work-title>\n  </work>",
+            escape(title)
+        );
+    }
+    let _ = writeln!(
+        xml,
+        "  <identification>\n    <encoding>\n      <software>Hum {}</software>\n    \
+         </encoding>\n  </identification>",
+        crate::VERSION
+    );
+
+    xml.push_str("  <part-list>\n");
+    for (index, part) in score.parts.iter().enumerate() {
+        let _ = writeln!(
+            xml,
+            "    <score-part id=\"P{}\">\n      <part-name>{}</part-name>\n    </score-part>",
+            index + 1,
+            escape(part.voice.as_deref().unwrap_or(DEFAULT_VOICE))
+        );
+    }
+    xml.push_str("  </part-list>\n");
+
+    for index in 0..score.parts.len() {
+        let _ = writeln!(xml, "  <part id=\"P{}\">", index + 1);
+        for measure_index in 0..score.measures.len() {
+            write_measure(&mut xml, score, index, measure_index, divisions);
+        }
+        xml.push_str("  </part>\n");
+    }
+
+    xml.push_str("</score-partwise>\n");
+    xml
+}
+
+fn write_measure(
+    xml: &mut String,
+    score: &WrittenScore,
+    part_index: usize,
+    index: usize,
+    divisions: u32,
+) {
+    let part = &score.parts[part_index];
+    let measure = &score.measures[index];
+    let notes = part.measures.get(&index).map_or(&[][..], Vec::as_slice);
+    let _ = writeln!(xml, "    <measure number=\"{}\">", index + 1);
+    if measure.new_system {
+        xml.push_str("      <print new-system=\"yes\"/>\n");
+    }
+
+    if measure.repeat_start || measure.ending.is_some() {
+        xml.push_str("      <barline location=\"left\">\n");
+        if measure.repeat_start {
+            xml.push_str("        <bar-style>heavy-light</bar-style>\n");
+            xml.push_str("        <repeat direction=\"forward\"/>\n");
+        }
+        if let Some(number) = measure.ending {
+            let _ = writeln!(
+                xml,
+                "        <ending number=\"{}\" type=\"start\"/>",
+                number
+            );
+        }
+        xml.push_str("      </barline>\n");
+    }
+
+    let previous = index
+        .checked_sub(1)
+        .map(|previous| &score.measures[previous]);
+    let time_changes =
+        previous.is_none_or(|previous| previous.time_signature != measure.time_signature);
+    if index == 0 || time_changes {
+        xml.push_str("      <attributes>\n");
+        if index == 0 {
+            let _ = writeln!(xml, "        <divisions>{}</divisions>", divisions);
+            xml.push_str("        <key>\n          <fifths>0</fifths>\n        </key>\n");
+        }
+        let (beats, beat_type) = measure.time_signature;
+        let _ = writeln!(
+            xml,
+            "        <time>\n          <beats>{}</beats>\n          <beat-type>{}</beat-type>\n        </time>",
+            beats, beat_type
+        );
+        if index == 0 {
+            let (sign, line) = if is_low_part(part) {
+                ("F", 4)
+            } else {
+                ("G", 2)
+            };
+            let _ = writeln!(
+                xml,
+                "        <clef>\n          <sign>{}</sign>\n          <line>{}</line>\n        </clef>",
+                sign, line
+            );
+        }
+        xml.push_str("      </attributes>\n");
+    }
+
+    // Tempo marks and signs are written once, above the top part:
+    if part_index == 0 {
+        if let Some(bpm) = measure.tempo {
+            let (_, beat_type) = measure.time_signature;
+            let _ = writeln!(
+                xml,
+                "      <direction placement=\"above\">\n        <direction-type>\n          \
+                 <metronome>\n            <beat-unit>{}</beat-unit>\n            \
+                 <per-minute>{}</per-minute>\n          </metronome>\n        </direction-type>\n        \
+                 <sound tempo=\"{}\"/>\n      </direction>",
+                note_type(beat_type).unwrap_or("quarter"),
+                bpm,
+                format_number(bpm as f32 * QUARTERS_PER_WHOLE as f32 / beat_type as f32)
+            );
+        }
+        for sign in &measure.signs {
+            let direction_type = match sign.as_str() {
+                "segno" => "<segno/>".to_string(),
+                "coda" => "<coda/>".to_string(),
+                _ => format!("<words>{}</words>", escape(sign)),
+            };
+            let _ = writeln!(
+                xml,
+                "      <direction placement=\"above\">\n        <direction-type>\n          {}\n        \
+                 </direction-type>\n      </direction>",
+                direction_type
+            );
+        }
+    }
+
+    if notes.is_empty() {
+        let (beats, beat_type) = measure.time_signature;
+        let _ = writeln!(
+            xml,
+            "      <note>\n        <rest measure=\"yes\"/>\n        <duration>{}</duration>\n        \
+             <voice>1</voice>\n      </note>",
+            beats * QUARTERS_PER_WHOLE * divisions / beat_type
+        );
+    }
+    for note in notes {
+        write_note(xml, note, divisions);
+    }
+
+    if measure.repeat_end || measure.ending.is_some() {
+        xml.push_str("      <barline location=\"right\">\n");
+        if measure.repeat_end {
+            xml.push_str("        <bar-style>light-heavy</bar-style>\n");
+        }
+        if let Some(number) = measure.ending {
+            // An ending that repeats is closed, and the last ending is left open:
+            let kind = if measure.repeat_end {
+                "stop"
+            } else {
+                "discontinue"
+            };
+            let _ = writeln!(
+                xml,
+                "        <ending number=\"{}\" type=\"{}\"/>",
+                number, kind
+            );
+        }
+        if measure.repeat_end {
+            xml.push_str("        <repeat direction=\"backward\"/>\n");
+        }
+        xml.push_str("      </barline>\n");
+    }
+
+    xml.push_str("    </measure>\n");
+}
+
+fn write_note(xml: &mut String, note: &WrittenNote, divisions: u32) {
+    xml.push_str("      <note>\n");
+    if note.grace {
+        xml.push_str("        <grace/>\n");
+    }
+
+    match note.pitch {
+        Some(pitch) => {
+            xml.push_str("        <pitch>\n");
+            let _ = writeln!(
+                xml,
+                "          <step>{}</step>",
+                pitch.letter.to_ascii_uppercase()
+            );
+            // Cents offsets are written as microtonal alterations:
+            let alter = pitch.accidentals as f32 + note.cents / CENTS_PER_SEMITONE;
+            if alter != 0.0 {
+                let _ = writeln!(xml, "          <alter>{}</alter>", format_number(alter));
+            }
+            let _ = writeln!(xml, "          <octave>{}</octave>", pitch.octave);
+            xml.push_str("        </pitch>\n");
+        }
+        None => xml.push_str("        <rest/>\n"),
+    }
+
+    if !note.grace {
+        let (numerator, denominator) = note_quarters(note);
+        let _ = writeln!(
+            xml,
+            "        <duration>{}</duration>",
+            numerator * divisions / denominator
+        );
+    }
+    xml.push_str("        <voice>1</voice>\n");
+
+    let (numerator, denominator) = note.length;
+    if let Some(notated) = notated_length(numerator, denominator) {
+        let _ = writeln!(xml, "        <type>{}</type>", notated.note_type);
+        for _ in 0..notated.dots + note.dots {
+            xml.push_str("        <dot/>\n");
+        }
+        if let Some((actual, normal)) = notated.tuplet {
+            let _ = writeln!(
+                xml,
+                "        <time-modification>\n          <actual-notes>{}</actual-notes>\n          \
+                 <normal-notes>{}</normal-notes>\n        </time-modification>",
+                actual, normal
+            );
+        }
+    }
+
+    write_notations(xml, &note.marks);
+
+    if let Some(ref lyric) = note.lyric {
+        let syllabic = match (lyric.continues_from, lyric.continues_into) {
+            (false, false) => "single",
+            (false, true) => "begin",
+            (true, true) => "middle",
+            (true, false) => "end",
+        };
+        let _ = writeln!(
+            xml,
+            "        <lyric number=\"1\">\n          <syllabic>{}</syllabic>\n          \
+             <text>{}</text>\n        </lyric>",
+            syllabic,
+            escape(&lyric.text)
+        );
+    }
+
+    xml.push_str("      </note>\n");
+}
+
+fn write_notations(xml: &mut String, marks: &str) {
+    let marks = marks.replace(GLIDE_MARK, "");
+    let mut articulations = String::new();
+    for (mark, element) in [
+        (ACCENT_MARK, "accent"),
+        (STACCATO_MARK, "staccato"),
+        (TENUTO_MARK, "tenuto"),
+    ] {
+        if marks.contains(mark) {
+            let _ = writeln!(articulations, "            <{}/>", element);
+        }
+    }
+
+    let ornament = if marks.contains(TURN_MARK) {
+        Some("turn")
+    } else if marks.contains(MORDENT_MARK) {
+        Some("mordent")
+    } else if marks.contains(TRILL_MARK) {
+        Some("trill-mark")
+    } else {
+        None
+    };
+    let fermata = marks.contains(FERMATA_MARK);
+
+    if articulations.is_empty() && ornament.is_none() && !fermata {
+        return;
+    }
+
+    xml.push_str("        <notations>\n");
+    if !articulations.is_empty() {
+        let _ = write!(
+            xml,
+            "          <articulations>\n{}          </articulations>\n",
+            articulations
+        );
+    }
+    if let Some(ornament) = ornament {
+        let _ = writeln!(
+            xml,
+            "          <ornaments>\n            <{}/>\n          </ornaments>",
+            ornament
+        );
+    }
+    if fermata {
+        xml.push_str("          <fermata type=\"upright\"/>\n");
+    }
+    xml.push_str("        </notations>\n");
+}
+
+// Checks if most of a part lies below middle C, so that it reads better in the
+// bass clef.
+fn is_low_part(part: &WrittenPart) -> bool {
+    let keys: Vec<i32> = part
+        .measures
+        .values()
+        .flatten()
+        .filter_map(|note| note.pitch.map(|pitch| pitch.midi_key()))
+        .collect();
+    !keys.is_empty() && keys.iter().sum::<i32>() < MIDDLE_C_KEY * keys.len() as i32
+}
+
+// How a written length is engraved: a plain note value, its dots, and the
+// tuplet ratio (actual notes to normal notes) that fits it into the beat.
+#[derive(Debug, PartialEq)]
+struct NotatedLength {
+    note_type: &'static str,
+    dots: u32,
+    tuplet: Option<(u32, u32)>,
+}
+
+// Finds how to engrave a written length, or returns `None` if it has no
+// notation (e.g., 5/8 of a whole note).
+fn notated_length(numerator: u32, denominator: u32) -> Option<NotatedLength> {
+    let divisor = gcd(numerator, denominator);
+    let (numerator, denominator) = (numerator / divisor, denominator / divisor);
+
+    let (base, dots) = match numerator {
+        1 => (denominator, 0),
+        3 if denominator % 2 == 0 => (denominator / 2, 1),
+        7 if denominator % 4 == 0 => (denominator / 4, 2),
+        _ => return None,
+    };
+
+    // Tuplets are written as the next larger plain note value, e.g. an eighth
+    // note triplet (1/12) is an eighth note (1/8) played 3 in the time of 2:
+    let plain = 1 << (u32::BITS - 1 - base.leading_zeros());
+    let tuplet = if plain == base {
+        None
+    } else {
+        let divisor = gcd(base, plain);
+        Some((base / divisor, plain / divisor))
+    };
+    Some(NotatedLength {
+        note_type: note_type(plain)?,
+        dots,
+        tuplet,
+    })
+}
+
+fn note_type(denominator: u32) -> Option<&'static str> {
+    match denominator {
+        1 => Some("whole"),
+        2 => Some("half"),
+        4 => Some("quarter"),
+        8 => Some("eighth"),
+        16 => Some("16th"),
+        32 => Some("32nd"),
+        64 => Some("64th"),
+        128 => Some("128th"),
+        256 => Some("256th"),
+        _ => None,
+    }
+}
+
+// Calculates the length of a note in quarter notes, as a reduced fraction.
+fn note_quarters(note: &WrittenNote) -> (u32, u32) {
+    // Each dot adds half the value of the previous one: n/d * (2^(k+1) - 1) / 2^k
+    let (numerator, denominator) = note.length;
+    let numerator = numerator * QUARTERS_PER_WHOLE * ((2 << note.dots) - 1);
+    let denominator = denominator << note.dots;
+    let divisor = gcd(numerator, denominator);
+    (numerator / divisor, denominator / divisor)
+}
+
+// Finds the number of divisions per quarter note that every note and measure
+// in the score can be counted in.
+fn score_divisions(score: &WrittenScore) -> u32 {
+    let note_denominators = score
+        .parts
+        .iter()
+        .flat_map(|part| part.measures.values().flatten())
+        .filter(|note| !note.grace)
+        .map(|note| note_quarters(note).1);
+    let measure_denominators = score.measures.iter().map(|measure| {
+        let (beats, beat_type) = measure.time_signature;
+        beat_type / gcd(beats * QUARTERS_PER_WHOLE, beat_type)
+    });
+
+    note_denominators
+        .chain(measure_denominators)
+        .fold(1, |divisions, denominator| {
+            divisions / gcd(divisions, denominator) * denominator
+        })
+}
+
+fn format_number(value: f32) -> String {
+    if value.fract() == 0.0 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a.max(1) } else { gcd(b, a % b) }
+}
+
+/// Reads an uncompressed partwise MusicXML document into a score that can be
+/// written as Hum text.
+///
+/// Each part keeps its name, ties are joined into single notes, chords and
+/// voices become overlapping notes, and the first lyric of each note is kept.
+/// New systems (and pages) in the first part start new checkpoint blocks, and
+/// `<sound tempo="...">` marks set the tempo. Grace notes are left out.
+///
+/// # Arguments
+///
+/// * `text` - The contents of the MusicXML file.
+///
+/// # Returns
+///
+/// A `Result` containing the `ImportedScore` or an error message.
+pub fn decode(text: &str) -> Result<ImportedScore, String> {
+    let root = XmlReader::new(text).read_document()?;
+    if root.name != "score-partwise" {
+        return Err(format!(
+            "Expected a partwise MusicXML score, but found <{}>",
+            root.name
+        ));
+    }
+
+    let title = root
+        .child("work")
+        .and_then(|work| work.child("work-title"))
+        .or_else(|| root.child("movement-title"))
+        .map(XmlElement::text)
+        .filter(|title| !title.is_empty());
+
+    let part_names: HashMap<&str, String> = root
+        .child("part-list")
+        .into_iter()
+        .flat_map(|part_list| part_list.children_named("score-part"))
+        .filter_map(|score_part| {
+            let name = score_part.child("part-name").map(XmlElement::text);
+            Some((score_part.attribute("id")?, name.unwrap_or_default()))
+        })
+        .collect();
+
+    let mut score = ImportedScore {
+        title,
+        ..ImportedScore::default()
+    };
+    for (index, part) in root.children_named("part").enumerate() {
+        let name = part
+            .attribute("id")
+            .and_then(|id| part_names.get(id))
+            .cloned()
+            .unwrap_or_default();
+        let notes = read_part(part, index == 0, &mut score)?;
+        score.parts.push(ImportedPart { name, notes });
+    }
+
+    score.tempos.sort_by_key(|(time, _)| *time);
+    score.tempos.dedup_by_key(|(time, _)| *time);
+    score.time_signatures.sort_by_key(|(time, _)| *time);
+    score.time_signatures.dedup_by_key(|(time, _)| *time);
+    Ok(score)
+}
+
+// Reads the notes of a part, adding its tempo marks and time signatures (and
+// system breaks, for the first part) to the score.
+fn read_part(
+    part: &XmlElement,
+    first_part: bool,
+    score: &mut ImportedScore,
+) -> Result<Vec<ImportedNote>, String> {
+    let units_per_quarter = (UNITS_PER_WHOLE / QUARTERS_PER_WHOLE) as f32;
+    let mut notes: Vec<ImportedNote> = Vec::new();
+    let mut divisions = 1.0;
+    let mut time_signature = DEFAULT_TIME_SIGNATURE;
+    let mut measure_start: u32 = 0;
+    let mut ties: HashMap<String, usize> = HashMap::new(); // Tied notes by pitch
+    let too_long = || "The MusicXML score is too long".to_string();
+
+    for (measure_index, measure) in part.children_named("measure").enumerate() {
+        let first_note = notes.len();
+        let mut position: i64 = 0; // Units from the start of the measure
+        let mut chord_start = 0;
+
+        for element in measure.elements() {
+            let duration = || {
+                let duration = element
+                    .child("duration")
+                    .map(|duration| duration.text().parse::<f32>())
+                    .unwrap_or(Ok(0.0))
+                    .map_err(|_| "Invalid MusicXML duration".to_string())?;
+                Ok::<i64, String>((duration * units_per_quarter / divisions).round() as i64)
+            };
+            let time = u32::try_from(position.max(0))
+                .ok()
+                .and_then(|position| measure_start.checked_add(position))
+                .ok_or_else(too_long)?;
+
+            match element.name.as_str() {
+                "print" if first_part && measure_index > 0 => {
+                    let breaks = ["new-system", "new-page"]
+                        .iter()
+                        .any(|name| element.attribute(name) == Some("yes"));
+                    if breaks {
+                        score.system_breaks.push(measure_index);
+                    }
+                }
+                "attributes" => {
+                    if let Some(value) = element.child("divisions") {
+                        divisions = value
+                            .text()
+                            .parse::<f32>()
+                            .ok()
+                            .filter(|divisions| *divisions > 0.0)
+                            .ok_or_else(|| "Invalid MusicXML divisions".to_string())?;
+                    }
+                    if let Some(time) = element.child("time") {
+                        time_signature = read_time_signature(time)?;
+                        score.time_signatures.push((measure_start, time_signature));
+                    }
+                }
+                "direction" | "sound" => {
+                    let sound = match element.name.as_str() {
+                        "sound" => Some(element),
+                        _ => element.child("sound"),
+                    };
+                    let tempo = sound
+                        .and_then(|sound| sound.attribute("tempo"))
+                        .and_then(|tempo| tempo.parse::<f32>().ok())
+                        .filter(|tempo| *tempo > 0.0);
+                    if let Some(tempo) = tempo {
+                        score.tempos.push((time, tempo));
+                    }
+                }
+                "backup" => {
+                    position = position.checked_sub(duration()?).ok_or_else(too_long)?;
+                }
+                "forward" => {
+                    position = position.checked_add(duration()?).ok_or_else(too_long)?;
+                }
+                "note" if element.child("grace").is_some() => {}
+                "note" => {
+                    let length = duration()?;
+                    let start = if element.child("chord").is_some() {
+                        chord_start
+                    } else {
+                        chord_start = time;
+                        position = position.checked_add(length).ok_or_else(too_long)?;
+                        time
+                    };
+
+                    let Some(pitch) = element.child("pitch") else {
+                        continue; // Rests are the gaps between notes
+                    };
+                    let pitch = read_pitch_name(pitch)?;
+                    let tie_types: Vec<&str> = element
+                        .children_named("tie")
+                        .filter_map(|tie| tie.attribute("type"))
+                        .collect();
+
+                    // A tied note lengthens the note that it continues:
+                    let continued = ties
+                        .remove(&pitch)
+                        .filter(|_| tie_types.contains(&"stop"))
+                        .filter(|&index| {
+                            notes[index].start.checked_add(notes[index].length) == Some(start)
+                        });
+                    // Every note must end at a time that fits in the score:
+                    let fits = |start: u32, length: i64| {
+                        u32::try_from(length)
+                            .ok()
+                            .filter(|&length| start.checked_add(length).is_some())
+                            .ok_or_else(too_long)
+                    };
+                    let index = match continued {
+                        Some(index) => {
+                            let note = &mut notes[index];
+                            note.length = fits(
+                                note.start,
+                                (note.length as i64).saturating_add(length.max(0)),
+                            )?;
+                            index
+                        }
+                        None => {
+                            notes.push(ImportedNote {
+                                start,
+                                length: fits(start, length.max(1))?,
+                                pitch: pitch.clone(),
+                                lyric: read_lyric(element),
+                            });
+                            notes.len() - 1
+                        }
+                    };
+                    if tie_types.contains(&"start") {
+                        ties.insert(pitch, index);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // A pickup measure is filled with rests before its notes:
+        let (numerator, denominator) = time_signature;
+        let measure_length = numerator * UNITS_PER_WHOLE / denominator.max(1);
+        if measure_index == 0 && measure.attribute("implicit") == Some("yes") {
+            let pickup = position.clamp(0, measure_length as i64) as u32;
+            for note in &mut notes[first_note..] {
+                note.start = (note.start + note.length)
+                    .checked_add(measure_length - pickup)
+                    .map(|end| end - note.length)
+                    .ok_or_else(too_long)?;
+            }
+        }
+        measure_start = measure_start
+            .checked_add(measure_length)
+            .ok_or_else(too_long)?;
+    }
+
+    Ok(notes)
+}
+
+fn read_time_signature(time: &XmlElement) -> Result<(u32, u32), String> {
+    let invalid = || "Invalid MusicXML time signature".to_string();
+    // Beats may be a sum, as in "3+2". A measure must fit in the score's time units:
+    let beats = time
+        .child("beats")
+        .ok_or_else(invalid)?
+        .text()
+        .split('+')
+        .try_fold(0u32, |sum, beats| {
+            sum.checked_add(beats.trim().parse().ok()?)
+        })
+        .filter(|beats| *beats > 0 && beats.checked_mul(UNITS_PER_WHOLE).is_some())
+        .ok_or_else(invalid)?;
+    let beat_type = time
+        .child("beat-type")
+        .and_then(|beat_type| beat_type.text().parse::<u32>().ok())
+        .filter(|beat_type| *beat_type > 0)
+        .ok_or_else(invalid)?;
+    Ok((beats, beat_type))
+}
+
+// Spells a MusicXML pitch as a Hum note name, e.g. `Cs_4`. Microtonal
+// alterations become a cents offset, as in `Cn_4+50c`.
+fn read_pitch_name(pitch: &XmlElement) -> Result<String, String> {
+    let invalid = || "Invalid MusicXML pitch".to_string();
+    let step = pitch
+        .child("step")
+        .map(XmlElement::text)
+        .filter(|step| step.len() == 1)
+        .ok_or_else(invalid)?;
+    let octave = pitch
+        .child("octave")
+        .and_then(|octave| octave.text().parse::<i32>().ok())
+        .ok_or_else(invalid)?;
+    let alter = match pitch.child("alter") {
+        Some(alter) => alter.text().parse::<f32>().map_err(|_| invalid())?,
+        None => 0.0,
+    };
+
+    let semitones = alter.trunc() as i32;
+    let accidentals = match semitones {
+        0 => "n".to_string(),
+        _ if semitones > 0 => "s".repeat(semitones as usize),
+        _ => "f".repeat(semitones.unsigned_abs() as usize),
+    };
+    let name = format!("{}{}_{}", step.to_ascii_uppercase(), accidentals, octave);
+
+    let cents = ((alter - semitones as f32) * CENTS_PER_SEMITONE).round() as i32;
+    if cents == 0 {
+        Ok(name)
+    } else {
+        Ok(format!("{}{:+}c", name, cents))
+    }
+}
+
+// Reads the first lyric of a note. Syllables that continue into the next one
+// end in `-`, as they do in Hum's lyric lines.
+fn read_lyric(note: &XmlElement) -> Option<String> {
+    let lyric = note.child("lyric")?;
+    let text = lyric.child("text")?.text();
+    if text.is_empty() {
+        return None;
+    }
+
+    let syllabic = lyric.child("syllabic").map(XmlElement::text);
+    match syllabic.as_deref() {
+        Some("begin") | Some("middle") => Some(format!("{}{}", text, HYPHEN)),
+        _ => Some(text),
+    }
+}
+
+// An element of an XML document, with its attributes and its child elements
+// and text in order.
+#[derive(Debug, Default)]
+struct XmlElement {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<XmlNode>,
+}
+
+#[derive(Debug)]
+enum XmlNode {
+    Element(XmlElement),
+    Text(String),
+}
+
+impl XmlElement {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &XmlElement> {
+        self.children.iter().filter_map(|child| match child {
+            XmlNode::Element(element) => Some(element),
+            XmlNode::Text(_) => None,
+        })
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.elements().filter(move |element| element.name == name)
+    }
+
+    fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.elements().find(|element| element.name == name)
+    }
+
+    // Returns the text directly inside the element, trimmed.
+    fn text(&self) -> String {
+        let text: String = self
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                XmlNode::Text(text) => Some(text.as_str()),
+                XmlNode::Element(_) => None,
+            })
+            .collect();
+        text.trim().to_string()
+    }
+}
+
+// A small reader for the parts of XML that MusicXML documents use: elements,
+// attributes, text, entities, CDATA, comments, processing instructions, and a
+// document type declaration (which is skipped).
+struct XmlReader<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> XmlReader<'a> {
+    fn new(text: &'a str) -> XmlReader<'a> {
+        XmlReader { text, position: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn error(&self, message: &str) -> String {
+        let line = self.text[..self.position].matches('\n').count() + 1;
+        format!("Invalid XML at line {}: {}", line, message)
+    }
+
+    fn read_document(mut self) -> Result<XmlElement, String> {
+        self.skip_misc()?;
+        if self.rest().starts_with("<!DOCTYPE") {
+            self.skip_doctype()?;
+            self.skip_misc()?;
+        }
+        let root = self.read_element()?;
+        self.skip_misc()?;
+        if !self.rest().is_empty() {
+            return Err(self.error("unexpected content after the root element"));
+        }
+        Ok(root)
+    }
+
+    // Skips whitespace, comments, and processing instructions.
+    fn skip_misc(&mut self) -> Result<(), String> {
+        loop {
+            self.position = self.text.len() - self.rest().trim_start().len();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn skip_past(&mut self, end: &str) -> Result<(), String> {
+        match self.rest().find(end) {
+            Some(index) => {
+                self.position += index + end.len();
+                Ok(())
+            }
+            None => Err(self.error(&format!("missing \"{}\"", end))),
+        }
+    }
+
+    // Skips a document type declaration, including any internal subset.
+    fn skip_doctype(&mut self) -> Result<(), String> {
+        let mut depth = 0;
+        for (index, c) in self.rest().char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                '>' if depth == 0 => {
+                    self.position += index + 1;
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+        Err(self.error("unterminated document type declaration"))
+    }
+
+    fn read_name(&mut self) -> Result<String, String> {
+        let length = self
+            .rest()
+            .find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '=' | '<'))
+            .unwrap_or(self.rest().len());
+        if length == 0 {
+            return Err(self.error("expected a name"));
+        }
+        let name = self.rest()[..length].to_string();
+        self.position += length;
+        Ok(name)
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.position = self.text.len() - self.rest().trim_start().len();
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), String> {
+        if self.rest().starts_with(token) {
+            self.position += token.len();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected \"{}\"", token)))
+        }
+    }
+
+    fn read_element(&mut self) -> Result<XmlElement, String> {
+        self.expect("<")?;
+        let mut element = XmlElement {
+            name: self.read_name()?,
+            ..XmlElement::default()
+        };
+
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.position += 2;
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.position += 1;
+                break;
+            }
+
+            let name = self.read_name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let quote = self
+                .rest()
+                .chars()
+                .next()
+                .filter(|c| matches!(c, '"' | '\''))
+                .ok_or_else(|| self.error("expected a quoted attribute value"))?;
+            self.position += 1;
+            let length = self
+                .rest()
+                .find(quote)
+                .ok_or_else(|| self.error("unterminated attribute value"))?;
+            let value = unescape(&self.rest()[..length]);
+            self.position += length + 1;
+            element.attributes.push((name, value));
+        }
+
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.position += 2;
+                let name = self.read_name()?;
+                if name != element.name {
+                    return Err(self.error(&format!("<{}> is closed by </{}>", element.name, name)));
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                return Ok(element);
+            } else if rest.starts_with("<!--") || rest.starts_with("<?") {
+                self.skip_misc()?;
+            } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let length = cdata
+                    .find("]]>")
+                    .ok_or_else(|| self.error("unterminated CDATA section"))?;
+                element
+                    .children
+                    .push(XmlNode::Text(cdata[..length].to_string()));
+                self.position += "<![CDATA[".len() + length + "]]>".len();
+            } else if rest.starts_with('<') {
+                element
+                    .children
+                    .push(XmlNode::Element(self.read_element()?));
+            } else if rest.is_empty() {
+                return Err(self.error(&format!("<{}> is never closed", element.name)));
+            } else {
+                let length = rest.find('<').unwrap_or(rest.len());
+                element
+                    .children
+                    .push(XmlNode::Text(unescape(&rest[..length])));
+                self.position += length;
+            }
+        }
+    }
+}
+
+// Replaces the predefined entities and character references in XML text.
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(index) = rest.find('&') {
+        result.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let replacement = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse::<u32>))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+
+        match replacement {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notated_length() {
+        let notated = |note_type, dots, tuplet| {
+            Some(NotatedLength {
+                note_type,
+                dots,
+                tuplet,
+            })
+        };
+        assert_eq!(notated_length(1, 4), notated("quarter", 0, None));
+        assert_eq!(notated_length(3, 8), notated("quarter", 1, None));
+        assert_eq!(notated_length(7, 16), notated("quarter", 2, None));
+        assert_eq!(notated_length(1, 12), notated("eighth", 0, Some((3, 2))));
+        assert_eq!(notated_length(1, 20), notated("16th", 0, Some((5, 4))));
+        assert_eq!(notated_length(2, 8), notated("quarter", 0, None));
+        assert_eq!(notated_length(5, 8), None);
+    }
+
+    #[test]
+    fn test_read_xml() {
+        let root = XmlReader::new(
+            "<?xml version=\"1.0\"?>\n<!DOCTYPE a [ <!ENTITY x \"y\"> ]>\n\
+             <a b='1'><!-- note --><c>Tom &amp; &#74;erry</c><d/><![CDATA[<raw>]]></a>",
+        )
+        .read_document()
+        .unwrap();
+        assert_eq!(root.name, "a");
+        assert_eq!(root.attribute("b"), Some("1"));
+        assert_eq!(root.child("c").unwrap().text(), "Tom & Jerry");
+        assert!(root.child("d").is_some());
+        assert_eq!(root.text(), "<raw>");
+
+        assert!(XmlReader::new("<a><b></a>").read_document().is_err());
+        assert!(XmlReader::new("<a>").read_document().is_err());
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("Tom & \"Jerry\" <3"),
+            "Tom &amp; &quot;Jerry&quot; &lt;3"
+        );
+    }
+}
@@ -16,6 +16,12 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use crate::hum_error::{ParseError, SourceLocation};
+use peg::Parse;
+
+/// A parsed command along with the place where it starts in the score.
+pub type LocatedCommand = (SourceLocation, (String, String));
+
 peg::parser! {
     pub grammar hum_grammar() for str {
         pub rule score() -> Vec<(String, String)>
@@ -23,12 +29,30 @@ peg::parser! {
                 commands
             }
 
+        // Like `score`, but also returns the byte offset where each command starts.
+        pub rule located_score() -> Vec<(usize, (String, String))>
+            = commands:(ws()* offset:position!() command:command() { (offset, command) })* {
+                commands
+            }
+
         rule command() -> (String, String)
             = comment()
             / tempo()
             / time()
+            / tuning()
+            / swing()
+            / humanize()
+            / include()
+            / motif()
+            / motif_end()
+            / motif_call()
+            / form()
+            / sign()
             / checkpoint()
             / voice()
+            / repeat_start()
+            / ending()
+            / repeat_end()
             / measure()
             / reset()
             / note()
@@ -48,23 +72,100 @@ peg::parser! {
                 ("time".to_string(), text.to_string())
             }
 
+        pub rule tuning() -> (String, String)
+            = ws()* "[" ws()* "tuning" ws_not_newline()+ text:$((!"]" !['\n'] [_])+) "]" ws()* {
+                ("tuning".to_string(), text.trim().to_string())
+            }
+
+        pub rule swing() -> (String, String)
+            = ws()* "[" ws()* "swing" ws_not_newline()+ amount:$((!"]" !['\n'] !" " [_])+) ws_not_newline()* "]" ws()* {
+                ("swing".to_string(), amount.to_string())
+            }
+
+        pub rule humanize() -> (String, String)
+            = ws()* "[" ws()* "humanize" ws_not_newline()+ params:$((!"]" !['\n'] [_])+) "]" ws()* {
+                ("humanize".to_string(), params.trim().to_string())
+            }
+
+        pub rule include() -> (String, String)
+            = ws()* "[" ws()* "include" ws_not_newline()+ "\"" path:$((!"\"" !['\n'] [_])+) "\"" ws()* "]" ws()* {
+                ("include".to_string(), path.to_string())
+            }
+
+        pub rule motif() -> (String, String)
+            = ws()* "{" ws()* "motif" ws()+ name:$(name()) ws()* "=" ws()* {
+                ("motif".to_string(), name.to_string())
+            }
+
+        pub rule motif_end() -> (String, String)
+            = ws()* "}" ws()* {
+                ("motif_end".to_string(), "}".to_string())
+            }
+
+        pub rule motif_call() -> (String, String)
+            = ws()* "@" call:$(name() (['+' | '-'] ['0'..='9']+)?) ws()* {
+                ("motif_call".to_string(), call.to_string())
+            }
+
+        pub rule form() -> (String, String)
+            = ws()* "[" ws()* "form" ws_not_newline()+ text:$((!"]" !['\n'] [_])+) "]" ws()* {
+                ("form".to_string(), text.split_whitespace().collect::<Vec<&str>>().join(" "))
+            }
+
+        pub rule sign() -> (String, String)
+            = ws()* "[" ws()* text:$(sign_name()) ws()* "]" ws()* {
+                ("sign".to_string(), text.to_string())
+            }
+
+        rule sign_name()
+            = "segno"
+            / "coda"
+            / "to coda"
+            / "fine"
+            / ("D.C." / "D.S.") (" al " ("fine" / "coda"))?
+
+        // Checkpoints may name the section that they start, e.g. "*** verse ***".
         pub rule checkpoint() -> (String, String)
-            = ws()* "*"+ ws()* {
-                // The second value doesn't matter for this one :)
-                ("checkpoint".to_string(), "(｡^‿^｡)".to_string())
+            = ws()* "*"+ ws_not_newline()* section:$(name())? ws_not_newline()* "*"* ws()* {
+                ("checkpoint".to_string(), section.unwrap_or_default().to_string())
             }
 
         pub rule voice() -> (String, String)
-            = ws()* "%" ws()* text:$(name()) ws()* {
-                ("voice".to_string(), text.to_string())
+            = ws()* "%" ws_not_newline()* text:$(name()) params:(ws_not_newline()+ p:$(voice_param()) { p })* ws()* {
+                if params.is_empty() {
+                    ("voice".to_string(), text.to_string())
+                } else {
+                    ("voice".to_string(), format!("{} {}", text, params.join(" ")))
+                }
             }
 
+        // Voice parameters, e.g. the vibrato in "% sine vibrato=6:30".
+        rule voice_param()
+            = name() "=" ['0'..='9' | '.' | ':']+
+
         pub rule measure() -> (String, String)
             = ws()* "|" ws()* {
                 // The second value doesn't matter for this one :)
                 ("measure".to_string(), "(｡￣▽￣｡)θ～♪♪".to_string())
             }
 
+        pub rule repeat_start() -> (String, String)
+            = ws()* "|:" ws()* {
+                // The second value doesn't matter for this one :)
+                ("repeat_start".to_string(), "𝄆".to_string())
+            }
+
+        pub rule ending() -> (String, String)
+            = ws()* "|" number:$(['1'..='9'] ['0'..='9']*) ws()* {
+                ("ending".to_string(), number.to_string())
+            }
+
+        pub rule repeat_end() -> (String, String)
+            = ws()* ":|" ws()* {
+                // The second value doesn't matter for this one :)
+                ("repeat_end".to_string(), "𝄇".to_string())
+            }
+
         pub rule reset() -> (String, String)
             = ws()* ";" ws_not_newline()* text:$((!['\n'][_])*) eol() {
                 ("reset".to_string(), text.trim().to_string())
@@ -72,12 +173,41 @@ peg::parser! {
 
         pub rule note() -> (String, String)
             = ws()*
-            "(" ws()* note_name:$(name()) ws()+ length:$(fraction()) dots_inside:$("+"*) ws()* ")"
-            dots_outside:$("+"*) ws()* {
+            grace:$("g"?) "(" ws()* note_name:$(note_name()) ws()+ length:$(fraction()) dots_inside:$("+"*) ws()* ")"
+            dots_outside:$("+"*) marks:$(note_mark()*) ws()* {
                 let all_dots = format!("{}{}", dots_inside, dots_outside);
-                (note_name.to_string(), format!("{}{}", length, all_dots).to_string())
+                // A grace note is stored with a leading "g" mark:
+                let marks = format!("{}{}", grace, marks);
+                if marks.is_empty() {
+                    (note_name.to_string(), format!("{}{}", length, all_dots).to_string())
+                } else {
+                    (note_name.to_string(), format!("{}{} {}", length, all_dots, marks))
+                }
             }
 
+        // Marks written directly after a note, e.g. the glide in "(Cn_4 1/4)/>",
+        // the articulations staccato ".", accent ">", tenuto "_" and fermata "^",
+        // or the ornaments "tr" (trill), "mord" (mordent) and "turn".
+        rule note_mark()
+            = "/>" / "." / ">" / "_" / "^" / "turn" / "tr" / "mord"
+
+        // Note names may have a negative octave, e.g. "Cn_-1", or a cents offset,
+        // e.g. "An_4-14c", so these minus signs are not treated as whitespace.
+        rule note_name()
+            = (frequency_name() / pitch_name()) cents_offset()?
+
+        rule frequency_name()
+            = decimal() "Hz"
+
+        rule pitch_name()
+            = ['a'..='z' | 'A'..='Z' | '0'..='9']+ ("_" "-"? ['0'..='9']+)?
+
+        rule cents_offset()
+            = ['+' | '-'] decimal() "c"
+
+        rule decimal()
+            = ['0'..='9']+ ("." ['0'..='9']+)?
+
         rule name()
             = ['a'..='z' | 'A'..='Z' | '0'..='9' | '_']+
 
@@ -106,6 +236,81 @@ peg::parser! {
     }
 }
 
+/// Splits the noun of a note command into its length (with dots) and its marks.
+///
+/// For example, the note `(Cn_4 1/4)+/>` is parsed with the noun "1/4+ />",
+/// which splits into "1/4+" and "/>". Notes without marks have empty marks.
+pub fn split_note_noun(noun: &str) -> (&str, &str) {
+    noun.split_once(' ').unwrap_or((noun, ""))
+}
+
+/// Parses a score, keeping the line and column where each command starts so
+/// that errors can point back at the source.
+///
+/// # Returns
+///
+/// A `Result` containing the located commands or a `ParseError`.
+pub fn parse_located_score(score_contents: &str) -> Result<Vec<LocatedCommand>, ParseError> {
+    parse_located_file(score_contents, None)
+}
+
+/// Parses a score like `parse_located_score`, recording the name of the file
+/// that it was read from in each location.
+///
+/// # Returns
+///
+/// A `Result` containing the located commands or a `ParseError`.
+pub fn parse_located_file(
+    score_contents: &str,
+    file: Option<&str>,
+) -> Result<Vec<LocatedCommand>, ParseError> {
+    let commands = hum_grammar::located_score(score_contents)?;
+    Ok(commands
+        .into_iter()
+        .map(|(offset, command)| {
+            let line_col = score_contents.position_repr(offset);
+            (SourceLocation::new(file, line_col), command)
+        })
+        .collect())
+}
+
+/// The mark that a grace note like `g(Dn_5 1/32)` is stored with in its noun.
+pub const GRACE_MARK: char = 'g';
+
+/// Checks if the noun of a note command belongs to a grace note.
+///
+/// Grace notes are parsed with a leading "g" mark, e.g. `g(Dn_5 1/32)` has the
+/// noun "1/32 g".
+pub fn is_grace_note(noun: &str) -> bool {
+    split_note_noun(noun).1.starts_with(GRACE_MARK)
+}
+
+/// Checks if a command verb is a reserved keyword rather than the name of a note.
+pub(crate) fn is_reserved_command(verb: &str) -> bool {
+    matches!(
+        verb,
+        "comment"
+            | "tempo"
+            | "time"
+            | "tuning"
+            | "swing"
+            | "humanize"
+            | "include"
+            | "motif"
+            | "motif_end"
+            | "motif_call"
+            | "form"
+            | "sign"
+            | "checkpoint"
+            | "voice"
+            | "measure"
+            | "repeat_start"
+            | "ending"
+            | "repeat_end"
+            | "reset"
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::hum_grammar;
@@ -124,6 +329,161 @@ mod tests {
             hum_grammar::note("(Bf_3 1/2)++"),
             Ok(("Bf_3".to_string(), "1/2++".to_string()))
         );
+        assert_eq!(
+            hum_grammar::note("(Cn_-1 1/4)"),
+            Ok(("Cn_-1".to_string(), "1/4".to_string()))
+        );
+        assert_eq!(
+            hum_grammar::note("(440Hz 1/4)"),
+            Ok(("440Hz".to_string(), "1/4".to_string()))
+        );
+        assert_eq!(
+            hum_grammar::note("(An_4-14c 1/4)+"),
+            Ok(("An_4-14c".to_string(), "1/4+".to_string()))
+        );
+        assert_eq!(
+            hum_grammar::note("(60.5Hz+3.5c 1/2)"),
+            Ok(("60.5Hz+3.5c".to_string(), "1/2".to_string()))
+        );
+        assert_eq!(
+            hum_grammar::note("(Rest 1/4)"),
+            Ok(("Rest".to_string(), "1/4".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_glide() {
+        assert_eq!(
+            hum_grammar::score("(Cn_4 1/4)+/>(Gn_4 1/4)"),
+            Ok(vec![
+                ("Cn_4".to_string(), "1/4+ />".to_string()),
+                ("Gn_4".to_string(), "1/4".to_string())
+            ])
+        );
+        assert_eq!(super::split_note_noun("1/4+ />"), ("1/4+", "/>"));
+        assert_eq!(super::split_note_noun("1/4+"), ("1/4+", ""));
+    }
+
+    #[test]
+    fn test_parse_articulations() {
+        assert_eq!(
+            hum_grammar::note("(Cn_4 1/4)."),
+            Ok(("Cn_4".to_string(), "1/4 .".to_string()))
+        );
+        assert_eq!(
+            hum_grammar::note("(Cn_4 1/4)+>_^"),
+            Ok(("Cn_4".to_string(), "1/4+ >_^".to_string()))
+        );
+        assert_eq!(
+            hum_grammar::note("(Cn_4 1/4)./>"),
+            Ok(("Cn_4".to_string(), "1/4 ./>".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_grace_notes_and_ornaments() {
+        assert_eq!(
+            hum_grammar::score("g(Dn_5 1/32) (Cn_5 1/4)tr"),
+            Ok(vec![
+                ("Dn_5".to_string(), "1/32 g".to_string()),
+                ("Cn_5".to_string(), "1/4 tr".to_string())
+            ])
+        );
+        assert_eq!(
+            hum_grammar::note("(Cn_5 1/4)+turn^"),
+            Ok(("Cn_5".to_string(), "1/4+ turn^".to_string()))
+        );
+        assert_eq!(
+            hum_grammar::note("g(Bn_4 1/16)>"),
+            Ok(("Bn_4".to_string(), "1/16 g>".to_string()))
+        );
+        assert!(super::is_grace_note("1/16 g>"));
+        assert!(!super::is_grace_note("1/16 >"));
+    }
+
+    #[test]
+    fn test_parse_form_signs() {
+        assert_eq!(
+            hum_grammar::score("|: (Cn_4 1/4) |1 (Dn_4 1/4) :| |2 (En_4 1/4) [ D.S. al coda ]"),
+            Ok(vec![
+                ("repeat_start".to_string(), "𝄆".to_string()),
+                ("Cn_4".to_string(), "1/4".to_string()),
+                ("ending".to_string(), "1".to_string()),
+                ("Dn_4".to_string(), "1/4".to_string()),
+                ("repeat_end".to_string(), "𝄇".to_string()),
+                ("ending".to_string(), "2".to_string()),
+                ("En_4".to_string(), "1/4".to_string()),
+                ("sign".to_string(), "D.S. al coda".to_string()),
+            ])
+        );
+        assert_eq!(
+            hum_grammar::sign("[ to coda ]"),
+            Ok(("sign".to_string(), "to coda".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_located_score() {
+        let commands = super::parse_located_score("[ 60_bpm ]\n| (Cn_4 1/4)").unwrap();
+        let locations: Vec<(usize, usize)> = commands
+            .iter()
+            .map(|(location, _)| (location.line, location.column))
+            .collect();
+        assert_eq!(locations, vec![(1, 1), (2, 1), (2, 3)]);
+    }
+
+    #[test]
+    fn test_parse_sections() {
+        assert_eq!(
+            hum_grammar::checkpoint("*** verse ***\n"),
+            Ok(("checkpoint".to_string(), "verse".to_string()))
+        );
+        assert_eq!(
+            hum_grammar::checkpoint("*****\n"),
+            Ok(("checkpoint".to_string(), "".to_string()))
+        );
+        assert_eq!(
+            hum_grammar::form("[ form intro  verse chorus outro ]"),
+            Ok(("form".to_string(), "intro verse chorus outro".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_include() {
+        assert_eq!(
+            hum_grammar::include("[ include \"parts/drums.hum\" ]"),
+            Ok(("include".to_string(), "parts/drums.hum".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_groove() {
+        assert_eq!(
+            hum_grammar::swing("[ swing 60% ]"),
+            Ok(("swing".to_string(), "60%".to_string()))
+        );
+        assert_eq!(
+            hum_grammar::humanize("[ humanize timing=10ms velocity=5% seed=3 ]"),
+            Ok((
+                "humanize".to_string(),
+                "timing=10ms velocity=5% seed=3".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_motifs() {
+        assert_eq!(
+            hum_grammar::score("{ motif riff = (Cn_4 1/8) } @riff @riff+5 @riff-12 ---"),
+            Ok(vec![
+                ("motif".to_string(), "riff".to_string()),
+                ("Cn_4".to_string(), "1/8".to_string()),
+                ("motif_end".to_string(), "}".to_string()),
+                ("motif_call".to_string(), "riff".to_string()),
+                ("motif_call".to_string(), "riff+5".to_string()),
+                ("motif_call".to_string(), "riff-12".to_string()),
+            ])
+        );
     }
 
     #[test]
@@ -142,12 +502,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_tuning() {
+        assert_eq!(
+            hum_grammar::tuning("[ tuning A4=432 ]"),
+            Ok(("tuning".to_string(), "A4=432".to_string()))
+        );
+        assert_eq!(
+            hum_grammar::tuning("[ tuning just Dn ]"),
+            Ok(("tuning".to_string(), "just Dn".to_string()))
+        );
+    }
+
     #[test]
     fn test_parse_voice() {
         assert_eq!(
             hum_grammar::voice("% piano"),
             Ok(("voice".to_string(), "piano".to_string()))
         );
+        assert_eq!(
+            hum_grammar::voice("% sine  vibrato=6:30 tremolo=4:0.3\n"),
+            Ok((
+                "voice".to_string(),
+                "sine vibrato=6:30 tremolo=4:0.3".to_string()
+            ))
+        );
     }
 
     #[test]
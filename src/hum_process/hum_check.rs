@@ -0,0 +1,414 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use super::{
+    Form, NoteEvent, PlaybackState, apply_command, format_number, measure_order, parse_note_length,
+};
+use crate::hum_error::{GenerateError, IncludeError, SourceLocation};
+use crate::hum_parse::{LocatedCommand, is_grace_note, is_reserved_command, split_note_noun};
+use crate::hum_process::hum_tuning::Tuning;
+use crate::hum_process::hum_voice::Voice;
+use std::fmt;
+
+const LENGTH_TOLERANCE: f32 = 1e-4; // In whole notes
+const QUARTERS_PER_WHOLE_NOTE: f32 = 4.0;
+
+/// How serious a problem found by `check_located_commands` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The score can't be rendered.
+    Error,
+    /// The score can be rendered, but probably doesn't sound as intended.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in a score, and where it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub location: Option<SourceLocation>,
+}
+
+impl Diagnostic {
+    fn warning(message: String, location: Option<SourceLocation>) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            message,
+            location,
+        }
+    }
+
+    /// Describes the diagnostic as a JSON object with `severity`, `message`,
+    /// `file`, `line`, and `column` keys. Unknown locations are `null`.
+    pub fn to_json(&self) -> String {
+        let (file, line, column) = match self.location {
+            Some(ref location) => (
+                location
+                    .file
+                    .as_deref()
+                    .map_or("null".to_string(), json_string),
+                location.line.to_string(),
+                location.column.to_string(),
+            ),
+            None => ("null".to_string(), "null".to_string(), "null".to_string()),
+        };
+        format!(
+            "{{\"severity\": \"{}\", \"message\": {}, \"file\": {}, \"line\": {}, \"column\": {}}}",
+            self.severity,
+            json_string(&self.message),
+            file,
+            line,
+            column
+        )
+    }
+}
+
+impl From<GenerateError> for Diagnostic {
+    fn from(error: GenerateError) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            message: error.message,
+            location: error.location,
+        }
+    }
+}
+
+impl From<IncludeError> for Diagnostic {
+    fn from(error: IncludeError) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            message: error.message,
+            location: error.location,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.location {
+            Some(ref location) => write!(f, "{}: {}: {}", location, self.severity, self.message),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+// The notes written in one line of music for one measure.
+struct Cell {
+    block: usize,   // Checkpoint block that the line is in
+    line: usize,    // Line of music in the block
+    measure: usize, // Index of the written measure
+    length: f32,    // Total length of the notes in whole notes
+    notes: usize,
+    location: Option<SourceLocation>, // Where the bar line that starts it is
+}
+
+/// Checks a score for problems without rendering it.
+///
+/// Every command is read as it would be for rendering (without generating
+/// any samples), and each one that can't be played is reported as an error,
+/// rather than stopping at the first. Warnings are given for unknown voices
+/// (which are played as sine waves), measures whose notes don't add up to
+/// their time signature, lines of music in a checkpoint block that aren't the
+/// same length, and time signature changes in the middle of a measure.
+///
+/// # Arguments
+///
+/// * `score_commands` - The located commands from `hum_parse::parse_located_score`.
+/// * `tuning` - The tuning in effect at the start of the score.
+///
+/// # Returns
+///
+/// The problems found, in the order in which they appear in the score.
+pub fn check_located_commands(
+    score_commands: Vec<LocatedCommand>,
+    tuning: Tuning,
+) -> Vec<Diagnostic> {
+    let (locations, score_commands): (Vec<SourceLocation>, Vec<(String, String)>) =
+        score_commands.into_iter().unzip();
+
+    let mut state = PlaybackState::new(tuning);
+    let mut events: Vec<NoteEvent> = Vec::new();
+    let mut form: Option<Form> = None;
+    let mut diagnostics = Vec::new();
+    let mut cells: Vec<Cell> = Vec::new();
+    let mut in_cell = false;
+    let mut block = 0;
+
+    for (index, (verb, noun)) in score_commands.iter().enumerate() {
+        let location = locations.get(index).cloned();
+        match verb.as_ref() {
+            "tempo" => diagnostics.extend(check_tempo(noun, &location)),
+            "time" => {
+                diagnostics.extend(check_time(noun, &location));
+                if in_cell && cells.last().is_some_and(|cell| cell.notes > 0) {
+                    diagnostics.push(Diagnostic::warning(
+                        format!(
+                            "The time signature changes in the middle of measure {}",
+                            state.measure_index + 1
+                        ),
+                        location.clone(),
+                    ));
+                }
+            }
+            "voice" => diagnostics.extend(check_voice(noun, &location)),
+            "checkpoint" => {
+                in_cell = false;
+                block += 1;
+            }
+            "reset" => in_cell = false,
+            _ if !is_reserved_command(verb) && in_cell && !is_grace_note(noun) => {
+                // Unreadable lengths are reported when the note is applied:
+                let (length, _) = split_note_noun(noun);
+                if let (Ok(length), Some(cell)) = (parse_note_length(length), cells.last_mut()) {
+                    cell.length += length;
+                    cell.notes += 1;
+                }
+            }
+            _ => (),
+        }
+
+        if let Err(mut error) = apply_command(
+            &mut state,
+            &mut events,
+            &mut form,
+            &score_commands,
+            index,
+            location.as_ref(),
+        ) {
+            error.location = location.clone();
+            diagnostics.push(error.into());
+        }
+
+        if matches!(verb.as_ref(), "measure" | "repeat_start" | "ending") {
+            in_cell = true;
+            cells.push(Cell {
+                block,
+                line: state.line_index,
+                measure: state.measure_index as usize,
+                length: 0.0,
+                notes: 0,
+                location,
+            });
+        }
+    }
+
+    if let Err(error) = measure_order(&state, &form, &locations) {
+        diagnostics.push(error.into());
+    }
+    diagnostics.extend(check_measure_lengths(&state, &cells));
+    diagnostics.extend(check_line_lengths(&cells));
+
+    // Keep the problems in reading order, with those that have no location last:
+    diagnostics.sort_by_key(|diagnostic| {
+        diagnostic
+            .location
+            .as_ref()
+            .map(|location| (location.file.clone(), location.line, location.column))
+            .map_or((1, None, 0, 0), |(file, line, column)| {
+                (0, file, line, column)
+            })
+    });
+    diagnostics
+}
+
+/// Describes a list of diagnostics as a JSON array of objects, one per line.
+pub fn to_json(diagnostics: &[Diagnostic]) -> String {
+    if diagnostics.is_empty() {
+        return "[]\n".to_string();
+    }
+    let objects: Vec<String> = diagnostics
+        .iter()
+        .map(|diagnostic| format!("  {}", diagnostic.to_json()))
+        .collect();
+    format!("[\n{}\n]\n", objects.join(",\n"))
+}
+
+// Reports tempos that can be read but can't be played.
+fn check_tempo(noun: &str, location: &Option<SourceLocation>) -> Option<Diagnostic> {
+    noun.parse::<f32>()
+        .ok()
+        .filter(|tempo| !tempo.is_finite() || *tempo <= 0.0)
+        .map(|_| Diagnostic {
+            severity: Severity::Error,
+            message: format!("The tempo must be more than 0 bpm: {}", noun),
+            location: location.clone(),
+        })
+}
+
+// Warns about voices that can be read but aren't built in, since they are
+// played as sine waves.
+fn check_voice(noun: &str, location: &Option<SourceLocation>) -> Option<Diagnostic> {
+    Voice::parse(noun)
+        .ok()
+        .filter(|voice| !voice.is_known())
+        .map(|voice| {
+            Diagnostic::warning(
+                format!(
+                    "There is no voice named {}, so it is played as sine",
+                    voice.name
+                ),
+                location.clone(),
+            )
+        })
+}
+
+// Reports time signatures that can be read but can't be counted.
+fn check_time(noun: &str, location: &Option<SourceLocation>) -> Option<Diagnostic> {
+    let parts: Vec<f32> = noun
+        .split('/')
+        .map(|part| part.parse::<f32>().ok())
+        .collect::<Option<_>>()?;
+    if parts.len() != 2 || parts.iter().all(|part| *part > 0.0) {
+        return None;
+    }
+    Some(Diagnostic {
+        severity: Severity::Error,
+        message: format!(
+            "The time signature must have a numerator and denominator above 0: {}",
+            noun
+        ),
+        location: location.clone(),
+    })
+}
+
+// Warns about lines of music whose notes don't fill a measure exactly, as
+// the first line to reach the measure set its time signature.
+fn check_measure_lengths(state: &PlaybackState, cells: &[Cell]) -> Vec<Diagnostic> {
+    cells
+        .iter()
+        .filter(|cell| cell.notes > 0)
+        .filter_map(|cell| {
+            let (numerator, denominator) = state.measures.get(cell.measure)?.time_signature;
+            let expected = numerator as f32 / denominator as f32;
+            if denominator == 0 || (cell.length - expected).abs() < LENGTH_TOLERANCE {
+                return None;
+            }
+            Some(Diagnostic::warning(
+                format!(
+                    "Measure {} has {} beats in line {} of its block, but {}/{} needs {}",
+                    cell.measure + 1,
+                    format_number(cell.length * denominator as f32),
+                    cell.line + 1,
+                    numerator,
+                    denominator,
+                    numerator
+                ),
+                cell.location.clone(),
+            ))
+        })
+        .collect()
+}
+
+// Warns about lines of music that are shorter or longer than the first line
+// of their checkpoint block.
+fn check_line_lengths(cells: &[Cell]) -> Vec<Diagnostic> {
+    // The total length of each line, and where it starts:
+    let mut lines: Vec<(usize, usize, f32, Option<SourceLocation>)> = Vec::new();
+    for cell in cells {
+        match lines.last_mut() {
+            Some(line) if line.0 == cell.block && line.1 == cell.line => line.2 += cell.length,
+            _ => lines.push((cell.block, cell.line, cell.length, cell.location.clone())),
+        }
+    }
+
+    let mut diagnostics = Vec::new();
+    let mut first: Option<&(usize, usize, f32, Option<SourceLocation>)> = None;
+    for line in &lines {
+        match first {
+            Some(first) if first.0 == line.0 => {
+                if (line.2 - first.2).abs() >= LENGTH_TOLERANCE {
+                    diagnostics.push(Diagnostic::warning(
+                        format!(
+                            "Line {} of this block has {} quarter notes, but line {} has {}",
+                            line.1 + 1,
+                            format_number(line.2 * QUARTERS_PER_WHOLE_NOTE),
+                            first.1 + 1,
+                            format_number(first.2 * QUARTERS_PER_WHOLE_NOTE)
+                        ),
+                        line.3.clone(),
+                    ));
+                }
+            }
+            _ => first = Some(line),
+        }
+    }
+    diagnostics
+}
+
+// Quotes and escapes a string for JSON.
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for character in text.chars() {
+        match character {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_strings_are_escaped() {
+        assert_eq!(
+            json_string("a \"b\"\\\n\u{1}é"),
+            "\"a \\\"b\\\"\\\\\\n\\u0001é\""
+        );
+    }
+
+    #[test]
+    fn test_diagnostics_as_json() {
+        let diagnostics = vec![
+            Diagnostic::warning("Late".to_string(), None),
+            Diagnostic {
+                severity: Severity::Error,
+                message: "Bad".to_string(),
+                location: Some(SourceLocation {
+                    file: Some("a.hum".to_string()),
+                    line: 2,
+                    column: 3,
+                }),
+            },
+        ];
+        assert_eq!(to_json(&[]), "[]\n");
+        assert_eq!(
+            to_json(&diagnostics),
+            "[\n  {\"severity\": \"warning\", \"message\": \"Late\", \"file\": null, \"line\": null, \"column\": null},\n  \
+             {\"severity\": \"error\", \"message\": \"Bad\", \"file\": \"a.hum\", \"line\": 2, \"column\": 3}\n]\n"
+        );
+    }
+}
@@ -0,0 +1,442 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Guards against forms that would never end, in passes through each measure.
+const MAX_PASSES_PER_MEASURE: usize = 64;
+
+/// Where the music stops after a D.C. or D.S. jump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JumpEnd {
+    /// Play to the end of the score (or to a fine, if there is one).
+    End,
+    /// Play to the measure marked with `[ fine ]`.
+    Fine,
+    /// Play to the `[ to coda ]` sign, then skip to the `[ coda ]`.
+    Coda,
+}
+
+/// A jump back to an earlier point of the score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jump {
+    /// Da capo: jump back to the beginning.
+    DaCapo(JumpEnd),
+    /// Dal segno: jump back to the `[ segno ]` sign.
+    DalSegno(JumpEnd),
+}
+
+impl Jump {
+    fn end(&self) -> JumpEnd {
+        match *self {
+            Jump::DaCapo(end) | Jump::DalSegno(end) => end,
+        }
+    }
+}
+
+/// A measure of the score as written, with its duration and form signs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Measure {
+    /// The duration of the measure in seconds.
+    pub duration: f32,
+    /// The time signature in effect when the measure was reached, e.g. `(3, 4)`.
+    pub time_signature: (u32, u32),
+    /// The tempo in effect when the measure was reached, in beats per minute.
+    pub tempo: f32,
+    /// The measure starts with `|:`.
+    pub repeat_start: bool,
+    /// The measure ends with `:|`.
+    pub repeat_end: bool,
+    /// The measure starts a numbered ending, e.g. `|2`.
+    pub ending: Option<u32>,
+    pub segno: bool,
+    pub coda: bool,
+    pub to_coda: bool,
+    pub fine: bool,
+    pub jump: Option<Jump>,
+}
+
+impl Measure {
+    pub fn new(duration: f32) -> Measure {
+        Measure {
+            duration,
+            ..Measure::default()
+        }
+    }
+}
+
+/// A named region of the score, from a named checkpoint up to the next one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    /// The index of the first measure of the section.
+    pub start: usize,
+}
+
+/// Applies a form sign like "segno" or "D.S. al coda" to a measure.
+///
+/// # Arguments
+///
+/// * `measures` - The measures of the score that have been read so far.
+/// * `index` - The index of the measure that the sign is written in.
+/// * `sign` - The text of the sign.
+///
+/// # Returns
+///
+/// A `Result` that is an error message if the sign is unknown or misplaced.
+pub fn apply_sign(measures: &mut [Measure], index: usize, sign: &str) -> Result<(), String> {
+    let measure = &mut measures[index];
+
+    match sign {
+        "segno" => measure.segno = true,
+        "coda" => measure.coda = true,
+        "to coda" => measure.to_coda = true,
+        "fine" => measure.fine = true,
+        _ => {
+            let jump = parse_jump(sign).ok_or_else(|| format!("Unknown form sign: {}", sign))?;
+
+            if matches!(jump, Jump::DalSegno(_)) && !measures[..=index].iter().any(|m| m.segno) {
+                return Err(format!("There is no segno before the {}", sign));
+            }
+
+            measures[index].jump = Some(jump);
+        }
+    }
+
+    Ok(())
+}
+
+// Parses a jump like "D.C." or "D.S. al fine".
+fn parse_jump(sign: &str) -> Option<Jump> {
+    let (target, end) = match sign.split_once(" al ") {
+        Some((target, "fine")) => (target, JumpEnd::Fine),
+        Some((target, "coda")) => (target, JumpEnd::Coda),
+        Some(_) => return None,
+        None => (sign, JumpEnd::End),
+    };
+
+    match target {
+        "D.C." => Some(Jump::DaCapo(end)),
+        "D.S." => Some(Jump::DalSegno(end)),
+        _ => None,
+    }
+}
+
+/// Works out the order that the measures of a score are played in.
+///
+/// Repeats are played twice (or once per numbered ending), and a D.C. or D.S.
+/// jump is taken once. After a jump, repeats are not taken again and only the
+/// last ending of each set of endings is played.
+///
+/// # Arguments
+///
+/// * `measures` - The measures of the score as written.
+///
+/// # Returns
+///
+/// A `Result` containing the measure indices in the order they are played, or
+/// an error message if the form can't be followed.
+pub fn performance_order(measures: &[Measure]) -> Result<Vec<usize>, String> {
+    let endings = ending_spans(measures);
+    let limit = measures.len() * MAX_PASSES_PER_MEASURE;
+
+    let mut order = Vec::new();
+    let mut index = 0;
+    let mut section_start = 0; // Where the next `:|` jumps back to
+    let mut pass = 1; // Which time through the current repeat this is
+    let mut returning = false; // Whether a repeat has just jumped back
+    let mut in_endings = false;
+    let mut jumped: Option<Jump> = None;
+
+    while index < measures.len() {
+        if order.len() > limit {
+            return Err("The repeats in the score never end".to_string());
+        }
+
+        let measure = &measures[index];
+
+        if measure.repeat_start && !returning {
+            section_start = index;
+            pass = 1;
+        }
+        returning = false;
+
+        // Skip the endings that don't belong to this pass:
+        if let Some((number, is_last)) = endings[index] {
+            in_endings = true;
+            let plays = match jumped {
+                Some(_) => is_last,
+                None => number == pass,
+            };
+            if !plays {
+                index += 1;
+                continue;
+            }
+        } else if in_endings {
+            in_endings = false;
+            section_start = index;
+            pass = 1;
+        }
+
+        order.push(index);
+
+        if measure.repeat_end && jumped.is_none() {
+            if endings[index].is_some() || pass == 1 {
+                pass += 1;
+                index = section_start;
+                returning = true;
+                in_endings = false;
+                continue;
+            }
+            section_start = index + 1;
+            pass = 1;
+        }
+
+        match jumped {
+            Some(jump) => {
+                if measure.fine && jump.end() != JumpEnd::Coda {
+                    break;
+                }
+                if measure.to_coda && jump.end() == JumpEnd::Coda {
+                    index = (index + 1..measures.len())
+                        .find(|&i| measures[i].coda)
+                        .ok_or_else(|| "There is no coda after the to coda sign".to_string())?;
+                    continue;
+                }
+            }
+            None => {
+                if let Some(jump) = measure.jump {
+                    jumped = Some(jump);
+                    index = match jump {
+                        Jump::DaCapo(_) => 0,
+                        Jump::DalSegno(_) => (0..=index)
+                            .rev()
+                            .find(|&i| measures[i].segno)
+                            .ok_or_else(|| "There is no segno to jump back to".to_string())?,
+                    };
+                    section_start = index;
+                    pass = 1;
+                    in_endings = false;
+                    continue;
+                }
+            }
+        }
+
+        index += 1;
+    }
+
+    Ok(order)
+}
+
+/// Works out the order that measures are played in for an arrangement like
+/// `[ form intro verse chorus verse ]`.
+///
+/// Each section is played in turn, following the repeats and jumps inside it.
+///
+/// # Arguments
+///
+/// * `measures` - The measures of the score as written.
+/// * `sections` - The named sections of the score, in the order they are written.
+/// * `form` - The names of the sections to play.
+///
+/// # Returns
+///
+/// A `Result` containing the measure indices in the order they are played, or
+/// an error message if a section doesn't exist or can't be followed.
+pub fn arrangement_order(
+    measures: &[Measure],
+    sections: &[Section],
+    form: &[String],
+) -> Result<Vec<usize>, String> {
+    let mut order = Vec::new();
+
+    for name in form {
+        let index = sections
+            .iter()
+            .position(|section| section.name == *name)
+            .ok_or_else(|| format!("There is no section named {}", name))?;
+
+        let end = sections
+            .get(index + 1)
+            .map_or(measures.len(), |next| next.start)
+            .min(measures.len());
+        let start = sections[index].start.min(end);
+
+        let section_order = performance_order(&measures[start..end])?;
+        order.extend(section_order.into_iter().map(|measure| measure + start));
+    }
+
+    Ok(order)
+}
+
+// Finds the numbered ending that each measure belongs to, and whether it is
+// the last ending of its set. An ending lasts until its `:|`, or for just one
+// measure if it has none (like most final endings).
+fn ending_spans(measures: &[Measure]) -> Vec<Option<(u32, bool)>> {
+    let mut spans = vec![None; measures.len()];
+
+    for (start, measure) in measures.iter().enumerate() {
+        let Some(number) = measure.ending else {
+            continue;
+        };
+
+        let mut end = start;
+        for (i, later) in measures.iter().enumerate().skip(start) {
+            if i > start && (later.ending.is_some() || later.repeat_start) {
+                break;
+            }
+            if later.repeat_end {
+                end = i;
+                break;
+            }
+        }
+
+        for span in &mut spans[start..=end] {
+            *span = Some((number, false));
+        }
+    }
+
+    // Mark the highest-numbered ending in each run of endings as the last one:
+    let mut start = 0;
+    while start < spans.len() {
+        if spans[start].is_none() {
+            start += 1;
+            continue;
+        }
+
+        let end = (start..spans.len())
+            .find(|&i| spans[i].is_none())
+            .unwrap_or(spans.len());
+        let highest = spans[start..end].iter().flatten().map(|span| span.0).max();
+
+        for span in spans[start..end].iter_mut().flatten() {
+            span.1 = Some(span.0) == highest;
+        }
+        start = end;
+    }
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measures(count: usize) -> Vec<Measure> {
+        vec![Measure::new(1.0); count]
+    }
+
+    #[test]
+    fn test_plain_order() {
+        assert_eq!(performance_order(&measures(3)), Ok(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn test_repeats() {
+        let mut score = measures(4);
+        score[1].repeat_start = true;
+        score[2].repeat_end = true;
+        assert_eq!(performance_order(&score), Ok(vec![0, 1, 2, 1, 2, 3]));
+
+        // A repeat without a start sign goes back to the beginning:
+        let mut score = measures(3);
+        score[1].repeat_end = true;
+        assert_eq!(performance_order(&score), Ok(vec![0, 1, 0, 1, 2]));
+    }
+
+    #[test]
+    fn test_endings() {
+        let mut score = measures(5);
+        score[0].repeat_start = true;
+        score[1].ending = Some(1);
+        score[2].repeat_end = true;
+        score[3].ending = Some(2);
+        assert_eq!(performance_order(&score), Ok(vec![0, 1, 2, 0, 3, 4]));
+    }
+
+    #[test]
+    fn test_da_capo_al_fine() {
+        let mut score = measures(4);
+        apply_sign(&mut score, 1, "fine").unwrap();
+        apply_sign(&mut score, 3, "D.C. al fine").unwrap();
+        assert_eq!(performance_order(&score), Ok(vec![0, 1, 2, 3, 0, 1]));
+    }
+
+    #[test]
+    fn test_dal_segno_al_coda() {
+        let mut score = measures(6);
+        apply_sign(&mut score, 1, "segno").unwrap();
+        apply_sign(&mut score, 2, "to coda").unwrap();
+        apply_sign(&mut score, 3, "D.S. al coda").unwrap();
+        apply_sign(&mut score, 5, "coda").unwrap();
+        assert_eq!(performance_order(&score), Ok(vec![0, 1, 2, 3, 1, 2, 5]));
+    }
+
+    #[test]
+    fn test_repeats_after_da_capo() {
+        let mut score = measures(5);
+        score[0].repeat_start = true;
+        score[1].ending = Some(1);
+        score[1].repeat_end = true;
+        score[2].ending = Some(2);
+        apply_sign(&mut score, 4, "D.C.").unwrap();
+        assert_eq!(
+            performance_order(&score),
+            Ok(vec![0, 1, 0, 2, 3, 4, 0, 2, 3, 4])
+        );
+    }
+
+    #[test]
+    fn test_arrangement() {
+        let mut score = measures(5);
+        score[2].repeat_end = true;
+        let sections = vec![
+            Section {
+                name: "intro".to_string(),
+                start: 0,
+            },
+            Section {
+                name: "verse".to_string(),
+                start: 1,
+            },
+            Section {
+                name: "chorus".to_string(),
+                start: 3,
+            },
+        ];
+        let form: Vec<String> = ["intro", "verse", "chorus", "verse"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+
+        assert_eq!(
+            arrangement_order(&score, &sections, &form),
+            Ok(vec![0, 1, 2, 1, 2, 3, 4, 1, 2, 1, 2])
+        );
+        assert!(arrangement_order(&score, &sections, &["bridge".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_signs() {
+        let mut score = measures(2);
+        assert!(apply_sign(&mut score, 1, "D.S.").is_err());
+        assert!(apply_sign(&mut score, 1, "D.C. al segno").is_err());
+
+        apply_sign(&mut score, 0, "D.C. al coda").unwrap();
+        apply_sign(&mut score, 0, "to coda").unwrap();
+        assert!(performance_order(&score).is_err());
+    }
+}
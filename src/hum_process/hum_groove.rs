@@ -0,0 +1,221 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+const STRAIGHT_SWING: f32 = 0.5; // Both eighth notes in a pair are the same length
+const OFF_KEYWORD: &str = "off";
+const PERCENT_SUFFIX: char = '%';
+const MILLISECONDS_SUFFIX: &str = "ms";
+const MILLISECONDS_PER_SECOND: f32 = 1000.0;
+const PARAM_SEPARATOR: char = '=';
+const DEFAULT_SEED: u64 = 0;
+
+/// The feel of the music: swing, and random "humanize" jitter in the timing
+/// and loudness of each note.
+///
+/// The jitter comes from a seeded random number generator, so the same score
+/// always renders the same way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Groove {
+    /// The fraction of each beat taken by the first of a pair of eighth notes.
+    pub swing: f32,
+    /// The largest shift in seconds of the start of a note.
+    pub timing: f32,
+    /// The largest change in the volume of a note, as a fraction of it.
+    pub velocity: f32,
+    rng: SplitMix64,
+}
+
+impl Default for Groove {
+    fn default() -> Self {
+        Groove {
+            swing: STRAIGHT_SWING,
+            timing: 0.0,
+            velocity: 0.0,
+            rng: SplitMix64(DEFAULT_SEED),
+        }
+    }
+}
+
+impl Groove {
+    /// Sets the swing from a `[ swing ... ]` directive like "60%" or "off".
+    pub fn set_swing(&mut self, noun: &str) -> Result<(), String> {
+        if noun == OFF_KEYWORD {
+            self.swing = STRAIGHT_SWING;
+            return Ok(());
+        }
+
+        let swing = parse_percentage(noun).ok_or_else(|| format!("Invalid swing: {}", noun))?;
+        if !(swing > 0.0 && swing < 1.0) {
+            return Err(format!("Swing must be between 0% and 100%: {}", noun));
+        }
+        self.swing = swing;
+        Ok(())
+    }
+
+    /// Sets the jitter from a `[ humanize ... ]` directive.
+    ///
+    /// The directive is either "off" or a list of `timing=<ms>ms`,
+    /// `velocity=<percent>%`, and `seed=<number>` parameters. Parameters that
+    /// are not given are turned off, and the random numbers start over from
+    /// the seed.
+    pub fn set_humanize(&mut self, noun: &str) -> Result<(), String> {
+        self.timing = 0.0;
+        self.velocity = 0.0;
+        self.rng = SplitMix64(DEFAULT_SEED);
+
+        if noun == OFF_KEYWORD {
+            return Ok(());
+        }
+
+        for param in noun.split_whitespace() {
+            let invalid = || format!("Invalid humanize parameter: {}", param);
+            let (name, value) = param.split_once(PARAM_SEPARATOR).ok_or_else(invalid)?;
+            match name {
+                "timing" => {
+                    self.timing = value
+                        .strip_suffix(MILLISECONDS_SUFFIX)
+                        .and_then(|ms| ms.parse::<f32>().ok())
+                        .filter(|ms| ms.is_finite() && *ms >= 0.0)
+                        .ok_or_else(invalid)?
+                        / MILLISECONDS_PER_SECOND;
+                }
+                "velocity" => {
+                    self.velocity = parse_percentage(value)
+                        .filter(|velocity| (0.0..=1.0).contains(velocity))
+                        .ok_or_else(invalid)?;
+                }
+                "seed" => self.rng = SplitMix64(value.parse().map_err(|_| invalid())?),
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks if the groove changes the music at all.
+    pub fn is_active(&self) -> bool {
+        self.swing != STRAIGHT_SWING || self.timing > 0.0 || self.velocity > 0.0
+    }
+
+    /// Moves a position in a measure to where it falls with swing.
+    ///
+    /// Each beat is split into two eighth notes, and the first one is
+    /// stretched to take up the swing fraction of the beat. Positions on the
+    /// beat don't move, so the written rhythm still adds up.
+    pub fn swing_position(&self, position: f32, beat_duration: f32) -> f32 {
+        if self.swing == STRAIGHT_SWING || beat_duration <= 0.0 {
+            return position;
+        }
+
+        let beat_start = (position / beat_duration).floor() * beat_duration;
+        let offset = (position - beat_start) / beat_duration;
+        let swung = if offset <= STRAIGHT_SWING {
+            offset * self.swing / STRAIGHT_SWING
+        } else {
+            self.swing + (offset - STRAIGHT_SWING) * (1.0 - self.swing) / STRAIGHT_SWING
+        };
+
+        beat_start + swung * beat_duration
+    }
+
+    /// Returns a random shift in seconds for the start of the next note and a
+    /// factor for its volume.
+    pub fn humanize(&mut self) -> (f32, f32) {
+        if self.timing == 0.0 && self.velocity == 0.0 {
+            return (0.0, 1.0);
+        }
+
+        let shift = self.timing * self.rng.next_signed();
+        let gain = 1.0 + self.velocity * self.rng.next_signed();
+        (shift, gain)
+    }
+}
+
+// Parses a percentage like "60%" into a fraction.
+fn parse_percentage(value: &str) -> Option<f32> {
+    let percent: f32 = value.strip_suffix(PERCENT_SUFFIX)?.parse().ok()?;
+    Some(percent / 100.0)
+}
+
+// A small, fast random number generator (SplitMix64) whose output only depends
+// on its seed.
+#[derive(Debug, Clone, PartialEq)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Returns a random number between -1 and 1.
+    fn next_signed(&mut self) -> f32 {
+        let unit = (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+        unit * 2.0 - 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swing_position() {
+        let mut groove = Groove::default();
+        assert_eq!(groove.swing_position(0.5, 1.0), 0.5);
+
+        groove.set_swing("60%").unwrap();
+        assert_eq!(groove.swing_position(0.0, 1.0), 0.0);
+        assert!((groove.swing_position(0.5, 1.0) - 0.6).abs() < 1e-6);
+        assert!((groove.swing_position(0.75, 1.0) - 0.8).abs() < 1e-6);
+        assert!((groove.swing_position(2.5, 1.0) - 2.6).abs() < 1e-6);
+        assert_eq!(groove.swing_position(3.0, 1.0), 3.0);
+
+        assert!(groove.set_swing("100%").is_err());
+        assert!(groove.set_swing("sixty").is_err());
+        groove.set_swing("off").unwrap();
+        assert_eq!(groove.swing, STRAIGHT_SWING);
+    }
+
+    #[test]
+    fn test_humanize_is_seeded() {
+        let mut groove = Groove::default();
+        assert_eq!(groove.humanize(), (0.0, 1.0));
+
+        groove
+            .set_humanize("timing=10ms velocity=20% seed=7")
+            .unwrap();
+        let first: Vec<(f32, f32)> = (0..8).map(|_| groove.humanize()).collect();
+        groove
+            .set_humanize("timing=10ms velocity=20% seed=7")
+            .unwrap();
+        let second: Vec<(f32, f32)> = (0..8).map(|_| groove.humanize()).collect();
+
+        assert_eq!(first, second);
+        for (shift, gain) in first {
+            assert!(shift.abs() <= 0.01);
+            assert!((0.8..=1.2).contains(&gain));
+        }
+
+        assert!(groove.set_humanize("timing=10").is_err());
+        assert!(groove.set_humanize("swing=10%").is_err());
+    }
+}
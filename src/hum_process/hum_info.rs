@@ -0,0 +1,424 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use super::{
+    Form, NoteEvent, PlaybackState, apply_command, apply_fermatas, arrange_events,
+    arrange_measures, flush_grace_notes, format_number, measure_order, sample_span,
+};
+use crate::SAMPLE_RATE;
+use crate::hum_error::{GenerateError, SourceLocation};
+use crate::hum_parse::{LocatedCommand, is_reserved_command};
+use crate::hum_process::hum_tuning::Tuning;
+use std::fmt;
+
+/// A change of tempo or time signature, at the start of a written measure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<T> {
+    /// The number of the written measure, counting from 1.
+    pub measure: usize,
+    /// When the measure is first played in seconds, if it is played at all.
+    pub start: Option<f32>,
+    /// The new tempo (in beats per minute) or time signature.
+    pub value: T,
+}
+
+/// The notes played by one voice, and the lowest and highest of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoiceRange {
+    /// The name of the voice, e.g. "sine".
+    pub name: String,
+    /// The number of notes written for the voice, not counting rests.
+    pub notes: usize,
+    /// The lowest and highest notes as they are written, e.g. `("Cn_3", "Gn_5")`,
+    /// if the voice plays any notes.
+    pub range: Option<(String, String)>,
+}
+
+/// When a checkpoint block is played.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockTiming {
+    /// The name of the block's section, if its checkpoint names one.
+    pub name: Option<String>,
+    /// The written measures of the block, counting from 1 (inclusive).
+    pub measures: (usize, usize),
+    /// When the block is first played in seconds, if it is played at all.
+    pub start: Option<f32>,
+    /// How long the measures of the block are played for in total, in seconds
+    /// (including repeats).
+    pub duration: f32,
+}
+
+/// A summary of a score's length, meter, voices, and sections.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreInfo {
+    /// How long the rendered audio is in seconds. It ends when the last note
+    /// or rest stops sounding.
+    pub duration: f32,
+    /// How long the played measures are in seconds. This can be longer than
+    /// the audio when the last measure isn't filled.
+    pub notated_duration: f32,
+    /// The number of measures as written.
+    pub written_measures: usize,
+    /// The number of measures played, after repeats and jumps.
+    pub played_measures: usize,
+    /// The tempo of the first measure, and each measure where it changes.
+    pub tempos: Vec<Change<f32>>,
+    /// The time signature of the first measure, and each measure where it changes.
+    pub time_signatures: Vec<Change<(u32, u32)>>,
+    /// The voices in the order in which they are first used.
+    pub voices: Vec<VoiceRange>,
+    /// The checkpoint blocks in the order in which they are written.
+    pub blocks: Vec<BlockTiming>,
+}
+
+/// Reads a score and summarizes it without rendering any audio.
+///
+/// The score is timed as it would be for rendering, so the durations and
+/// start times include repeats, jumps, and fermatas, and the duration is the
+/// length of the audio that rendering would write.
+///
+/// # Arguments
+///
+/// * `score_commands` - The located commands from `hum_parse::parse_located_score`.
+/// * `tuning` - The tuning in effect at the start of the score.
+///
+/// # Returns
+///
+/// A `Result` containing the `ScoreInfo` or a `GenerateError`.
+pub fn describe_located_commands(
+    score_commands: Vec<LocatedCommand>,
+    tuning: Tuning,
+) -> Result<ScoreInfo, GenerateError> {
+    let (locations, score_commands): (Vec<SourceLocation>, Vec<(String, String)>) =
+        score_commands.into_iter().unzip();
+
+    let mut state = PlaybackState::new(tuning);
+    let mut events: Vec<NoteEvent> = Vec::new();
+    let mut form: Option<Form> = None;
+    let mut voices: Vec<VoiceRange> = Vec::new();
+    let mut pitches: Vec<(f32, f32)> = Vec::new(); // Lowest and highest frequency of each voice
+    let mut blocks: Vec<(Option<String>, usize)> = vec![(None, 0)]; // Name and first measure
+
+    for (index, (verb, noun)) in score_commands.iter().enumerate() {
+        if let Err(mut error) = apply_command(
+            &mut state,
+            &mut events,
+            &mut form,
+            &score_commands,
+            index,
+            locations.get(index),
+        ) {
+            error.location = locations.get(index).cloned();
+            return Err(error);
+        }
+
+        if verb == "checkpoint" {
+            let name = Some(noun.clone()).filter(|name| !name.is_empty());
+            blocks.push((name, state.checkpoint_index as usize));
+        } else if verb == "voice" && !voices.iter().any(|voice| voice.name == state.voice.name) {
+            voices.push(VoiceRange {
+                name: state.voice.name.clone(),
+                notes: 0,
+                range: None,
+            });
+            pitches.push((f32::INFINITY, f32::NEG_INFINITY));
+        } else if !is_reserved_command(verb) {
+            let frequency = state.tuning.note_frequency(verb).unwrap_or(f32::NAN);
+            if !frequency.is_nan() {
+                add_note(
+                    &mut voices,
+                    &mut pitches,
+                    &state.voice.name,
+                    verb,
+                    frequency,
+                );
+            }
+        }
+    }
+
+    flush_grace_notes(&mut state, &mut events);
+
+    let order = measure_order(&state, &form, &locations)?;
+    let mut events = arrange_events(events, &state.measures, &order);
+    let mut measures = arrange_measures(&state.measures, &order);
+    apply_fermatas(&mut events, &mut measures);
+
+    // When each written measure is first played:
+    let mut starts: Vec<Option<f32>> = vec![None; state.measures.len()];
+    for (&index, measure) in order.iter().zip(&measures) {
+        starts[index].get_or_insert(measure.start);
+    }
+
+    let mut tempos: Vec<Change<f32>> = Vec::new();
+    let mut time_signatures: Vec<Change<(u32, u32)>> = Vec::new();
+    for (index, measure) in state.measures.iter().enumerate() {
+        if tempos
+            .last()
+            .is_none_or(|change| change.value != measure.tempo)
+        {
+            tempos.push(Change {
+                measure: index + 1,
+                start: starts[index],
+                value: measure.tempo,
+            });
+        }
+        if time_signatures
+            .last()
+            .is_none_or(|change| change.value != measure.time_signature)
+        {
+            time_signatures.push(Change {
+                measure: index + 1,
+                start: starts[index],
+                value: measure.time_signature,
+            });
+        }
+    }
+
+    let block_ends: Vec<usize> = blocks
+        .iter()
+        .skip(1)
+        .map(|&(_, first)| first)
+        .chain([state.measures.len()])
+        .collect();
+    let blocks = blocks
+        .into_iter()
+        .zip(block_ends)
+        .filter(|((_, first), end)| first < end)
+        .map(|((name, first), end)| {
+            let played = || {
+                order
+                    .iter()
+                    .zip(&measures)
+                    .filter(|&(&index, _)| (first..end).contains(&index))
+            };
+            BlockTiming {
+                name,
+                measures: (first + 1, end),
+                start: played().map(|(_, measure)| measure.start).next(),
+                duration: played().map(|(_, measure)| measure.duration).sum(),
+            }
+        })
+        .collect();
+
+    // The audio is as long as the renderer would make it, down to the sample:
+    let samples = events
+        .iter()
+        .map(|event| sample_span(event).1)
+        .max()
+        .unwrap_or(0);
+    let notated_duration = measures
+        .last()
+        .map_or(0.0, |measure| measure.start + measure.duration);
+
+    Ok(ScoreInfo {
+        duration: samples as f32 / SAMPLE_RATE as f32,
+        notated_duration,
+        written_measures: state.measures.len(),
+        played_measures: measures.len(),
+        tempos,
+        time_signatures,
+        voices,
+        blocks,
+    })
+}
+
+// Counts a note played by a voice, widening the voice's range to include it.
+fn add_note(
+    voices: &mut Vec<VoiceRange>,
+    pitches: &mut Vec<(f32, f32)>,
+    voice_name: &str,
+    name: &str,
+    frequency: f32,
+) {
+    // Notes before the first voice command are played by the default voice:
+    let index = match voices.iter().position(|voice| voice.name == voice_name) {
+        Some(index) => index,
+        None => {
+            voices.push(VoiceRange {
+                name: voice_name.to_string(),
+                notes: 0,
+                range: None,
+            });
+            pitches.push((f32::INFINITY, f32::NEG_INFINITY));
+            voices.len() - 1
+        }
+    };
+
+    let voice = &mut voices[index];
+    let (lowest, highest) = &mut pitches[index];
+    voice.notes += 1;
+    let (mut low_name, mut high_name) = voice
+        .range
+        .take()
+        .unwrap_or_else(|| (name.to_string(), name.to_string()));
+    if frequency < *lowest {
+        *lowest = frequency;
+        low_name = name.to_string();
+    }
+    if frequency > *highest {
+        *highest = frequency;
+        high_name = name.to_string();
+    }
+    voice.range = Some((low_name, high_name));
+}
+
+impl fmt::Display for ScoreInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Duration: {} rendered, {} notated",
+            format_time(self.duration),
+            format_time(self.notated_duration)
+        )?;
+        writeln!(
+            f,
+            "Measures: {} written, {} played",
+            self.written_measures, self.played_measures
+        )?;
+
+        writeln!(f, "Tempo:")?;
+        for change in &self.tempos {
+            let bpm = format_number(change.value);
+            writeln!(f, "  {}: {} bpm", format_change(change), bpm)?;
+        }
+        writeln!(f, "Time signatures:")?;
+        for change in &self.time_signatures {
+            let (numerator, denominator) = change.value;
+            writeln!(
+                f,
+                "  {}: {}/{}",
+                format_change(change),
+                numerator,
+                denominator
+            )?;
+        }
+
+        writeln!(f, "Voices:")?;
+        for voice in &self.voices {
+            match voice.range {
+                Some((ref lowest, ref highest)) => writeln!(
+                    f,
+                    "  {}: {} notes from {} to {}",
+                    voice.name, voice.notes, lowest, highest
+                )?,
+                None => writeln!(f, "  {}: rests only", voice.name)?,
+            }
+        }
+
+        writeln!(f, "Blocks:")?;
+        let rows: Vec<[String; 5]> = self
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(index, block)| {
+                [
+                    (index + 1).to_string(),
+                    block.name.clone().unwrap_or_default(),
+                    match block.measures {
+                        (first, last) if first == last => first.to_string(),
+                        (first, last) => format!("{}-{}", first, last),
+                    },
+                    block.start.map_or("-".to_string(), format_time),
+                    format_time(block.duration),
+                ]
+            })
+            .collect();
+        let header = ["Block", "Section", "Measures", "Start", "Duration"].map(String::from);
+        let widths: Vec<usize> = (0..header.len())
+            .map(|column| {
+                rows.iter()
+                    .chain([&header])
+                    .map(|row| row[column].chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        for row in [&header].into_iter().chain(&rows) {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, &width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            writeln!(f, "  {}", cells.join("  ").trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+// Writes where a change happens, e.g. "measure 5 at 0:08.000".
+fn format_change<T>(change: &Change<T>) -> String {
+    match change.start {
+        Some(start) => format!("measure {} at {}", change.measure, format_time(start)),
+        None => format!("measure {} (not played)", change.measure),
+    }
+}
+
+// Writes a time in seconds as minutes and seconds, e.g. "1:05.250".
+fn format_time(seconds: f32) -> String {
+    let milliseconds = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{}:{:02}.{:03}",
+        milliseconds / 60_000,
+        milliseconds / 1000 % 60,
+        milliseconds % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(format_number(120.0), "120");
+        assert_eq!(format_number(92.5), "92.5");
+        assert_eq!(format_number(8.0 / 3.0), "2.667");
+    }
+
+    #[test]
+    fn test_times() {
+        assert_eq!(format_time(0.0), "0:00.000");
+        assert_eq!(format_time(65.25), "1:05.250");
+        assert_eq!(format_time(600.0005), "10:00.001");
+    }
+
+    #[test]
+    fn test_voice_ranges() {
+        let mut voices = Vec::new();
+        let mut pitches = Vec::new();
+        add_note(&mut voices, &mut pitches, "sine", "Cn_4", 261.6);
+        add_note(&mut voices, &mut pitches, "sine", "An_4", 440.0);
+        add_note(&mut voices, &mut pitches, "sine", "Gn_3", 196.0);
+        add_note(&mut voices, &mut pitches, "square", "Cn_2", 65.4);
+        assert_eq!(
+            voices,
+            vec![
+                VoiceRange {
+                    name: "sine".to_string(),
+                    notes: 3,
+                    range: Some(("Gn_3".to_string(), "An_4".to_string())),
+                },
+                VoiceRange {
+                    name: "square".to_string(),
+                    notes: 1,
+                    range: Some(("Cn_2".to_string(), "Cn_2".to_string())),
+                },
+            ]
+        );
+    }
+}
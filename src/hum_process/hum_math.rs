@@ -16,13 +16,11 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-use std::collections::HashMap;
-
+use super::hum_tuning::Tuning;
 use crate::SAMPLE_RATE;
 
-const CONCERT_PITCH_NAME: &str = "An";
-const CONCERT_PITCH_OCTAVE: u8 = 4;
-const CONCERT_PITCH_FREQ: f32 = 440.0;
+pub const SEMITONES_PER_OCTAVE: i32 = 12;
+const MIDI_KEY_OF_C0: i32 = 12;
 
 pub const NOTES_SHARPS: [&str; 12] = [
     "Cn", "Cs", "Dn", "Ds", "En", "Fn", "Fs", "Gn", "Gs", "An", "As", "Bn",
@@ -31,126 +29,252 @@ pub const NOTES_FLATS: [&str; 12] = [
     "Cn", "Df", "Dn", "Ef", "En", "Fn", "Gf", "Gn", "Af", "An", "Bf", "Bn",
 ];
 
-pub const LOWEST_OCTAVE: u8 = 0;
-pub const HIGHEST_OCTAVE: u8 = 7;
+/// The highest frequency that can be represented at the output sample rate.
+pub const NYQUIST_FREQ: f32 = SAMPLE_RATE as f32 / 2.0;
+
+/// A pitch spelled as a letter name, an accidental count, and an octave.
+///
+/// The accidental count is the number of semitones the letter is raised
+/// (positive) or lowered (negative), so `Cf_4` and `Bn_3` are different
+/// spellings of the same sounding pitch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pitch {
+    pub letter: char,
+    pub accidentals: i32,
+    pub octave: i32,
+}
+
+impl Pitch {
+    /// Parses a note name such as `Cn_4`, `Bf_3`, `Fx_4`, or `Bff_3`.
+    ///
+    /// The letter is followed by `n` (natural) or by any number of `s`
+    /// (sharp), `x` (double sharp), or `f` (flat) characters, then `_` and
+    /// the octave number. Sharps and flats cannot be mixed in one name.
+    ///
+    /// Returns `None` if the name is not a valid pitch spelling.
+    pub fn parse(name: &str) -> Option<Pitch> {
+        let (spelling, octave) = name.split_once('_')?;
+        let octave: i32 = octave.parse().ok()?;
+
+        let mut chars = spelling.chars();
+        let letter = chars.next()?;
+        letter_offset(letter)?;
+
+        let accidental_str = chars.as_str();
+        let accidentals = match accidental_str {
+            "n" => 0,
+            "" => return None,
+            _ if accidental_str.chars().all(|c| c == 'f') => -(accidental_str.len() as i32),
+            _ if accidental_str.chars().all(|c| c == 's' || c == 'x') => accidental_str
+                .chars()
+                .map(|c| if c == 'x' { 2 } else { 1 })
+                .sum(),
+            _ => return None,
+        };
+
+        Some(Pitch {
+            letter,
+            accidentals,
+            octave,
+        })
+    }
+
+    /// Returns the number of semitones between `Cn_0` and this pitch.
+    ///
+    /// Accidentals wrap across octave boundaries, so `Cf_4` and `Bn_3` share
+    /// the same index.
+    pub fn semitone_index(&self) -> i32 {
+        // The letter was validated when the pitch was parsed.
+        let offset = letter_offset(self.letter).unwrap_or(0);
+        self.octave * SEMITONES_PER_OCTAVE + offset + self.accidentals
+    }
+
+    /// Returns the MIDI key number of this pitch (`Cn_4` is key 60).
+    pub fn midi_key(&self) -> i32 {
+        self.semitone_index() + MIDI_KEY_OF_C0
+    }
+}
 
-/// Represents the accidental style for note generation (sharps or flats).
-pub enum AccidentalStyle {
-    Sharps,
-    Flats,
+// Returns the semitone offset of a natural letter name above C.
+fn letter_offset(letter: char) -> Option<i32> {
+    match letter {
+        'C' => Some(0),
+        'D' => Some(2),
+        'E' => Some(4),
+        'F' => Some(5),
+        'G' => Some(7),
+        'A' => Some(9),
+        'B' => Some(11),
+        _ => None,
+    }
 }
 
 /// Generates a waveform for a given signal function, frequency, and duration.
 ///
+/// The waveform is produced by a phase-accumulating oscillator, so the
+/// frequency can change continuously over the course of the wave (e.g., for
+/// glides) without discontinuities.
+///
 /// # Arguments
 ///
-/// * `signal` - A closure that takes time and frequency and returns amplitude.
-/// * `frequency` - The frequency of the wave in Hz.
+/// * `signal` - A closure that takes the phase of the oscillator (in cycles) and returns amplitude.
+/// * `frequency` - A closure that takes time in seconds and returns the frequency in Hz.
 /// * `duration` - The duration of the wave in seconds.
 ///
 /// # Returns
 ///
 /// A `Vec<f32>` containing the generated samples.
-pub fn generate_wave(signal: &dyn Fn(f32, f32) -> f32, frequency: f32, duration: f32) -> Vec<f32> {
+pub fn generate_wave(
+    signal: &dyn Fn(f32) -> f32,
+    frequency: &dyn Fn(f32) -> f32,
+    duration: f32,
+) -> Vec<f32> {
     let sample_rate = SAMPLE_RATE as f32; // The number of samples per second
     let num_samples = (sample_rate * duration) as usize;
-    // Find all of the time values in the wave and calculate the function of time (signal):
+    let mut phase = 0.0_f64;
+
+    // Find all of the time values in the wave and calculate the function of phase (signal):
     (0..num_samples)
         .map(|sample_index| sample_index as f32 / sample_rate)
-        .map(|time_in_seconds| signal(time_in_seconds, frequency))
+        .map(|time_in_seconds| {
+            let signal_value = signal(phase as f32);
+            // Advance the phase by the instantaneous frequency, keeping it within one cycle:
+            phase = (phase + frequency(time_in_seconds) as f64 / sample_rate as f64).fract();
+            signal_value
+        })
         .map(|signal_value| signal_value.clamp(-1.0, 1.0))
         .collect()
 }
 
-/// Returns eight octaves of the standard 12 note scale tuned to A 440Hz.
+/// Returns a closure that sweeps exponentially from one frequency to another.
+///
+/// The sweep is linear in pitch, so a glide across an octave spends equal time
+/// in each semitone.
 ///
 /// # Arguments
 ///
-/// * `style` - The accidental style to use ("sharps" or "flats").
+/// * `start_frequency` - The frequency at the start of the sweep in Hz.
+/// * `end_frequency` - The frequency at the end of the sweep in Hz.
+/// * `duration` - The duration of the sweep in seconds.
+pub fn frequency_sweep(
+    start_frequency: f32,
+    end_frequency: f32,
+    duration: f32,
+) -> impl Fn(f32) -> f32 {
+    let ratio = end_frequency / start_frequency;
+    move |time| {
+        if duration > 0.0 {
+            start_frequency * ratio.powf((time / duration).clamp(0.0, 1.0))
+        } else {
+            start_frequency
+        }
+    }
+}
+
+/// Returns the frequency of a pitch in the standard 12 note scale tuned to A 440Hz.
+///
+/// # Arguments
+///
+/// * `pitch` - The pitch to resolve.
 ///
 /// # Returns
 ///
-/// A `HashMap` mapping note names (e.g., "Cn_4") to their frequencies.
-pub fn get_standard_note_frequencies(style: AccidentalStyle) -> HashMap<String, f32> {
-    let note_names = match style {
-        AccidentalStyle::Sharps => &NOTES_SHARPS,
-        AccidentalStyle::Flats => &NOTES_FLATS,
-    };
-
-    calculate_note_frequencies(
-        note_names,
-        LOWEST_OCTAVE,
-        HIGHEST_OCTAVE,
-        CONCERT_PITCH_NAME,
-        CONCERT_PITCH_OCTAVE,
-        CONCERT_PITCH_FREQ,
-    )
+/// The frequency of the pitch in Hz.
+pub fn pitch_frequency(pitch: &Pitch) -> f32 {
+    Tuning::default().frequency(pitch)
 }
 
-// Calculates the frequencies of notes with an arbitrary scale and tuning (concert) pitch:
-fn calculate_note_frequencies(
-    note_names: &[&str],
-    lowest_octave: u8,
-    highest_octave: u8,
-    concert_pitch_name: &str,
-    concert_pitch_octave: u8,
-    concert_pitch_frequency: f32,
-) -> HashMap<String, f32> {
-    assert!(
-        lowest_octave <= highest_octave,
-        "Hum ERR: the lowest octave is indexed higher than the highest octave."
-    );
-    assert!(
-        concert_pitch_octave >= lowest_octave && concert_pitch_octave <= highest_octave,
-        "Hum ERR: the concert pitch octave is outside the range of possible octaves."
-    );
-
-    let notes_per_octave = note_names.len();
-    let root = 2_f32.powf(1_f32 / notes_per_octave as f32);
-
-    let concert_pitch_position = note_names
-        .iter()
-        .position(|&name| name == concert_pitch_name)
-        .expect("Hum ERR: the concert pitch is not represented in the options for note names.");
-
-    let mut note_frequencies: HashMap<String, f32> = HashMap::new();
-
-    for octave in lowest_octave..(highest_octave + 1) {
-        for (position, note) in note_names.iter().enumerate() {
-            let name_offset = position as i32 - concert_pitch_position as i32;
-            let octave_offset = octave as i32 - concert_pitch_octave as i32;
-            let note_offset = name_offset + octave_offset * notes_per_octave as i32;
-            let frequency = concert_pitch_frequency * root.powi(note_offset);
-            note_frequencies.insert(format!("{}_{}", note, octave), frequency);
-        }
-    }
-
-    // Insert the "rest" note with a frequency of "not a number":
-    note_frequencies.insert("Rest".to_string(), f32::NAN);
-
-    note_frequencies
+/// Resolves a note name (e.g., "Cn_4" or "Rest") to a frequency in the standard
+/// tuning.
+///
+/// The special note `Rest` resolves to a frequency of "not a number". Pitches
+/// are computed on demand, so any octave is allowed, including negative
+/// octaves (e.g., `Cn_-1`) for sub-audio effects.
+///
+/// # Returns
+///
+/// The frequency in Hz, or `None` if there is no note with the given name.
+pub fn note_frequency(name: &str) -> Option<f32> {
+    Tuning::default().note_frequency(name)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn assert_close(actual: f32, expected: f32, message: &str) {
+        assert!((actual - expected).abs() < expected * 1e-5, "{}", message);
+    }
+
     #[test]
     fn test_concert_pitch() {
-        let freqs = get_standard_note_frequencies(AccidentalStyle::Sharps);
-        let a4 = freqs.get("An_4").expect("An_4 should exist");
-        assert!((a4 - 440.0).abs() < 0.001, "An_4 should be 440.0 Hz");
+        let a4 = note_frequency("An_4").expect("An_4 should exist");
+        assert_close(a4, 440.0, "An_4 should be 440.0 Hz");
     }
 
     #[test]
     fn test_octave_relationship() {
-        let freqs = get_standard_note_frequencies(AccidentalStyle::Sharps);
-        let a4 = freqs.get("An_4").unwrap();
-        let a5 = freqs.get("An_5").unwrap();
+        let a4 = note_frequency("An_4").unwrap();
+        let a5 = note_frequency("An_5").unwrap();
+        assert_close(a5, a4 * 2.0, "An_5 should be double An_4");
+    }
+
+    #[test]
+    fn test_enharmonic_spellings() {
+        let pairs = [
+            ("Cf_4", "Bn_3"),
+            ("Es_4", "Fn_4"),
+            ("Bs_3", "Cn_4"),
+            ("Fx_4", "Gn_4"),
+            ("Bff_3", "An_3"),
+            ("Css_4", "Dn_4"),
+        ];
+
+        for (spelled, expected) in pairs {
+            let spelled_freq = note_frequency(spelled).expect(spelled);
+            let expected_freq = note_frequency(expected).expect(expected);
+            assert_close(spelled_freq, expected_freq, spelled);
+        }
+    }
 
-        assert!(
-            (a5 - (a4 * 2.0)).abs() < 0.001,
-            "An_5 should be double An_4"
+    #[test]
+    fn test_parse_pitch() {
+        assert_eq!(
+            Pitch::parse("Bff_3"),
+            Some(Pitch {
+                letter: 'B',
+                accidentals: -2,
+                octave: 3
+            })
         );
+        assert_eq!(Pitch::parse("Fsx_4").map(|p| p.accidentals), Some(3));
+        assert_eq!(Pitch::parse("A_4"), None);
+        assert_eq!(Pitch::parse("Csf_4"), None);
+        assert_eq!(Pitch::parse("Hn_4"), None);
+    }
+
+    #[test]
+    fn test_frequency_sweep() {
+        let sweep = frequency_sweep(220.0, 880.0, 2.0);
+        assert_close(sweep(0.0), 220.0, "Sweep starts at the start frequency");
+        assert_close(sweep(1.0), 440.0, "Sweep is halfway in pitch at half time");
+        assert_close(sweep(2.0), 880.0, "Sweep ends at the end frequency");
+    }
+
+    #[test]
+    fn test_generate_wave_phase_continuity() {
+        let wave = generate_wave(&|phase| phase, &|_| 11_025.0, 0.001);
+        assert_eq!(&wave[..5], &[0.0, 0.25, 0.5, 0.75, 0.0]);
+    }
+
+    #[test]
+    fn test_extended_octave_range() {
+        let c4 = note_frequency("Cn_4").unwrap();
+        let c8 = note_frequency("Cn_8").unwrap();
+        let c_neg1 = note_frequency("Cn_-1").unwrap();
+
+        assert_close(c8, c4 * 16.0, "Cn_8 should be four octaves above Cn_4");
+        assert_close(c_neg1, c4 / 32.0, "Cn_-1 should be five octaves below Cn_4");
+        assert_eq!(Pitch::parse("Bs_-1").map(|p| p.semitone_index()), Some(0));
     }
 }
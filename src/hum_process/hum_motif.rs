@@ -0,0 +1,125 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+
+use super::hum_transpose::transpose_note_name;
+use crate::hum_error::{GenerateError, SourceLocation};
+use crate::hum_parse::{LocatedCommand, is_reserved_command};
+
+// Commands other than notes that may be written inside a motif.
+const MOTIF_BODY_COMMANDS: [&str; 2] = ["measure", "comment"];
+
+/// Replaces every motif call in a score with the commands of the motif,
+/// transposed by the number of semitones written after its name.
+///
+/// A motif must be defined before it is called. Motifs may call motifs that
+/// were defined before them, and the definitions themselves are removed.
+pub fn expand(commands: Vec<LocatedCommand>) -> Result<Vec<LocatedCommand>, GenerateError> {
+    let mut motifs: HashMap<String, Vec<LocatedCommand>> = HashMap::new();
+    let mut definition: Option<(SourceLocation, String, Vec<LocatedCommand>)> = None;
+    let mut expanded = Vec::new();
+
+    for (location, (verb, noun)) in commands {
+        let error = |message: String| GenerateError {
+            message,
+            location: Some(location.clone()),
+        };
+
+        match verb.as_ref() {
+            "motif" => {
+                if definition.is_some() {
+                    return Err(error(
+                        "Motifs cannot be defined inside another motif".to_string(),
+                    ));
+                }
+                if motifs.contains_key(&noun) {
+                    return Err(error(format!("There is already a motif named {}", noun)));
+                }
+                definition = Some((location.clone(), noun, Vec::new()));
+            }
+            "motif_end" => match definition.take() {
+                Some((_, name, body)) => {
+                    motifs.insert(name, body);
+                }
+                None => return Err(error("There is no motif to end here".to_string())),
+            },
+            "motif_call" => {
+                let (name, delta) = split_call(&noun).map_err(error)?;
+                let body = motifs
+                    .get(name)
+                    .ok_or_else(|| error(format!("There is no motif named {}", name)))?;
+                let target = match definition {
+                    Some((_, _, ref mut body)) => body,
+                    None => &mut expanded,
+                };
+                for (body_location, (body_verb, body_noun)) in body {
+                    let body_verb = if is_reserved_command(body_verb) {
+                        body_verb.clone()
+                    } else {
+                        transpose_note_name(body_verb, delta).map_err(error)?
+                    };
+                    target.push((body_location.clone(), (body_verb, body_noun.clone())));
+                }
+            }
+            _ => match definition {
+                Some((_, _, ref mut body)) => {
+                    if is_reserved_command(&verb) && !MOTIF_BODY_COMMANDS.contains(&verb.as_str()) {
+                        return Err(error("Motifs can only contain notes and bars".to_string()));
+                    }
+                    body.push((location, (verb, noun)));
+                }
+                None => expanded.push((location, (verb, noun))),
+            },
+        }
+    }
+
+    match definition {
+        Some((location, name, _)) => Err(GenerateError {
+            message: format!("Motif {} is missing its closing brace", name),
+            location: Some(location),
+        }),
+        None => Ok(expanded),
+    }
+}
+
+// Splits a call like "riff+5" into the motif name and the transposition.
+fn split_call(call: &str) -> Result<(&str, i32), String> {
+    match call.find(['+', '-']) {
+        Some(index) => {
+            let delta = call[index..]
+                .parse()
+                .map_err(|_| format!("Invalid transposition in motif call {}", call))?;
+            Ok((&call[..index], delta))
+        }
+        None => Ok((call, 0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_call() {
+        assert_eq!(split_call("riff"), Ok(("riff", 0)));
+        assert_eq!(split_call("riff+5"), Ok(("riff", 5)));
+        assert_eq!(split_call("bass_line-12"), Ok(("bass_line", -12)));
+        assert!(split_call("riff+99999999999").is_err());
+    }
+}
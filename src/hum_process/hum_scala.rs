@@ -0,0 +1,376 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::fs;
+use std::path::Path;
+
+const COMMENT_PREFIX: char = '!';
+const CENTS_PER_OCTAVE: f64 = 1200.0;
+const UNMAPPED_KEY: &str = "x";
+const DEFAULT_MIDDLE_KEY: i32 = 60; // Cn_4
+const DEFAULT_REFERENCE_KEY: i32 = 69; // An_4
+
+/// A microtonal scale, as described by a Scala (.scl) file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scale {
+    /// The description line of the scale.
+    pub description: String,
+    /// The frequency ratio of each scale degree above the tonic, starting with
+    /// degree 1. The last ratio is the period of the scale (usually 2/1).
+    pub ratios: Vec<f64>,
+}
+
+impl Scale {
+    /// Creates a scale that divides the octave into `divisions` equal steps.
+    pub fn equal_divisions(divisions: usize) -> Scale {
+        Scale {
+            description: format!("{} equal divisions of the octave", divisions),
+            ratios: (1..=divisions)
+                .map(|step| 2_f64.powf(step as f64 / divisions as f64))
+                .collect(),
+        }
+    }
+
+    /// Parses the contents of a Scala (.scl) file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the scale or an error message.
+    pub fn parse_scl(contents: &str) -> Result<Scale, String> {
+        let mut lines = data_lines(contents);
+
+        let description = lines
+            .next()
+            .ok_or("Scala file is missing a description line")?
+            .to_string();
+        let count = parse_number::<usize>(lines.next(), "note count")?;
+
+        let ratios = lines
+            .take(count)
+            .map(parse_pitch_line)
+            .collect::<Result<Vec<f64>, String>>()?;
+
+        if ratios.len() != count || count == 0 {
+            return Err(format!(
+                "Scala file declares {} notes but contains {}",
+                count,
+                ratios.len()
+            ));
+        }
+
+        Ok(Scale {
+            description,
+            ratios,
+        })
+    }
+
+    /// Reads and parses a Scala (.scl) file.
+    pub fn from_file(path: &Path) -> Result<Scale, String> {
+        Scale::parse_scl(&read_file(path)?)
+    }
+
+    /// Returns the number of degrees in one period of the scale.
+    pub fn len(&self) -> usize {
+        self.ratios.len()
+    }
+
+    /// Returns `true` if the scale has no degrees.
+    pub fn is_empty(&self) -> bool {
+        self.ratios.is_empty()
+    }
+
+    /// Returns the frequency ratio of a degree above the tonic.
+    ///
+    /// Degrees outside of the first period wrap around, so degree `len()` is
+    /// the period and negative degrees fall below the tonic. Every degree of
+    /// an empty scale is the tonic.
+    pub fn degree_ratio(&self, degree: i32) -> f64 {
+        if self.is_empty() {
+            return 1.0;
+        }
+        let len = self.len() as i32;
+        let period = self.ratios[self.len() - 1];
+        let step = degree.rem_euclid(len) as usize;
+        let step_ratio = if step == 0 {
+            1.0
+        } else {
+            self.ratios[step - 1]
+        };
+        period.powi(degree.div_euclid(len)) * step_ratio
+    }
+}
+
+/// A Scala keyboard mapping (.kbm) that assigns scale degrees to keys.
+///
+/// Keys are numbered like MIDI notes, so `Cn_4` is key 60.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyboardMapping {
+    /// The lowest and highest keys that are mapped (inclusive). Keys outside
+    /// of this range are unmapped.
+    pub key_range: (i32, i32),
+    /// The key where scale degree 0 (the tonic) is mapped.
+    pub middle_key: i32,
+    /// The key that sounds at the reference frequency.
+    pub reference_key: i32,
+    /// The frequency of the reference key in Hz.
+    pub reference_frequency: f64,
+    /// The scale degree that makes up one repetition of the mapping.
+    pub octave_degree: i32,
+    /// The scale degree of each key in one repetition of the mapping, or
+    /// `None` for unmapped keys. An empty mapping maps keys linearly.
+    pub mapping: Vec<Option<i32>>,
+}
+
+impl KeyboardMapping {
+    /// Creates the default linear mapping, with the tonic on `Cn_4` and the
+    /// reference frequency on `An_4`. Every key is mapped.
+    pub fn linear(reference_frequency: f64) -> KeyboardMapping {
+        KeyboardMapping {
+            key_range: (i32::MIN, i32::MAX),
+            middle_key: DEFAULT_MIDDLE_KEY,
+            reference_key: DEFAULT_REFERENCE_KEY,
+            reference_frequency,
+            octave_degree: 0,
+            mapping: Vec::new(),
+        }
+    }
+
+    /// Parses the contents of a Scala keyboard mapping (.kbm) file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the mapping or an error message.
+    pub fn parse_kbm(contents: &str) -> Result<KeyboardMapping, String> {
+        let mut lines = data_lines(contents);
+
+        let size = parse_number::<usize>(lines.next(), "map size")?;
+        let first_key = parse_number::<i32>(lines.next(), "first key")?;
+        let last_key = parse_number::<i32>(lines.next(), "last key")?;
+        let middle_key = parse_number::<i32>(lines.next(), "middle key")?;
+        let reference_key = parse_number::<i32>(lines.next(), "reference key")?;
+        let reference_frequency = parse_number::<f64>(lines.next(), "reference frequency")?;
+        let octave_degree = parse_number::<i32>(lines.next(), "octave degree")?;
+
+        let mapping = lines
+            .take(size)
+            .map(|line| match first_word(line) {
+                UNMAPPED_KEY => Ok(None),
+                word => word
+                    .parse::<i32>()
+                    .map(Some)
+                    .map_err(|_| format!("Invalid keyboard mapping entry: {}", word)),
+            })
+            .collect::<Result<Vec<Option<i32>>, String>>()?;
+
+        if first_key > last_key {
+            return Err(format!(
+                "The first key {} of the keyboard mapping is above the last key {}",
+                first_key, last_key
+            ));
+        }
+        if mapping.len() != size {
+            return Err(format!(
+                "Keyboard mapping declares {} keys but contains {}",
+                size,
+                mapping.len()
+            ));
+        }
+
+        let keyboard_mapping = KeyboardMapping {
+            key_range: (first_key, last_key),
+            middle_key,
+            reference_key,
+            reference_frequency,
+            octave_degree,
+            mapping,
+        };
+
+        if keyboard_mapping.key_degree(reference_key).is_none() {
+            return Err(format!("The reference key {} is not mapped", reference_key));
+        }
+
+        Ok(keyboard_mapping)
+    }
+
+    /// Reads and parses a Scala keyboard mapping (.kbm) file.
+    pub fn from_file(path: &Path) -> Result<KeyboardMapping, String> {
+        KeyboardMapping::parse_kbm(&read_file(path)?)
+    }
+
+    /// Returns the scale degree of a key, or `None` if the key is unmapped.
+    pub fn key_degree(&self, key: i32) -> Option<i32> {
+        let (first_key, last_key) = self.key_range;
+        if key < first_key || key > last_key {
+            return None;
+        }
+
+        let offset = key - self.middle_key;
+
+        if self.mapping.is_empty() {
+            return Some(offset);
+        }
+
+        let size = self.mapping.len() as i32;
+        let repetition = offset.div_euclid(size);
+        self.mapping[offset.rem_euclid(size) as usize]
+            .map(|degree| repetition * self.octave_degree + degree)
+    }
+
+    /// Returns the frequency of a scale degree under this mapping.
+    pub fn degree_frequency(&self, scale: &Scale, degree: i32) -> f64 {
+        // The reference key is validated when the mapping is parsed.
+        let reference_degree = self.key_degree(self.reference_key).unwrap_or(0);
+        self.reference_frequency * scale.degree_ratio(degree) / scale.degree_ratio(reference_degree)
+    }
+}
+
+// Returns the lines of a Scala file that are not comments.
+fn data_lines(contents: &str) -> impl Iterator<Item = &str> {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with(COMMENT_PREFIX))
+        .map(|line| line.trim())
+}
+
+// Returns the first word of a line, ignoring any trailing description.
+fn first_word(line: &str) -> &str {
+    line.split_whitespace().next().unwrap_or("")
+}
+
+// Parses a required numeric field of a Scala file.
+fn parse_number<T: std::str::FromStr>(line: Option<&str>, field: &str) -> Result<T, String> {
+    let line = line.ok_or(format!("Scala file is missing the {}", field))?;
+    first_word(line)
+        .parse::<T>()
+        .map_err(|_| format!("Invalid {} in Scala file: {}", field, line))
+}
+
+// Parses a pitch line of a Scala file into a frequency ratio.
+//
+// Pitches containing a period are in cents; all others are ratios like "3/2" or "2".
+fn parse_pitch_line(line: &str) -> Result<f64, String> {
+    let word = first_word(line);
+    let invalid = || format!("Invalid pitch in Scala file: {}", line);
+
+    let ratio = if word.contains('.') {
+        let cents = word.parse::<f64>().map_err(|_| invalid())?;
+        2_f64.powf(cents / CENTS_PER_OCTAVE)
+    } else if let Some((numerator, denominator)) = word.split_once('/') {
+        let numerator = numerator.parse::<f64>().map_err(|_| invalid())?;
+        let denominator = denominator.parse::<f64>().map_err(|_| invalid())?;
+        numerator / denominator
+    } else {
+        word.parse::<f64>().map_err(|_| invalid())?
+    };
+
+    if ratio.is_finite() && ratio > 0.0 {
+        Ok(ratio)
+    } else {
+        Err(invalid())
+    }
+}
+
+// Reads a Scala file, describing any failure in the error message.
+fn read_file(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|err| format!("Could not read {}: {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PENTATONIC_SCL: &str = "! pentatonic.scl
+!
+Just pentatonic
+ 5
+!
+ 9/8
+ 5/4
+ 3/2
+ 5/3
+ 2/1
+";
+
+    const WHITE_KEYS_KBM: &str = "! Maps the pentatonic scale to C, D, E, G, and A.
+12
+0
+127
+60
+69
+440.0
+5
+0
+x
+1
+x
+2
+x
+x
+3
+x
+4
+x
+x
+";
+
+    #[test]
+    fn test_parse_scl() {
+        let scale = Scale::parse_scl(PENTATONIC_SCL).unwrap();
+        assert_eq!(scale.description, "Just pentatonic");
+        assert_eq!(scale.len(), 5);
+        assert!((scale.degree_ratio(5) - 2.0).abs() < 1e-9);
+        assert!((scale.degree_ratio(-1) - 5.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_cents() {
+        let scale = Scale::parse_scl("Quarter tones\n1\n1200.0 octave\n").unwrap();
+        assert!((scale.degree_ratio(1) - 2.0).abs() < 1e-9);
+        assert!(Scale::parse_scl("Broken\n2\n3/2\n").is_err());
+        assert_eq!(Scale::equal_divisions(0).degree_ratio(3), 1.0);
+    }
+
+    #[test]
+    fn test_parse_kbm() {
+        let mapping = KeyboardMapping::parse_kbm(WHITE_KEYS_KBM).unwrap();
+        assert_eq!(mapping.key_degree(60), Some(0));
+        assert_eq!(mapping.key_degree(61), None);
+        assert_eq!(mapping.key_degree(69), Some(4));
+        assert_eq!(mapping.key_degree(72), Some(5));
+        assert_eq!(mapping.key_degree(57), Some(-1));
+
+        let scale = Scale::parse_scl(PENTATONIC_SCL).unwrap();
+        assert!((mapping.degree_frequency(&scale, 4) - 440.0).abs() < 1e-9);
+        assert!((mapping.degree_frequency(&scale, 0) - 264.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_kbm_key_range() {
+        let narrow = WHITE_KEYS_KBM.replacen("0\n127\n", "60\n72\n", 1);
+        let mapping = KeyboardMapping::parse_kbm(&narrow).unwrap();
+        assert_eq!(mapping.key_range, (60, 72));
+        assert_eq!(mapping.key_degree(72), Some(5));
+        assert_eq!(mapping.key_degree(74), None);
+        assert_eq!(mapping.key_degree(57), None);
+
+        let reversed = WHITE_KEYS_KBM.replacen("0\n127\n", "127\n0\n", 1);
+        assert!(KeyboardMapping::parse_kbm(&reversed).is_err());
+        let missing_reference = WHITE_KEYS_KBM.replacen("0\n127\n", "60\n67\n", 1);
+        assert!(KeyboardMapping::parse_kbm(&missing_reference).is_err());
+    }
+}
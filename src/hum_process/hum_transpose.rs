@@ -0,0 +1,160 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use super::hum_math::{NOTES_FLATS, NOTES_SHARPS, Pitch, SEMITONES_PER_OCTAVE};
+use super::hum_tuning::split_cents_offset;
+
+const CENTS_PER_SEMITONE: f32 = 100.0;
+const CENTS_SUFFIX: char = 'c';
+const REST_NAME: &str = "Rest";
+
+/// Calculates the new note name and octave after transposition.
+///
+/// The result is spelled with sharps if the original note has no flats, and
+/// with flats otherwise (e.g., `Df_4` down a semitone is `Cn_4`).
+pub fn calculate_transposition(
+    note_name: &str,
+    octave: i32,
+    delta: i32,
+) -> Result<(String, i32), String> {
+    let pitch = Pitch::parse(&format!("{}_{}", note_name, octave))
+        .ok_or_else(|| "Invalid note name".to_string())?;
+    let names = if pitch.accidentals < 0 {
+        NOTES_FLATS
+    } else {
+        NOTES_SHARPS
+    };
+
+    let new_pitch_idx = pitch
+        .semitone_index()
+        .checked_add(delta)
+        .ok_or_else(|| "The transposition is out of range".to_string())?;
+    let new_octave = new_pitch_idx.div_euclid(SEMITONES_PER_OCTAVE);
+    let new_note_name = names[new_pitch_idx.rem_euclid(SEMITONES_PER_OCTAVE) as usize];
+
+    Ok((format!("{}_{}", new_note_name, new_octave), new_octave))
+}
+
+/// Transposes a note name from a score by the given number of semitones.
+///
+/// Pitch names are respelled (keeping any cents offset), rests are left
+/// alone, and raw frequencies or scale steps are given a cents offset instead,
+/// so `440Hz` up two semitones becomes `440Hz+200c`.
+///
+/// # Returns
+///
+/// A `Result` containing the transposed name or an error message.
+pub fn transpose_note_name(name: &str, delta: i32) -> Result<String, String> {
+    if name == REST_NAME || delta == 0 {
+        return Ok(name.to_string());
+    }
+
+    let (base, cents) =
+        split_cents_offset(name).ok_or_else(|| format!("Invalid cents offset in note {}", name))?;
+
+    if let Some(pitch) = Pitch::parse(base) {
+        let spelling = base.split('_').next().unwrap_or(base);
+        let (transposed, _) = calculate_transposition(spelling, pitch.octave, delta)?;
+        // Keep the cents offset exactly as it was written.
+        return Ok(format!("{}{}", transposed, &name[base.len()..]));
+    }
+
+    let cents = cents + delta as f32 * CENTS_PER_SEMITONE;
+    if cents == 0.0 {
+        Ok(base.to_string())
+    } else {
+        Ok(format!("{}{:+}{}", base, cents, CENTS_SUFFIX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calculate_transposition_sharps() {
+        // Cn_4 + 1 semitone -> Cs_4
+        let (note, octave) = calculate_transposition("Cn", 4, 1).unwrap();
+        assert_eq!(note, "Cs_4");
+        assert_eq!(octave, 4);
+
+        // Cs_4 + 1 semitone -> Dn_4
+        let (note, octave) = calculate_transposition("Cs", 4, 1).unwrap();
+        assert_eq!(note, "Dn_4");
+        assert_eq!(octave, 4);
+    }
+
+    #[test]
+    fn test_calculate_transposition_flats() {
+        // Df_4 - 1 semitone -> Cn_4
+        let (note, octave) = calculate_transposition("Df", 4, -1).unwrap();
+        assert_eq!(note, "Cn_4");
+        assert_eq!(octave, 4);
+    }
+
+    #[test]
+    fn test_calculate_transposition_octave_crossing() {
+        // Bn_4 + 1 semitone -> Cn_5
+        let (note, octave) = calculate_transposition("Bn", 4, 1).unwrap();
+        assert_eq!(note, "Cn_5");
+        assert_eq!(octave, 5);
+
+        // Cn_4 - 1 semitone -> Bn_3
+        let (note, octave) = calculate_transposition("Cn", 4, -1).unwrap();
+        assert_eq!(note, "Bn_3");
+        assert_eq!(octave, 3);
+    }
+
+    #[test]
+    fn test_calculate_transposition_extended_octaves() {
+        // Bn_7 + 1 semitone -> Cn_8
+        let (note, octave) = calculate_transposition("Bn", 7, 1).unwrap();
+        assert_eq!(note, "Cn_8");
+        assert_eq!(octave, 8);
+
+        // Cn_0 - 1 semitone -> Bn_-1
+        let (note, octave) = calculate_transposition("Cn", 0, -1).unwrap();
+        assert_eq!(note, "Bn_-1");
+        assert_eq!(octave, -1);
+    }
+
+    #[test]
+    fn test_calculate_transposition_enharmonic_spellings() {
+        // Fx_4 + 1 semitone -> Gs_4
+        let (note, _) = calculate_transposition("Fx", 4, 1).unwrap();
+        assert_eq!(note, "Gs_4");
+
+        // Bff_3 + 1 semitone -> Bf_3
+        let (note, _) = calculate_transposition("Bff", 3, 1).unwrap();
+        assert_eq!(note, "Bf_3");
+
+        assert!(calculate_transposition("Hn", 4, 1).is_err());
+        assert!(calculate_transposition("Cn", 4, i32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_transpose_note_name() {
+        assert_eq!(transpose_note_name("Cn_4", 5).unwrap(), "Fn_4");
+        assert_eq!(transpose_note_name("Ef_4", -3).unwrap(), "Cn_4");
+        assert_eq!(transpose_note_name("An_4-14c", 2).unwrap(), "Bn_4-14c");
+        assert_eq!(transpose_note_name("Rest", 7).unwrap(), "Rest");
+        assert_eq!(transpose_note_name("440Hz", 2).unwrap(), "440Hz+200c");
+        assert_eq!(transpose_note_name("s7_4+50c", -1).unwrap(), "s7_4-50c");
+        assert_eq!(transpose_note_name("s7_4+100c", -1).unwrap(), "s7_4");
+    }
+}
@@ -0,0 +1,558 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2018-2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use super::hum_math::{Pitch, SEMITONES_PER_OCTAVE};
+use super::hum_scala::{KeyboardMapping, Scale};
+use std::borrow::Cow;
+use std::path::Path;
+
+const CONCERT_PITCH_LETTER: char = 'A';
+const CONCERT_PITCH_OCTAVE: i32 = 4;
+const DEFAULT_CONCERT_PITCH_FREQ: f32 = 440.0;
+const CONCERT_PITCH_PREFIX: &str = "A4=";
+const CENTS_PER_OCTAVE: f64 = 1200.0;
+const STEP_NOTE_PREFIX: char = 's';
+const FREQUENCY_SUFFIX: &str = "Hz";
+const CENTS_SUFFIX: char = 'c';
+const EDO_SUFFIX: &str = "edo";
+const QUOTE_CHAR: char = '"';
+
+// Frequency ratios above the tonic for 5-limit just intonation:
+const JUST_RATIOS: [f64; 12] = [
+    1.0,
+    16.0 / 15.0,
+    9.0 / 8.0,
+    6.0 / 5.0,
+    5.0 / 4.0,
+    4.0 / 3.0,
+    45.0 / 32.0,
+    3.0 / 2.0,
+    8.0 / 5.0,
+    5.0 / 3.0,
+    9.0 / 5.0,
+    15.0 / 8.0,
+];
+
+// Frequency ratios above C for Pythagorean tuning (a chain of pure fifths from Gf to B):
+const PYTHAGOREAN_RATIOS: [f64; 12] = [
+    1.0,
+    256.0 / 243.0,
+    9.0 / 8.0,
+    32.0 / 27.0,
+    81.0 / 64.0,
+    4.0 / 3.0,
+    729.0 / 512.0,
+    3.0 / 2.0,
+    128.0 / 81.0,
+    27.0 / 16.0,
+    16.0 / 9.0,
+    243.0 / 128.0,
+];
+
+// Cents above C for quarter-comma meantone:
+const MEANTONE_CENTS: [f64; 12] = [
+    0.0, 76.049, 193.157, 310.265, 386.314, 503.422, 579.471, 696.578, 772.627, 889.735, 1006.843,
+    1082.892,
+];
+
+// Cents above C for Werckmeister III (well temperament):
+const WERCKMEISTER_III_CENTS: [f64; 12] = [
+    0.0, 90.225, 192.180, 294.135, 390.225, 498.045, 588.270, 696.090, 792.180, 888.270, 996.090,
+    1092.180,
+];
+
+/// The temperament used to divide the octave into twelve pitches.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Temperament {
+    /// Twelve-tone equal temperament.
+    Equal,
+    /// 5-limit just intonation built on the given tonic pitch class (0 = C).
+    Just(i32),
+    /// Pythagorean tuning built from pure fifths.
+    Pythagorean,
+    /// Quarter-comma meantone.
+    Meantone,
+    /// Andreas Werckmeister's well temperament "III".
+    WerckmeisterIII,
+    /// A microtonal scale with an optional keyboard mapping. Without a
+    /// mapping, keys map linearly onto scale degrees with the tonic on `Cn_4`
+    /// and the concert pitch on `An_4`.
+    Scale(Scale, Option<KeyboardMapping>),
+}
+
+impl Temperament {
+    /// Returns the tonic pitch class and the ratio of each of the twelve
+    /// scale degrees above the tonic.
+    ///
+    /// Microtonal scales are resolved through their keyboard mapping instead,
+    /// so they fall back to equal temperament here.
+    fn degree_ratios(&self) -> (i32, [f64; 12]) {
+        let from_cents = |cents: &[f64; 12]| cents.map(|c| 2_f64.powf(c / CENTS_PER_OCTAVE));
+
+        match self {
+            Temperament::Equal | Temperament::Scale(_, _) => (
+                0,
+                std::array::from_fn(|degree| {
+                    2_f64.powf(degree as f64 / SEMITONES_PER_OCTAVE as f64)
+                }),
+            ),
+            Temperament::Just(tonic) => (tonic.rem_euclid(SEMITONES_PER_OCTAVE), JUST_RATIOS),
+            Temperament::Pythagorean => (0, PYTHAGOREAN_RATIOS),
+            Temperament::Meantone => (0, from_cents(&MEANTONE_CENTS)),
+            Temperament::WerckmeisterIII => (0, from_cents(&WERCKMEISTER_III_CENTS)),
+        }
+    }
+}
+
+/// A tuning system: a concert pitch for A4 and a temperament.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tuning {
+    /// The frequency of `An_4` in Hz.
+    pub concert_pitch: f32,
+    /// The temperament used for the other pitches.
+    pub temperament: Temperament,
+}
+
+impl Default for Tuning {
+    fn default() -> Tuning {
+        Tuning::new(DEFAULT_CONCERT_PITCH_FREQ, Temperament::Equal)
+    }
+}
+
+impl Tuning {
+    /// Creates a tuning with the given concert pitch (A4) and temperament.
+    pub fn new(concert_pitch: f32, temperament: Temperament) -> Tuning {
+        Tuning {
+            concert_pitch,
+            temperament,
+        }
+    }
+
+    /// Returns the frequency of a pitch in this tuning.
+    ///
+    /// Enharmonic spellings (e.g., `Cs_4` and `Df_4`) share one frequency,
+    /// because pitches are mapped onto keys of a twelve-tone keyboard. Keys
+    /// that a keyboard mapping leaves unmapped are silent and resolve to a
+    /// frequency of "not a number".
+    pub fn frequency(&self, pitch: &Pitch) -> f32 {
+        match &self.temperament {
+            Temperament::Scale(scale, mapping) => {
+                let mapping = self.keyboard_mapping(mapping.as_ref());
+                match mapping.key_degree(pitch.midi_key()) {
+                    Some(degree) => mapping.degree_frequency(scale, degree) as f32,
+                    None => f32::NAN,
+                }
+            }
+            _ => self.semitone_frequency(pitch.semitone_index()),
+        }
+    }
+
+    /// Returns the frequency of a scale step in a given octave.
+    ///
+    /// For microtonal scales, step 0 of octave 4 is the tonic of the scale and
+    /// each octave spans one period. For twelve-tone temperaments, each step is
+    /// a semitone above `Cn` of the given octave.
+    pub fn step_frequency(&self, step: i32, octave: i32) -> f32 {
+        match &self.temperament {
+            Temperament::Scale(scale, mapping) => {
+                let degree = (octave - CONCERT_PITCH_OCTAVE) * scale.len() as i32 + step;
+                self.keyboard_mapping(mapping.as_ref())
+                    .degree_frequency(scale, degree) as f32
+            }
+            _ => self.semitone_frequency(octave * SEMITONES_PER_OCTAVE + step),
+        }
+    }
+
+    /// Returns the number of scale steps in an octave (or, for microtonal
+    /// scales, in one period).
+    pub fn steps_per_octave(&self) -> i32 {
+        match &self.temperament {
+            Temperament::Scale(scale, _) => scale.len() as i32,
+            _ => SEMITONES_PER_OCTAVE,
+        }
+    }
+
+    /// Finds the scale step and octave of a note name, so that
+    /// `step_frequency` returns its frequency without any cents offset.
+    ///
+    /// # Returns
+    ///
+    /// The step and octave, or `None` for raw frequencies, unmapped keys, and
+    /// names that are not notes.
+    pub fn note_step(&self, name: &str) -> Option<(i32, i32)> {
+        let (name, _) = split_cents_offset(name)?;
+        if let Some(step) = parse_step_name(name) {
+            return Some(step);
+        }
+
+        let pitch = Pitch::parse(name)?;
+        let (steps, base_octave) = match &self.temperament {
+            Temperament::Scale(_, mapping) => {
+                let mapping = self.keyboard_mapping(mapping.as_ref());
+                (mapping.key_degree(pitch.midi_key())?, CONCERT_PITCH_OCTAVE)
+            }
+            _ => (pitch.semitone_index(), 0),
+        };
+        let len = self.steps_per_octave();
+        Some((steps.rem_euclid(len), base_octave + steps.div_euclid(len)))
+    }
+
+    /// Resolves a note name (e.g., "Cn_4", "s7_4", "440Hz", or "Rest") to a
+    /// frequency in this tuning.
+    ///
+    /// The special note `Rest` resolves to a frequency of "not a number".
+    /// Names of the form `s{step}_{octave}` refer to steps of the scale, and
+    /// names ending in `Hz` are raw frequencies that bypass the tuning. Any
+    /// name may end with a cents offset, as in `An_4-14c` or `440Hz+5c`.
+    ///
+    /// # Returns
+    ///
+    /// The frequency in Hz, or `None` if there is no note with the given name.
+    pub fn note_frequency(&self, name: &str) -> Option<f32> {
+        if name == "Rest" {
+            return Some(f32::NAN);
+        }
+
+        let (name, cents) = split_cents_offset(name)?;
+
+        let frequency = if let Some(frequency) = name.strip_suffix(FREQUENCY_SUFFIX) {
+            frequency.parse::<f32>().ok().filter(|f| *f > 0.0)?
+        } else if let Some((step, octave)) = parse_step_name(name) {
+            self.step_frequency(step, octave)
+        } else {
+            self.frequency(&Pitch::parse(name)?)
+        };
+
+        Some(frequency * 2_f32.powf(cents / CENTS_PER_OCTAVE as f32))
+    }
+
+    /// Applies the settings of a `[ tuning ... ]` directive to this tuning.
+    ///
+    /// The directive is a list of words. `A4=<freq>` sets the concert pitch,
+    /// and `equal`, `just <tonic>`, `pythagorean`, `meantone`,
+    /// `werckmeister3`, `<N>edo`, or `scala "<file.scl>"` selects a
+    /// temperament. `kbm "<file.kbm>"` adds a keyboard mapping to a Scala
+    /// scale. Settings that are not mentioned are left unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `directive` - The words of the directive.
+    /// * `base_dir` - The directory that relative `.scl` and `.kbm` paths are
+    ///   resolved from, usually the directory of the score.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or containing an error message.
+    pub fn apply_directive(&mut self, directive: &str, base_dir: &Path) -> Result<(), String> {
+        let words = split_directive(directive)?;
+        let mut words = words.iter().map(String::as_str);
+
+        while let Some(word) = words.next() {
+            if let Some(freq) = word.strip_prefix(CONCERT_PITCH_PREFIX) {
+                self.concert_pitch = match freq.parse::<f32>() {
+                    Ok(freq) if freq > 0.0 => freq,
+                    _ => return Err(format!("Invalid concert pitch: {}", word)),
+                };
+                continue;
+            }
+
+            let setting = word.to_lowercase();
+            self.temperament = match setting.as_str() {
+                "equal" | "12tet" => Temperament::Equal,
+                "pythagorean" => Temperament::Pythagorean,
+                "meantone" => Temperament::Meantone,
+                "werckmeister3" => Temperament::WerckmeisterIII,
+                "just" => {
+                    let tonic = words
+                        .next()
+                        .ok_or("Just intonation requires a tonic, e.g. `just Dn`")?;
+                    Temperament::Just(parse_pitch_class(tonic)?)
+                }
+                "scala" => {
+                    let filename = words
+                        .next()
+                        .ok_or("The scala setting requires a file, e.g. `scala \"scale.scl\"`")?;
+                    Temperament::Scale(Scale::from_file(&base_dir.join(filename))?, None)
+                }
+                "kbm" => {
+                    let filename = words
+                        .next()
+                        .ok_or("The kbm setting requires a file, e.g. `kbm \"keys.kbm\"`")?;
+                    match &self.temperament {
+                        Temperament::Scale(scale, _) => Temperament::Scale(
+                            scale.clone(),
+                            Some(KeyboardMapping::from_file(&base_dir.join(filename))?),
+                        ),
+                        _ => return Err("A keyboard mapping requires a scale".to_string()),
+                    }
+                }
+                _ => match setting.strip_suffix(EDO_SUFFIX).map(str::parse::<usize>) {
+                    Some(Ok(divisions)) if divisions > 0 => {
+                        Temperament::Scale(Scale::equal_divisions(divisions), None)
+                    }
+                    _ => return Err(format!("Unknown tuning setting: {}", word)),
+                },
+            };
+        }
+
+        Ok(())
+    }
+
+    // Returns the semitone frequency for a twelve-tone temperament.
+    fn semitone_frequency(&self, semitone_index: i32) -> f32 {
+        let (tonic, ratios) = self.temperament.degree_ratios();
+
+        // The frequency ratio of a semitone index relative to the tonic in octave 0:
+        let relative_value = |semitone_index: i32| {
+            let steps = semitone_index - tonic;
+            let octave = steps.div_euclid(SEMITONES_PER_OCTAVE);
+            let degree = steps.rem_euclid(SEMITONES_PER_OCTAVE) as usize;
+            2_f64.powi(octave) * ratios[degree]
+        };
+
+        let concert_pitch = Pitch {
+            letter: CONCERT_PITCH_LETTER,
+            accidentals: 0,
+            octave: CONCERT_PITCH_OCTAVE,
+        };
+
+        let ratio = relative_value(semitone_index) / relative_value(concert_pitch.semitone_index());
+        (self.concert_pitch as f64 * ratio) as f32
+    }
+
+    // Returns the keyboard mapping of a scale, or the default linear mapping.
+    fn keyboard_mapping<'a>(
+        &self,
+        mapping: Option<&'a KeyboardMapping>,
+    ) -> Cow<'a, KeyboardMapping> {
+        match mapping {
+            Some(mapping) => Cow::Borrowed(mapping),
+            None => Cow::Owned(KeyboardMapping::linear(self.concert_pitch as f64)),
+        }
+    }
+}
+
+/// Splits a note name like `An_4-14c` into the name and its offset in cents.
+///
+/// Names without an offset have an offset of zero. Returns `None` if the
+/// offset is malformed.
+pub fn split_cents_offset(name: &str) -> Option<(&str, f32)> {
+    let Some(without_suffix) = name.strip_suffix(CENTS_SUFFIX) else {
+        return Some((name, 0.0));
+    };
+
+    let sign_idx = without_suffix.rfind(['+', '-'])?;
+    let cents = without_suffix[sign_idx..].parse::<f32>().ok()?;
+    Some((&name[..sign_idx], cents))
+}
+
+// Parses a scale step name like "s7_4" into a step and an octave.
+fn parse_step_name(name: &str) -> Option<(i32, i32)> {
+    let (step, octave) = name.strip_prefix(STEP_NOTE_PREFIX)?.split_once('_')?;
+    Some((step.parse().ok()?, octave.parse().ok()?))
+}
+
+// Splits a tuning directive into words, keeping quoted file names together.
+fn split_directive(directive: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut rest = directive.trim_start();
+
+    while !rest.is_empty() {
+        if let Some(quoted) = rest.strip_prefix(QUOTE_CHAR) {
+            let end = quoted
+                .find(QUOTE_CHAR)
+                .ok_or(format!("Unterminated quote in tuning: {}", directive))?;
+            words.push(quoted[..end].to_string());
+            rest = quoted[end + 1..].trim_start();
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            words.push(rest[..end].to_string());
+            rest = rest[end..].trim_start();
+        }
+    }
+
+    Ok(words)
+}
+
+// Parses an octave-less pitch name like "Dn" or "Bf" into a pitch class (0 = C).
+fn parse_pitch_class(name: &str) -> Result<i32, String> {
+    Pitch::parse(&format!("{}_0", name))
+        .map(|pitch| pitch.semitone_index().rem_euclid(SEMITONES_PER_OCTAVE))
+        .ok_or(format!("Invalid tonic for just intonation: {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frequency(tuning: &Tuning, name: &str) -> f32 {
+        tuning.note_frequency(name).expect(name)
+    }
+
+    fn assert_close(actual: f32, expected: f32, message: &str) {
+        assert!((actual - expected).abs() < expected * 1e-5, "{}", message);
+    }
+
+    #[test]
+    fn test_custom_concert_pitch() {
+        let mut tuning = Tuning::default();
+        tuning.apply_directive("A4=432", Path::new("")).unwrap();
+
+        assert_close(frequency(&tuning, "An_4"), 432.0, "An_4 should be 432 Hz");
+        assert_close(frequency(&tuning, "An_3"), 216.0, "An_3 should be 216 Hz");
+    }
+
+    #[test]
+    fn test_just_intonation() {
+        let mut tuning = Tuning::default();
+        tuning.apply_directive("just Dn", Path::new("")).unwrap();
+
+        let d4 = frequency(&tuning, "Dn_4");
+        assert_close(
+            frequency(&tuning, "An_4"),
+            440.0,
+            "An_4 stays at concert pitch",
+        );
+        assert_close(frequency(&tuning, "Fs_4"), d4 * 1.25, "Major third is 5/4");
+        assert_close(
+            frequency(&tuning, "Cs_5"),
+            d4 * 1.875,
+            "Major seventh is 15/8",
+        );
+        assert_close(
+            frequency(&tuning, "Cs_4"),
+            d4 * 0.9375,
+            "Below the tonic wraps",
+        );
+    }
+
+    #[test]
+    fn test_historical_temperaments() {
+        let pythagorean = Tuning::new(440.0, Temperament::Pythagorean);
+        let c4 = frequency(&pythagorean, "Cn_4");
+        assert_close(frequency(&pythagorean, "Gn_4"), c4 * 1.5, "Pure fifth");
+
+        let meantone = Tuning::new(440.0, Temperament::Meantone);
+        let c4 = frequency(&meantone, "Cn_4");
+        assert_close(frequency(&meantone, "En_4"), c4 * 1.25, "Pure major third");
+
+        let werckmeister = Tuning::new(440.0, Temperament::WerckmeisterIII);
+        assert_close(frequency(&werckmeister, "An_4"), 440.0, "Concert pitch");
+        assert_close(frequency(&werckmeister, "An_5"), 880.0, "Pure octave");
+    }
+
+    #[test]
+    fn test_equal_divisions() {
+        let mut tuning = Tuning::default();
+        tuning.apply_directive("19edo", Path::new("")).unwrap();
+
+        let c4 = frequency(&tuning, "s0_4");
+        assert_close(
+            frequency(&tuning, "An_4"),
+            440.0,
+            "An_4 stays at concert pitch",
+        );
+        assert_close(
+            frequency(&tuning, "s19_4"),
+            c4 * 2.0,
+            "19 steps span an octave",
+        );
+        assert_close(
+            frequency(&tuning, "s0_5"),
+            c4 * 2.0,
+            "Octave numbers span a period",
+        );
+        assert_close(
+            frequency(&tuning, "Cs_4"),
+            c4 * 2_f32.powf(1.0 / 19.0),
+            "Keys map linearly onto steps",
+        );
+    }
+
+    #[test]
+    fn test_step_names_in_twelve_tone_tunings() {
+        let tuning = Tuning::default();
+        assert_close(
+            frequency(&tuning, "s9_4"),
+            440.0,
+            "Step 9 of octave 4 is An_4",
+        );
+    }
+
+    #[test]
+    fn test_raw_frequencies_and_cents() {
+        let tuning = Tuning::default();
+        assert_close(frequency(&tuning, "60Hz"), 60.0, "Raw frequency");
+        assert_close(frequency(&tuning, "60.5Hz"), 60.5, "Decimal frequency");
+        assert_close(
+            frequency(&tuning, "An_4+1200c"),
+            880.0,
+            "An octave in cents",
+        );
+        assert_close(
+            frequency(&tuning, "An_4-14c"),
+            440.0 * 2_f32.powf(-14.0 / 1200.0),
+            "Cents below a note",
+        );
+        assert_close(
+            frequency(&tuning, "Cn_-1-1200c"),
+            8.1758 / 2.0,
+            "Negative octave",
+        );
+        assert_eq!(tuning.note_frequency("An_4-c"), None);
+        assert_eq!(tuning.note_frequency("0Hz"), None);
+    }
+
+    #[test]
+    fn test_note_steps() {
+        let tuning = Tuning::default();
+        assert_eq!(tuning.note_step("Cs_4"), Some((1, 4)));
+        assert_eq!(tuning.note_step("Cf_4+10c"), Some((11, 3)));
+        assert_eq!(tuning.note_step("s3_2"), Some((3, 2)));
+        assert_eq!(tuning.note_step("440Hz"), None);
+
+        let mut edo = Tuning::default();
+        edo.apply_directive("19edo", Path::new("")).unwrap();
+        assert_eq!(edo.steps_per_octave(), 19);
+        assert_eq!(edo.note_step("Cn_4"), Some((0, 4)));
+        assert_eq!(edo.note_step("Bn_3"), Some((18, 3)));
+    }
+
+    #[test]
+    fn test_split_directive() {
+        assert_eq!(
+            split_directive(r#"scala "my scales/a.scl" kbm "a.kbm""#).unwrap(),
+            vec!["scala", "my scales/a.scl", "kbm", "a.kbm"]
+        );
+        assert!(split_directive(r#"scala "a.scl"#).is_err());
+    }
+
+    #[test]
+    fn test_invalid_directive() {
+        let mut tuning = Tuning::default();
+        assert!(tuning.apply_directive("A4=loud", Path::new("")).is_err());
+        assert!(tuning.apply_directive("just", Path::new("")).is_err());
+        assert!(tuning.apply_directive("slendro", Path::new("")).is_err());
+        assert!(tuning.apply_directive("0edo", Path::new("")).is_err());
+        assert!(
+            tuning
+                .apply_directive(r#"kbm "keys.kbm""#, Path::new(""))
+                .is_err()
+        );
+    }
+}
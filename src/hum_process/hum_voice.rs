@@ -19,17 +19,145 @@ along with this program.  If not, see <https://www.gnu.org/licenses/>.
 use std::f32::consts::PI;
 
 const SQUARE_WAVE_AMPLITUDE: f32 = 0.7;
+const DEFAULT_VOICE_NAME: &str = "sine";
+const VOICE_NAMES: [&str; 3] = ["sine", "square", "sawtooth"];
+const CENTS_PER_OCTAVE: f32 = 1200.0;
+const PARAM_SEPARATOR: char = '=';
+const VALUE_SEPARATOR: char = ':';
 
-pub fn silence(_: f32, _: f32) -> f32 {
+/// A low-frequency oscillator that modulates the pitch or amplitude of a voice.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Modulation {
+    /// The rate of the oscillator in Hz.
+    pub rate: f32,
+    /// The depth of the modulation (cents for vibrato, a fraction of the full
+    /// amplitude for tremolo).
+    pub depth: f32,
+}
+
+impl Modulation {
+    // Parses a modulation value like "6:30" (rate in Hz, then depth).
+    fn parse(param: &str, value: &str) -> Result<Modulation, String> {
+        let invalid = || format!("Invalid {} value: {}", param, value);
+        let (rate, depth) = value.split_once(VALUE_SEPARATOR).ok_or_else(invalid)?;
+        let rate: f32 = rate.parse().map_err(|_| invalid())?;
+        let depth: f32 = depth.parse().map_err(|_| invalid())?;
+
+        if !rate.is_finite() || !depth.is_finite() || rate < 0.0 || depth < 0.0 {
+            return Err(invalid());
+        }
+
+        Ok(Modulation { rate, depth })
+    }
+
+    // Returns the value of the oscillator (between -1 and 1) at a given time.
+    fn oscillate(&self, time: f32) -> f32 {
+        (time * self.rate * 2.0 * PI).sin()
+    }
+}
+
+/// The "instrument" or "sound" of a note: a waveform plus optional vibrato
+/// and tremolo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Voice {
+    /// The name of the waveform (e.g., "sine", "square", or "sawtooth").
+    pub name: String,
+    /// Pitch modulation, with the depth in cents.
+    pub vibrato: Option<Modulation>,
+    /// Amplitude modulation, with the depth as a fraction between 0 and 1.
+    pub tremolo: Option<Modulation>,
+}
+
+impl Default for Voice {
+    fn default() -> Voice {
+        Voice {
+            name: DEFAULT_VOICE_NAME.to_string(),
+            vibrato: None,
+            tremolo: None,
+        }
+    }
+}
+
+impl Voice {
+    /// Parses a voice command like "sine vibrato=6:30 tremolo=4:0.3".
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the voice or an error message.
+    pub fn parse(noun: &str) -> Result<Voice, String> {
+        let mut words = noun.split_whitespace();
+        let mut voice = Voice {
+            name: words.next().unwrap_or(DEFAULT_VOICE_NAME).to_string(),
+            ..Voice::default()
+        };
+
+        for word in words {
+            match word.split_once(PARAM_SEPARATOR) {
+                Some(("vibrato", value)) => {
+                    voice.vibrato = Some(Modulation::parse("vibrato", value)?);
+                }
+                Some(("tremolo", value)) => {
+                    let tremolo = Modulation::parse("tremolo", value)?;
+                    if tremolo.depth > 1.0 {
+                        return Err(format!("Tremolo depth must be at most 1: {}", word));
+                    }
+                    voice.tremolo = Some(tremolo);
+                }
+                _ => return Err(format!("Unknown voice parameter: {}", word)),
+            }
+        }
+
+        Ok(voice)
+    }
+
+    /// Checks if the voice names one of the built-in waveforms. Other names
+    /// are played as sine waves.
+    pub fn is_known(&self) -> bool {
+        VOICE_NAMES.contains(&self.name.as_str())
+    }
+
+    /// Returns the signal function of the voice's waveform.
+    pub fn signal(&self) -> fn(f32) -> f32 {
+        match self.name.as_str() {
+            "square" => square,
+            "sawtooth" => sawtooth,
+            _ => sine,
+        }
+    }
+
+    /// Returns the frequency multiplier of the vibrato at a given time.
+    pub fn vibrato_factor(&self, time: f32) -> f32 {
+        match self.vibrato {
+            Some(vibrato) => 2_f32.powf(vibrato.depth * vibrato.oscillate(time) / CENTS_PER_OCTAVE),
+            None => 1.0,
+        }
+    }
+
+    /// Returns the amplitude multiplier of the tremolo at a given time.
+    pub fn tremolo_factor(&self, time: f32) -> f32 {
+        match self.tremolo {
+            // Dip from full amplitude by up to the depth once per cycle, starting at full amplitude:
+            Some(tremolo) => {
+                let phase = 2.0 * PI * tremolo.rate * time;
+                1.0 - tremolo.depth * (1.0 - phase.cos()) / 2.0
+            }
+            None => 1.0,
+        }
+    }
+}
+
+// Each voice maps the phase of the oscillator (in cycles) to an amplitude.
+
+pub fn silence(_: f32) -> f32 {
     0.0 // coerce every value to 0
 }
 
-pub fn sine(time: f32, frequency: f32) -> f32 {
-    (time * frequency * 2.0 * PI).sin()
+pub fn sine(phase: f32) -> f32 {
+    (phase * 2.0 * PI).sin()
 }
 
-pub fn square(time: f32, frequency: f32) -> f32 {
-    let sine_value: f32 = sine(time, frequency);
+pub fn square(phase: f32) -> f32 {
+    let sine_value: f32 = sine(phase);
 
     if sine_value >= 0.0 {
         SQUARE_WAVE_AMPLITUDE
@@ -38,6 +166,49 @@ pub fn square(time: f32, frequency: f32) -> f32 {
     }
 }
 
-pub fn sawtooth(time: f32, frequency: f32) -> f32 {
-    2.0 * (time * frequency - (0.5 + time * frequency).floor())
+pub fn sawtooth(phase: f32) -> f32 {
+    2.0 * (phase - (0.5 + phase).floor())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_voice() {
+        let voice = Voice::parse("square vibrato=6:30 tremolo=4:0.25").unwrap();
+        assert_eq!(voice.name, "square");
+        assert_eq!(
+            voice.vibrato,
+            Some(Modulation {
+                rate: 6.0,
+                depth: 30.0
+            })
+        );
+        assert_eq!(
+            voice.tremolo,
+            Some(Modulation {
+                rate: 4.0,
+                depth: 0.25
+            })
+        );
+
+        assert_eq!(Voice::parse("sine").unwrap(), Voice::default());
+        assert!(Voice::parse("sine vibrato=6").is_err());
+        assert!(Voice::parse("sine tremolo=4:2").is_err());
+        assert!(Voice::parse("sine chorus=1:1").is_err());
+    }
+
+    #[test]
+    fn test_modulation_factors() {
+        let voice = Voice::parse("sine vibrato=1:1200 tremolo=1:0.5").unwrap();
+        assert!((voice.vibrato_factor(0.25) - 2.0).abs() < 1e-5);
+        assert!((voice.vibrato_factor(0.75) - 0.5).abs() < 1e-5);
+        // The tremolo starts at full amplitude and dips once per cycle:
+        assert!((voice.tremolo_factor(0.0) - 1.0).abs() < 1e-5);
+        assert!((voice.tremolo_factor(0.25) - 0.75).abs() < 1e-5);
+        assert!((voice.tremolo_factor(0.5) - 0.5).abs() < 1e-5);
+        assert!((voice.tremolo_factor(1.0) - 1.0).abs() < 1e-5);
+        assert_eq!(Voice::default().vibrato_factor(0.25), 1.0);
+    }
 }
@@ -16,15 +16,49 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+pub mod hum_check;
+mod hum_form;
+mod hum_groove;
+pub mod hum_info;
 pub mod hum_math;
+mod hum_motif;
+pub mod hum_scala;
+pub mod hum_transpose;
+pub mod hum_tuning;
 mod hum_voice;
 
-use std::collections::HashMap;
-
-use super::hum_error::GenerateError;
+use super::hum_error::{GenerateError, SourceLocation};
+use super::hum_parse::{LocatedCommand, is_grace_note, is_reserved_command, split_note_noun};
 use crate::SAMPLE_RATE;
+use hum_form::{Measure, Section};
+use hum_groove::Groove;
+use hum_math::SEMITONES_PER_OCTAVE;
+use hum_tuning::Tuning;
+use hum_voice::Voice;
+use std::path::Path;
 
 const DEFAULT_VOLUME: f32 = 0.05;
+const GLIDE_MARK: &str = "/>";
+const STACCATO_MARK: char = '.';
+const ACCENT_MARK: char = '>';
+const TENUTO_MARK: char = '_';
+const FERMATA_MARK: char = '^';
+const STACCATO_FRACTION: f32 = 0.5; // Sounding fraction of a staccato note
+const PORTATO_FRACTION: f32 = 0.75; // Sounding fraction of a note with staccato and tenuto
+const ACCENT_GAIN: f32 = 1.5;
+const FERMATA_FACTOR: f32 = 2.0; // How much longer a note under a fermata is held
+const TIME_TOLERANCE: f32 = 1e-6;
+const MAX_GRACE_FRACTION: f32 = 0.5; // Most of a note that its grace notes may take
+const ORNAMENT_NOTE_LENGTH: f32 = 1.0 / 32.0; // Length of the quick notes in ornaments
+const UPPER_NEIGHBOR_SEMITONES: f32 = 2.0; // Scaled to the nearest step of the tuning
+const LOWER_NEIGHBOR_SEMITONES: f32 = -1.0;
+const DEFAULT_TIME_SIGNATURE: (u32, u32) = (4, 4);
+const SWING_BEAT_LENGTH: f32 = 1.0 / 4.0; // Swing pairs up the eighth notes in each quarter
+const CHUNK_SIZE: usize = 4096; // Samples in each chunk of streamed audio
+const SECONDS_PER_MINUTE: f32 = 60.0;
+
+// The last form of a score and the index of its command.
+type Form = (usize, Vec<String>);
 
 struct PlaybackState {
     beats_per_second: f32,
@@ -32,28 +66,277 @@ struct PlaybackState {
     measure_greatest: i32,
     checkpoint_index: i32,
     time_signature: f32,
+    time_signature_parts: (u32, u32),
     beats_per_measure: f32,
     measure_duration: f32,
-    timestamp_at_measure_start: f32,
     timestamp_offset_in_measure: f32,
-    voice: String,
+    voice: Voice,
+    tuning: Tuning,
+    groove: Groove,
+    grace_notes: Vec<NoteEvent>,
+    line_index: usize,
+    measures: Vec<Measure>,
+    sections: Vec<Section>,
+}
+
+// Ornaments that are expanded into a sequence of quick notes when the score is rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ornament {
+    Trill,   // Alternates the note with its upper neighbor
+    Mordent, // The note, its lower neighbor, then the note again
+    Turn,    // Upper neighbor, the note, lower neighbor, the note
+}
+
+impl Ornament {
+    fn from_marks(marks: &str) -> Option<Ornament> {
+        // "turn" is checked before "tr", which it contains.
+        if marks.contains("turn") {
+            Some(Ornament::Turn)
+        } else if marks.contains("tr") {
+            Some(Ornament::Trill)
+        } else if marks.contains("mord") {
+            Some(Ornament::Mordent)
+        } else {
+            None
+        }
+    }
+
+    // Splits a note into the notes of the ornament. Each entry is an interval
+    // in scale steps from the written note and a duration in seconds.
+    fn pattern(&self, duration: f32, note_duration: f32, tuning: &Tuning) -> Vec<(i32, f32)> {
+        // A neighbor is at least one step away, even in scales with few steps:
+        let steps = |semitones: f32| {
+            let steps = semitones * tuning.steps_per_octave() as f32 / SEMITONES_PER_OCTAVE as f32;
+            (steps.abs().round() as i32).max(1) * steps.signum() as i32
+        };
+        let upper = steps(UPPER_NEIGHBOR_SEMITONES);
+        let lower = steps(LOWER_NEIGHBOR_SEMITONES);
+
+        match self {
+            Ornament::Trill => {
+                let count = ((duration / note_duration) as usize).max(1);
+                let length = duration / count as f32;
+                (0..count)
+                    .map(|i| (if i % 2 == 0 { 0 } else { upper }, length))
+                    .collect()
+            }
+            Ornament::Mordent => {
+                let note_duration = note_duration.min(duration / 3.0);
+                vec![
+                    (0, note_duration),
+                    (lower, note_duration),
+                    (0, duration - 2.0 * note_duration),
+                ]
+            }
+            Ornament::Turn => [upper, 0, lower, 0]
+                .iter()
+                .map(|&interval| (interval, duration / 4.0))
+                .collect(),
+        }
+    }
+
+    // Expands a note event into the note events of the ornament. The last note
+    // keeps the articulation, glide and fermata of the original note. The
+    // neighbors of the written note `name` are steps of the tuning.
+    fn expand(
+        &self,
+        event: NoteEvent,
+        note_duration: f32,
+        name: &str,
+        tuning: &Tuning,
+    ) -> Vec<NoteEvent> {
+        let pattern = self.pattern(event.duration, note_duration, tuning);
+        let (start_frequency, end_frequency) = event.frequencies;
+        let shortened_by = event.duration - event.sounding;
+
+        let mut events = Vec::with_capacity(pattern.len());
+        let mut position = event.position;
+
+        for (i, &(interval, duration)) in pattern.iter().enumerate() {
+            let frequency = step_neighbor(tuning, name, start_frequency, interval);
+            let is_last = i == pattern.len() - 1;
+
+            events.push(NoteEvent {
+                measure: event.measure,
+                position,
+                duration,
+                sounding: if is_last {
+                    (duration - shortened_by).max(0.0)
+                } else {
+                    duration
+                },
+                frequencies: if is_last && end_frequency != start_frequency {
+                    (frequency, end_frequency)
+                } else {
+                    (frequency, frequency)
+                },
+                gain: event.gain,
+                hold: if is_last { event.hold } else { 0.0 },
+                voice: event.voice.clone(),
+                line: event.line,
+            });
+            position += duration;
+        }
+
+        events
+    }
+}
+
+// Returns the frequency of the note that is `steps` scale steps away from a
+// written note, keeping the note's cents offset. Raw frequencies aren't on the
+// scale, so they move by equal-tempered semitones.
+fn step_neighbor(tuning: &Tuning, name: &str, frequency: f32, steps: i32) -> f32 {
+    if steps == 0 {
+        return frequency;
+    }
+    match tuning.note_step(name) {
+        Some((step, octave)) => {
+            frequency * tuning.step_frequency(step + steps, octave)
+                / tuning.step_frequency(step, octave)
+        }
+        None => frequency * 2_f32.powf(steps as f32 / SEMITONES_PER_OCTAVE as f32),
+    }
+}
+
+// A note with its timing resolved, waiting to be rendered into the track.
+#[derive(Clone)]
+struct NoteEvent {
+    measure: i32,            // Index of the written measure that the note is in
+    position: f32,           // Start position of the note in its measure (or track) in seconds
+    duration: f32,           // Written duration of the note in seconds
+    sounding: f32,           // Duration that the note actually sounds for in seconds
+    frequencies: (f32, f32), // Frequencies at the start and end of the note
+    gain: f32,               // Loudness relative to the default volume
+    hold: f32,               // Extra time added by a fermata in seconds
+    voice: Voice,            // "instrument" or "sound" of the note
+    line: usize,             // Line of music in its checkpoint block
+}
+
+/// A note as it is heard in a performance of a score, after repeats, jumps,
+/// and fermatas have been applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerformedNote {
+    /// The line of music that the note was written in, counted from the top of
+    /// its checkpoint block (lines end with `;`).
+    pub line: usize,
+    /// The name of the voice that plays the note, e.g. "sine".
+    pub voice: String,
+    /// When the note starts in seconds.
+    pub start: f32,
+    /// How long the note sounds in seconds.
+    pub duration: f32,
+    /// The frequency at the start of the note in Hz (NaN for rests).
+    pub frequency: f32,
+    /// The loudness of the note relative to an unaccented note.
+    pub gain: f32,
+}
+
+/// A measure as it is heard in a performance of a score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerformedMeasure {
+    /// When the measure starts in seconds.
+    pub start: f32,
+    /// How long the measure lasts in seconds, including any fermatas.
+    pub duration: f32,
+    /// The time signature of the measure as written, e.g. `(3, 4)`.
+    pub time_signature: (u32, u32),
+}
+
+/// The timing of a whole score: its notes and measures in the order in which
+/// they are played.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Performance {
+    pub notes: Vec<PerformedNote>,
+    pub measures: Vec<PerformedMeasure>,
+}
+
+/// Renders the audio of a score a chunk at a time, in the order that it is
+/// heard, so that it can be played or written before the rest is rendered.
+///
+/// Each note is rendered when the chunk that it starts in is reached, and a
+/// chunk comes out as soon as every note that sounds in it has been mixed.
+/// Iterating over the renderer yields the chunks of samples in order.
+pub struct StreamingRenderer {
+    events: Vec<NoteEvent>, // Sorted by when they start
+    next_event: usize,      // The first event that hasn't been mixed yet
+    buffer: Vec<f32>,       // Samples from `offset` on that have notes mixed in
+    offset: usize,          // The sample that the next chunk starts at
+    length: usize,          // The number of samples in the whole score
+}
+
+impl StreamingRenderer {
+    fn new(mut events: Vec<NoteEvent>) -> Self {
+        events.sort_by_key(|event| sample_span(event).0);
+        let length = events
+            .iter()
+            .map(|event| sample_span(event).1)
+            .max()
+            .unwrap_or(0);
+        StreamingRenderer {
+            events,
+            next_event: 0,
+            buffer: Vec::new(),
+            offset: 0,
+            length,
+        }
+    }
+
+    /// Returns the number of samples in the whole score, including the ones
+    /// that have already been rendered.
+    pub fn sample_count(&self) -> usize {
+        self.length
+    }
+}
+
+impl Iterator for StreamingRenderer {
+    type Item = Vec<f32>;
+
+    fn next(&mut self) -> Option<Vec<f32>> {
+        if self.offset >= self.length {
+            return None;
+        }
+        let end = (self.offset + CHUNK_SIZE).min(self.length);
+
+        // Notes only sound from when they start, so once every note that
+        // starts before the end of the chunk is mixed, the chunk is finished:
+        while let Some(event) = self.events.get(self.next_event) {
+            if sample_span(event).0 >= end {
+                break;
+            }
+            add_note_to_track(event, &mut self.buffer, self.offset);
+            self.next_event += 1;
+        }
+
+        let count = end - self.offset;
+        if self.buffer.len() < count {
+            self.buffer.resize(count, 0.0);
+        }
+        self.offset = end;
+        Some(self.buffer.drain(..count).collect())
+    }
 }
 
 impl PlaybackState {
-    fn new() -> Self {
+    fn new(tuning: Tuning) -> Self {
         let beats_per_second = 1.0;
         let beats_per_measure = 4.0;
         PlaybackState {
             beats_per_second,
             measure_index: -1,
             measure_greatest: -1,
-            checkpoint_index: -1,
+            checkpoint_index: 0,
             time_signature: 1.0,
+            time_signature_parts: DEFAULT_TIME_SIGNATURE,
             beats_per_measure,
             measure_duration: beats_per_measure / beats_per_second,
-            timestamp_at_measure_start: 0.0,
             timestamp_offset_in_measure: 0.0,
-            voice: "sine".to_string(),
+            voice: Voice::default(),
+            tuning,
+            groove: Groove::default(),
+            grace_notes: Vec::new(),
+            line_index: 0,
+            measures: Vec::new(),
+            sections: Vec::new(),
         }
     }
 }
@@ -68,39 +351,258 @@ impl PlaybackState {
 ///
 /// A `Result` containing the generated waveform as a `Vec<f32>` or a `GenerateError`.
 pub fn run_commands(score_commands: Vec<(String, String)>) -> Result<Vec<f32>, GenerateError> {
-    let mut state = PlaybackState::new();
-    let mut track: Vec<f32> = Vec::new();
-
-    // Get all of the frequencies for the 12-note scale with redundant sharps and flats:
-    let mut note_frequencies =
-        hum_math::get_standard_note_frequencies(hum_math::AccidentalStyle::Sharps);
-    let note_frequencies_flats =
-        hum_math::get_standard_note_frequencies(hum_math::AccidentalStyle::Flats);
-    note_frequencies.extend(note_frequencies_flats);
-
-    for command in score_commands {
-        let verb = command.0;
-        let noun = command.1;
-
-        match verb.as_ref() {
-            "comment" => {}
-            "tempo" => handle_tempo(&mut state, &noun)?,
-            "time" => handle_time(&mut state, &noun)?,
-            "checkpoint" => handle_checkpoint(&mut state),
-            "voice" => state.voice = noun,
-            "measure" => handle_measure(&mut state),
-            "reset" => handle_reset(&mut state),
-            _ => handle_note(&mut state, &mut track, &note_frequencies, &verb, &noun)?,
+    run_commands_with_tuning(score_commands, Tuning::default())
+}
+
+/// Processes a list of commands to generate an audio waveform in a custom tuning.
+///
+/// `[ tuning ... ]` directives in the score are applied on top of the given tuning.
+///
+/// # Arguments
+///
+/// * `score_commands` - A vector of tuples representing the parsed commands from the hum file.
+/// * `tuning` - The tuning in effect at the start of the score.
+///
+/// # Returns
+///
+/// A `Result` containing the generated waveform as a `Vec<f32>` or a `GenerateError`.
+pub fn run_commands_with_tuning(
+    score_commands: Vec<(String, String)>,
+    tuning: Tuning,
+) -> Result<Vec<f32>, GenerateError> {
+    render_commands(&score_commands, &[], tuning)
+}
+
+/// Expands the motifs in a list of located commands.
+///
+/// Motif definitions (`{ motif riff = ... }`) are removed, and each call such
+/// as `@riff` or `@riff+5` is replaced by the notes of the motif, transposed by
+/// the given number of semitones. This runs before the commands are rendered.
+///
+/// # Returns
+///
+/// A `Result` containing the expanded commands or a `GenerateError`.
+pub fn expand_motifs(
+    score_commands: Vec<LocatedCommand>,
+) -> Result<Vec<LocatedCommand>, GenerateError> {
+    hum_motif::expand(score_commands)
+}
+
+/// Processes a list of commands that were parsed with their locations in the score.
+///
+/// Errors caused by a command point back at the line and column where the
+/// command was written, even when repeats play it more than once.
+///
+/// # Arguments
+///
+/// * `score_commands` - The located commands from `hum_parse::parse_located_score`.
+/// * `tuning` - The tuning in effect at the start of the score.
+///
+/// # Returns
+///
+/// A `Result` containing the generated waveform as a `Vec<f32>` or a `GenerateError`.
+pub fn run_located_commands(
+    score_commands: Vec<LocatedCommand>,
+    tuning: Tuning,
+) -> Result<Vec<f32>, GenerateError> {
+    let (locations, score_commands): (Vec<SourceLocation>, Vec<(String, String)>) =
+        score_commands.into_iter().unzip();
+    render_commands(&score_commands, &locations, tuning)
+}
+
+/// Prepares a list of located commands to be rendered a chunk at a time.
+///
+/// The whole score is read (and checked for errors) first, and each chunk of
+/// audio is rendered when the `StreamingRenderer` is asked for it. Together,
+/// the chunks make up the same waveform that `run_located_commands` returns.
+///
+/// # Arguments
+///
+/// * `score_commands` - The located commands from `hum_parse::parse_located_score`.
+/// * `tuning` - The tuning in effect at the start of the score.
+///
+/// # Returns
+///
+/// A `Result` containing the `StreamingRenderer` or a `GenerateError`.
+pub fn stream_located_commands(
+    score_commands: Vec<LocatedCommand>,
+    tuning: Tuning,
+) -> Result<StreamingRenderer, GenerateError> {
+    let (locations, score_commands): (Vec<SourceLocation>, Vec<(String, String)>) =
+        score_commands.into_iter().unzip();
+    let (events, _) = perform_commands(&score_commands, &locations, tuning)?;
+    Ok(StreamingRenderer::new(events))
+}
+
+/// Works out when every note of a score is played, without rendering audio.
+///
+/// This is the timing that `run_located_commands` renders, for exporting the
+/// score to other formats such as MIDI.
+///
+/// # Arguments
+///
+/// * `score_commands` - The located commands from `hum_parse::parse_located_score`.
+/// * `tuning` - The tuning in effect at the start of the score.
+///
+/// # Returns
+///
+/// A `Result` containing the `Performance` of the score or a `GenerateError`.
+pub fn perform_located_commands(
+    score_commands: Vec<LocatedCommand>,
+    tuning: Tuning,
+) -> Result<Performance, GenerateError> {
+    let (locations, score_commands): (Vec<SourceLocation>, Vec<(String, String)>) =
+        score_commands.into_iter().unzip();
+    let (events, measures) = perform_commands(&score_commands, &locations, tuning)?;
+
+    let notes = events
+        .into_iter()
+        .map(|event| PerformedNote {
+            line: event.line,
+            voice: event.voice.name,
+            start: event.position,
+            duration: event.sounding,
+            frequency: event.frequencies.0,
+            gain: event.gain,
+        })
+        .collect();
+
+    Ok(Performance { notes, measures })
+}
+
+// Renders the commands of a score. The locations of the commands are optional,
+// so an empty slice may be passed.
+fn render_commands(
+    score_commands: &[(String, String)],
+    locations: &[SourceLocation],
+    tuning: Tuning,
+) -> Result<Vec<f32>, GenerateError> {
+    let (events, _) = perform_commands(score_commands, locations, tuning)?;
+    Ok(StreamingRenderer::new(events).flatten().collect())
+}
+
+// Reads the commands of a score and places its notes and measures at the times
+// when they are played.
+fn perform_commands(
+    score_commands: &[(String, String)],
+    locations: &[SourceLocation],
+    tuning: Tuning,
+) -> Result<(Vec<NoteEvent>, Vec<PerformedMeasure>), GenerateError> {
+    let mut state = PlaybackState::new(tuning);
+    let mut events: Vec<NoteEvent> = Vec::new();
+    let mut form: Option<Form> = None;
+
+    for index in 0..score_commands.len() {
+        let location = locations.get(index);
+        if let Err(mut error) = apply_command(
+            &mut state,
+            &mut events,
+            &mut form,
+            score_commands,
+            index,
+            location,
+        ) {
+            error.location = locations.get(index).cloned();
+            return Err(error);
         }
     }
 
-    Ok(track)
+    flush_grace_notes(&mut state, &mut events);
+
+    let order = measure_order(&state, &form, locations)?;
+    let mut events = arrange_events(events, &state.measures, &order);
+    let mut measures = arrange_measures(&state.measures, &order);
+    apply_fermatas(&mut events, &mut measures);
+
+    Ok((events, measures))
+}
+
+// Applies the command at `index` to the playback state, adding any notes that
+// it plays to `events`. Files that the command names are resolved relative to
+// its location, if it has one.
+fn apply_command(
+    state: &mut PlaybackState,
+    events: &mut Vec<NoteEvent>,
+    form: &mut Option<Form>,
+    score_commands: &[(String, String)],
+    index: usize,
+    location: Option<&SourceLocation>,
+) -> Result<(), GenerateError> {
+    let (verb, noun) = &score_commands[index];
+    match verb.as_ref() {
+        "comment" => Ok(()),
+        "tempo" => handle_tempo(state, noun),
+        "time" => handle_time(state, noun),
+        "tuning" => handle_tuning(
+            state,
+            noun,
+            location.map_or(Path::new(""), SourceLocation::dir),
+        ),
+        "swing" => handle_swing(state, noun),
+        "humanize" => handle_humanize(state, noun),
+        "sign" => handle_sign(state, noun),
+        "include" => Err(GenerateError {
+            message: format!("Include \"{}\" was not resolved before rendering", noun),
+            location: None,
+        }),
+        "motif" | "motif_end" | "motif_call" => Err(GenerateError {
+            message: "Motifs must be expanded before rendering".to_string(),
+            location: None,
+        }),
+        "form" => {
+            *form = Some((index, noun.split_whitespace().map(String::from).collect()));
+            Ok(())
+        }
+        "checkpoint" => {
+            flush_grace_notes(state, events);
+            handle_checkpoint(state, noun)
+        }
+        "voice" => handle_voice(state, noun),
+        "measure" => {
+            handle_measure(state);
+            Ok(())
+        }
+        "repeat_start" => handle_repeat_start(state),
+        "ending" => handle_ending(state, noun),
+        "repeat_end" => handle_repeat_end(state),
+        "reset" => {
+            flush_grace_notes(state, events);
+            handle_reset(state);
+            Ok(())
+        }
+        _ => {
+            let following_commands = &score_commands[index + 1..];
+            handle_note(state, events, verb, noun, following_commands)
+        }
+    }
+}
+
+// Works out the order in which the written measures are played, following the
+// form if the score has one, or else its repeats and signs.
+fn measure_order(
+    state: &PlaybackState,
+    form: &Option<Form>,
+    locations: &[SourceLocation],
+) -> Result<Vec<usize>, GenerateError> {
+    match form {
+        Some((index, form)) => hum_form::arrangement_order(&state.measures, &state.sections, form)
+            .map_err(|message| GenerateError {
+                message,
+                location: locations.get(*index).cloned(),
+            }),
+        None => hum_form::performance_order(&state.measures).map_err(|message| GenerateError {
+            message,
+            location: None,
+        }),
+    }
 }
 
 fn handle_tempo(state: &mut PlaybackState, noun: &str) -> Result<(), GenerateError> {
     state.beats_per_second = noun.parse::<f32>().map_err(|_| GenerateError {
         message: format!("Invalid tempo value: {}", noun),
-    })? / 60.0;
+        location: None,
+    })? / SECONDS_PER_MINUTE;
+    state.measure_duration = state.beats_per_measure / state.beats_per_second;
     Ok(())
 }
 
@@ -109,6 +611,7 @@ fn handle_time(state: &mut PlaybackState, noun: &str) -> Result<(), GenerateErro
     if time_signature_parts.len() != 2 {
         return Err(GenerateError {
             message: format!("Invalid time signature format: {}", noun),
+            location: None,
         });
     }
     let numerator: f32 = time_signature_parts[0]
@@ -118,6 +621,7 @@ fn handle_time(state: &mut PlaybackState, noun: &str) -> Result<(), GenerateErro
                 "Invalid time signature numerator: {}",
                 time_signature_parts[0]
             ),
+            location: None,
         })?;
     let denominator: f32 = time_signature_parts[1]
         .parse::<f32>()
@@ -126,121 +630,511 @@ fn handle_time(state: &mut PlaybackState, noun: &str) -> Result<(), GenerateErro
                 "Invalid time signature denominator: {}",
                 time_signature_parts[1]
             ),
+            location: None,
         })?;
 
     state.time_signature = numerator / denominator;
+    state.time_signature_parts = (numerator as u32, denominator as u32);
     state.beats_per_measure = numerator;
     state.measure_duration = state.beats_per_measure / state.beats_per_second;
     Ok(())
 }
 
-fn handle_checkpoint(state: &mut PlaybackState) {
+fn handle_tuning(
+    state: &mut PlaybackState,
+    noun: &str,
+    base_dir: &Path,
+) -> Result<(), GenerateError> {
+    state
+        .tuning
+        .apply_directive(noun, base_dir)
+        .map_err(|message| GenerateError {
+            message,
+            location: None,
+        })
+}
+
+fn handle_swing(state: &mut PlaybackState, noun: &str) -> Result<(), GenerateError> {
+    state
+        .groove
+        .set_swing(noun)
+        .map_err(|message| GenerateError {
+            message,
+            location: None,
+        })
+}
+
+fn handle_humanize(state: &mut PlaybackState, noun: &str) -> Result<(), GenerateError> {
+    state
+        .groove
+        .set_humanize(noun)
+        .map_err(|message| GenerateError {
+            message,
+            location: None,
+        })
+}
+
+fn handle_voice(state: &mut PlaybackState, noun: &str) -> Result<(), GenerateError> {
+    state.voice = Voice::parse(noun).map_err(|message| GenerateError {
+        message,
+        location: None,
+    })?;
+    Ok(())
+}
+
+fn handle_checkpoint(state: &mut PlaybackState, noun: &str) -> Result<(), GenerateError> {
     state.checkpoint_index = state.measure_greatest + 1;
     state.measure_index = state.measure_greatest;
+    state.line_index = 0;
+
+    // A named checkpoint starts a section that lasts until the next named checkpoint:
+    if !noun.is_empty() {
+        if state.sections.iter().any(|section| section.name == noun) {
+            return Err(GenerateError {
+                message: format!("There is already a section named {}", noun),
+                location: None,
+            });
+        }
+        state.sections.push(Section {
+            name: noun.to_string(),
+            start: state.checkpoint_index as usize,
+        });
+    }
+    Ok(())
 }
 
 fn handle_measure(state: &mut PlaybackState) {
     state.measure_index += 1;
-    state.timestamp_at_measure_start = state.measure_duration * (state.measure_index as f32);
     state.timestamp_offset_in_measure = 0.0;
 
+    // The first line of music to reach a measure sets its duration:
+    if state.measure_index as usize == state.measures.len() {
+        let mut measure = Measure::new(state.measure_duration);
+        measure.time_signature = state.time_signature_parts;
+        measure.tempo = state.beats_per_second * SECONDS_PER_MINUTE;
+        state.measures.push(measure);
+    }
+
     if state.measure_index > state.measure_greatest {
         state.measure_greatest = state.measure_index;
     }
 }
 
+fn handle_repeat_start(state: &mut PlaybackState) -> Result<(), GenerateError> {
+    handle_measure(state);
+    current_measure(state)?.repeat_start = true;
+    Ok(())
+}
+
+fn handle_ending(state: &mut PlaybackState, noun: &str) -> Result<(), GenerateError> {
+    handle_measure(state);
+    let number = noun.parse::<u32>().map_err(|_| GenerateError {
+        message: format!("Invalid ending number: {}", noun),
+        location: None,
+    })?;
+    current_measure(state)?.ending = Some(number);
+    Ok(())
+}
+
+fn handle_repeat_end(state: &mut PlaybackState) -> Result<(), GenerateError> {
+    current_measure(state)?.repeat_end = true;
+    Ok(())
+}
+
+fn handle_sign(state: &mut PlaybackState, noun: &str) -> Result<(), GenerateError> {
+    current_measure(state)?;
+    hum_form::apply_sign(&mut state.measures, state.measure_index as usize, noun).map_err(
+        |message| GenerateError {
+            message,
+            location: None,
+        },
+    )
+}
+
+// Gets the measure that form signs are currently applied to.
+fn current_measure(state: &mut PlaybackState) -> Result<&mut Measure, GenerateError> {
+    usize::try_from(state.measure_index)
+        .ok()
+        .and_then(|index| state.measures.get_mut(index))
+        .ok_or_else(|| GenerateError {
+            message: "Form signs must be written inside a measure".to_string(),
+            location: None,
+        })
+}
+
 fn handle_reset(state: &mut PlaybackState) {
     state.measure_index = state.checkpoint_index - 1;
+    state.line_index += 1;
 }
 
 fn handle_note(
     state: &mut PlaybackState,
-    track: &mut Vec<f32>,
-    note_frequencies: &HashMap<String, f32>,
+    events: &mut Vec<NoteEvent>,
     verb: &str,
     noun: &str,
+    following_commands: &[(String, String)],
 ) -> Result<(), GenerateError> {
-    match note_frequencies.get(verb) {
-        Some(note_frequency) => {
-            let length_parts: Vec<&str> = noun.split("/").collect();
-            if length_parts.len() != 2 {
-                return Err(GenerateError {
-                    message: format!("Invalid note length format: {}", noun),
-                });
-            }
-            let length_numerator: f32 =
-                length_parts[0].parse::<f32>().map_err(|_| GenerateError {
-                    message: format!("Invalid note length numerator: {}", length_parts[0]),
-                })?;
-
-            let mut length_denominator_str = length_parts[1].to_string();
-            let pluses = length_denominator_str.matches('+').count();
-            length_denominator_str = length_denominator_str.replace("+", "");
-
-            let length_denominator: f32 =
-                length_denominator_str
-                    .parse::<f32>()
-                    .map_err(|_| GenerateError {
-                        message: format!(
-                            "Invalid note length denominator: {}",
-                            length_denominator_str
-                        ),
-                    })?;
-
-            let note_length_of_measure =
-                (length_numerator / length_denominator) / state.time_signature;
-            let base_duration = state.measure_duration * note_length_of_measure;
-
-            // Calculate duration with dots: each dot adds half the value of the previous dot
-            // Formula: duration * (2 - (1/2)^n)
-            let multiplier = 2.0 - (0.5f32).powi(pluses as i32);
-            let note_duration = base_duration * multiplier;
-
-            let note_position =
-                state.timestamp_at_measure_start + state.timestamp_offset_in_measure;
-
-            add_note_to_track(
-                note_position,
-                note_duration,
-                *note_frequency,
-                &state.voice,
-                track,
-            );
-
-            state.timestamp_offset_in_measure += note_duration;
-            Ok(())
+    let grace = is_grace_note(noun);
+    let (noun, marks) = split_note_noun(noun);
+    let glide = marks.contains(GLIDE_MARK);
+
+    // The glide mark contains ">", so it is removed before looking for articulations:
+    let marks = &marks.replace(GLIDE_MARK, "");
+
+    let note_frequency = match state.tuning.note_frequency(verb) {
+        Some(note_frequency) if note_frequency > hum_math::NYQUIST_FREQ => {
+            return Err(GenerateError {
+                message: format!(
+                    "The note {} ({:.2} Hz) is above the Nyquist frequency of {} Hz",
+                    verb,
+                    note_frequency,
+                    hum_math::NYQUIST_FREQ
+                ),
+                location: None,
+            });
+        }
+        Some(note_frequency) => note_frequency,
+        None => {
+            return Err(GenerateError {
+                message: format!("There is no note named {}", verb),
+                location: None,
+            });
+        }
+    };
+
+    let note_duration = parse_note_duration(state, noun)?;
+
+    let end_frequency = if glide {
+        find_glide_target(state, verb, note_frequency, following_commands)?
+    } else {
+        note_frequency
+    };
+
+    // Articulations change how the note sounds, but not the written rhythm:
+    let staccato = marks.contains(STACCATO_MARK);
+    let tenuto = marks.contains(TENUTO_MARK);
+    let sounding_fraction = match (staccato, tenuto) {
+        (true, true) => PORTATO_FRACTION,
+        (true, false) => STACCATO_FRACTION,
+        _ => 1.0,
+    };
+    let gain = if marks.contains(ACCENT_MARK) {
+        ACCENT_GAIN
+    } else {
+        1.0
+    };
+    let hold = if marks.contains(FERMATA_MARK) {
+        note_duration * (FERMATA_FACTOR - 1.0)
+    } else {
+        0.0
+    };
+
+    let note_position = state.timestamp_offset_in_measure;
+
+    let mut event = NoteEvent {
+        measure: state.measure_index,
+        position: note_position,
+        duration: note_duration,
+        sounding: note_duration * sounding_fraction,
+        frequencies: (note_frequency, end_frequency),
+        gain,
+        hold,
+        voice: state.voice.clone(),
+        line: state.line_index,
+    };
+
+    // Grace notes take no written time. They are placed when the next note
+    // arrives, which gives up the time that they need:
+    if grace {
+        state.grace_notes.push(event);
+        return Ok(());
+    }
+
+    let first_event = events.len();
+    let grace_notes = std::mem::take(&mut state.grace_notes);
+    let grace_duration: f32 = grace_notes.iter().map(|grace| grace.duration).sum();
+    let grace_scale = if grace_duration > note_duration * MAX_GRACE_FRACTION {
+        note_duration * MAX_GRACE_FRACTION / grace_duration
+    } else {
+        1.0
+    };
+
+    let mut grace_position = note_position;
+    for mut grace in grace_notes {
+        grace.position = grace_position;
+        grace.duration *= grace_scale;
+        grace.sounding *= grace_scale;
+        grace_position += grace.duration;
+        events.push(grace);
+    }
+
+    let stolen = grace_position - note_position;
+    event.position += stolen;
+    event.duration -= stolen;
+    event.sounding = event.duration * sounding_fraction;
+
+    match Ornament::from_marks(marks) {
+        Some(ornament) => {
+            let ornament_note_duration = note_length_duration(state, ORNAMENT_NOTE_LENGTH);
+            events.extend(ornament.expand(event, ornament_note_duration, verb, &state.tuning));
+        }
+        None => events.push(event),
+    }
+
+    apply_groove(state, &mut events[first_event..]);
+
+    state.timestamp_offset_in_measure += note_duration;
+    Ok(())
+}
+
+// Moves the notes played for one written note (with its grace notes and
+// ornament) to where swing places them, and applies the same humanize jitter
+// to all of them. The written rhythm of the measure is not changed.
+fn apply_groove(state: &mut PlaybackState, events: &mut [NoteEvent]) {
+    if !state.groove.is_active() {
+        return;
+    }
+
+    let beat_duration = note_length_duration(state, SWING_BEAT_LENGTH);
+    let (shift, gain) = state.groove.humanize();
+
+    for event in events {
+        let start = state.groove.swing_position(event.position, beat_duration);
+        let end = state
+            .groove
+            .swing_position(event.position + event.duration, beat_duration);
+        let sounding_end = state
+            .groove
+            .swing_position(event.position + event.sounding, beat_duration);
+
+        event.position = (start + shift).max(0.0);
+        event.duration = end - start;
+        event.sounding = sounding_end - start;
+        event.gain *= gain;
+    }
+}
+
+// Calculates the duration in seconds of a note length like "1/4+".
+fn parse_note_duration(state: &PlaybackState, noun: &str) -> Result<f32, GenerateError> {
+    Ok(note_length_duration(state, parse_note_length(noun)?))
+}
+
+// Calculates a note length like "1/4+" as a fraction of a whole note.
+fn parse_note_length(noun: &str) -> Result<f32, GenerateError> {
+    let length_parts: Vec<&str> = noun.split("/").collect();
+    if length_parts.len() != 2 {
+        return Err(GenerateError {
+            message: format!("Invalid note length format: {}", noun),
+            location: None,
+        });
+    }
+    let length_numerator: f32 = length_parts[0].parse::<f32>().map_err(|_| GenerateError {
+        message: format!("Invalid note length numerator: {}", length_parts[0]),
+        location: None,
+    })?;
+
+    let mut length_denominator_str = length_parts[1].to_string();
+    let pluses = length_denominator_str.matches('+').count();
+    length_denominator_str = length_denominator_str.replace("+", "");
+
+    let length_denominator: f32 =
+        length_denominator_str
+            .parse::<f32>()
+            .map_err(|_| GenerateError {
+                message: format!(
+                    "Invalid note length denominator: {}",
+                    length_denominator_str
+                ),
+                location: None,
+            })?;
+
+    // Calculate length with dots: each dot adds half the value of the previous dot
+    // Formula: length * (2 - (1/2)^n)
+    let multiplier = 2.0 - (0.5f32).powi(pluses as i32);
+    Ok(length_numerator / length_denominator * multiplier)
+}
+
+// Calculates the duration in seconds of a note length given as a fraction of a whole note.
+fn note_length_duration(state: &PlaybackState, length: f32) -> f32 {
+    let note_length_of_measure = length / state.time_signature;
+    state.measure_duration * note_length_of_measure
+}
+
+// Plays any grace notes that were not followed by a note in their line of music.
+fn flush_grace_notes(state: &mut PlaybackState, events: &mut Vec<NoteEvent>) {
+    let mut position = state.timestamp_offset_in_measure;
+    for mut grace in state.grace_notes.drain(..) {
+        grace.position = position;
+        position += grace.duration;
+        events.push(grace);
+    }
+}
+
+// Finds the frequency that a gliding note sweeps to: the pitch of the next note
+// in the same line of music.
+fn find_glide_target(
+    state: &PlaybackState,
+    verb: &str,
+    note_frequency: f32,
+    following_commands: &[(String, String)],
+) -> Result<f32, GenerateError> {
+    let target = following_commands
+        .iter()
+        .take_while(|(verb, _)| !matches!(verb.as_str(), "reset" | "checkpoint"))
+        .find(|(verb, _)| !is_reserved_command(verb));
+
+    let target_frequency = match target {
+        Some((target_verb, _)) => state.tuning.note_frequency(target_verb),
+        None => {
+            return Err(GenerateError {
+                message: format!("The glide from {} is not followed by a note", verb),
+                location: None,
+            });
         }
-        None => Err(GenerateError {
-            message: format!("There is no note named {}", verb),
+    };
+
+    match target_frequency {
+        Some(frequency) if !frequency.is_nan() && !note_frequency.is_nan() => Ok(frequency),
+        _ => Err(GenerateError {
+            message: format!("The glide from {} must connect two pitched notes", verb),
+            location: None,
         }),
     }
 }
 
+// Places the notes of each written measure at the times that the measure is
+// played. Notes written before the first measure start at the beginning.
+fn arrange_events(events: Vec<NoteEvent>, measures: &[Measure], order: &[usize]) -> Vec<NoteEvent> {
+    let mut measure_starts: Vec<Vec<f32>> = vec![Vec::new(); measures.len()];
+    let mut start = 0.0;
+    for &index in order {
+        measure_starts[index].push(start);
+        start += measures[index].duration;
+    }
+
+    let mut arranged = Vec::with_capacity(events.len());
+    for event in events {
+        match usize::try_from(event.measure) {
+            Ok(index) => {
+                for &start in &measure_starts[index] {
+                    let mut played = event.clone();
+                    played.position += start;
+                    arranged.push(played);
+                }
+            }
+            Err(_) => arranged.push(event),
+        }
+    }
+    arranged
+}
+
+// Lists the measures in the order that they are played, with their start times.
+fn arrange_measures(measures: &[Measure], order: &[usize]) -> Vec<PerformedMeasure> {
+    let mut start = 0.0;
+    order
+        .iter()
+        .map(|&index| {
+            let measure = &measures[index];
+            let performed = PerformedMeasure {
+                start,
+                duration: measure.duration,
+                time_signature: measure.time_signature,
+            };
+            start += measure.duration;
+            performed
+        })
+        .collect()
+}
+
+// Stretches time at the end of each note under a fermata. Notes and measures
+// that start later are pushed back, and those still going at that moment are
+// held too.
+fn apply_fermatas(events: &mut [NoteEvent], measures: &mut [PerformedMeasure]) {
+    let mut holds: Vec<(f32, f32)> = events
+        .iter()
+        .filter(|event| event.hold > 0.0)
+        .map(|event| (event.position + event.duration, event.hold))
+        .collect();
+
+    // Work backwards so that earlier holds also move the later ones. Fermatas
+    // that end together (e.g., in every voice of a chord) only stretch time once.
+    holds.sort_by(|a, b| b.0.total_cmp(&a.0));
+    holds.dedup_by(|later, earlier| {
+        if (earlier.0 - later.0).abs() < TIME_TOLERANCE {
+            earlier.1 = earlier.1.max(later.1);
+            true
+        } else {
+            false
+        }
+    });
+
+    for (end, extra) in holds {
+        for event in events.iter_mut() {
+            if event.position >= end - TIME_TOLERANCE {
+                event.position += extra;
+            } else if event.position + event.duration > end - TIME_TOLERANCE {
+                event.duration += extra;
+                event.sounding += extra;
+            }
+        }
+        for measure in measures.iter_mut() {
+            if measure.start >= end - TIME_TOLERANCE {
+                measure.start += extra;
+            } else if measure.start + measure.duration > end - TIME_TOLERANCE {
+                measure.duration += extra;
+            }
+        }
+    }
+}
+
+// Writes a number for people to read, with up to three decimal places, e.g.
+// "120", "92.5", or "2.667".
+fn format_number(number: f32) -> String {
+    let text = format!("{:.3}", number);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+// Finds the samples where a note starts and ends, including any silence left
+// by a shortened articulation.
+fn sample_span(event: &NoteEvent) -> (usize, usize) {
+    let sample_position = (event.position * (SAMPLE_RATE as f32)) as usize;
+    let sample_duration = (event.sounding * (SAMPLE_RATE as f32)) as usize;
+    let written_duration = (event.duration * (SAMPLE_RATE as f32)) as usize;
+    (
+        sample_position,
+        sample_position + written_duration.max(sample_duration),
+    )
+}
+
 fn add_note_to_track(
-    position: f32,        // Start position of the note in the track in seconds
-    duration: f32,        // Duration of the note to add in seconds
-    frequency: f32,       // Frequency of the note
-    voice: &str,          // "instrument" or "sound" of the note
+    event: &NoteEvent,    // Note to add
     track: &mut Vec<f32>, // Master audio track to be mutated
+    offset: usize,        // The sample of the score that the track starts at
 ) {
+    let NoteEvent {
+        sounding,
+        frequencies: (start_frequency, end_frequency),
+        gain,
+        ref voice,
+        ..
+    } = *event;
+    let sweep = hum_math::frequency_sweep(start_frequency, end_frequency, sounding);
+    let frequency = |time: f32| sweep(time) * voice.vibrato_factor(time);
+
     // Generate the appropriate waveform for the note:
-    let note = if frequency.is_nan() {
+    let note = if start_frequency.is_nan() {
         // A frequency of NAN corresponds to a rest:
-        hum_math::generate_wave(&hum_voice::silence, frequency, duration)
+        hum_math::generate_wave(&hum_voice::silence, &frequency, sounding)
     } else {
-        match voice {
-            "square" => hum_math::generate_wave(&hum_voice::square, frequency, duration),
-            "sawtooth" => hum_math::generate_wave(&hum_voice::sawtooth, frequency, duration),
-            _ => hum_math::generate_wave(&hum_voice::sine, frequency, duration),
-        }
+        hum_math::generate_wave(&voice.signal(), &frequency, sounding)
     };
 
-    // Find the start sample for the note and the duration in number of samples:
-    let sample_position = (position * (SAMPLE_RATE as f32)) as usize;
-    let sample_duration = (duration * (SAMPLE_RATE as f32)) as usize;
-
-    // Find the end sample for the note:
-    let extended_position = sample_position + sample_duration;
+    // Find the start and end samples for the note in the track, and the
+    // duration in number of samples:
+    let (start, end) = sample_span(event);
+    let sample_position = start - offset;
+    let extended_position = end - offset;
+    let sample_duration = (sounding * (SAMPLE_RATE as f32)) as usize;
 
     // Extend the master track if it isn't long enough to contain the new note:
     match track.len().checked_sub(extended_position) {
@@ -252,10 +1146,11 @@ fn add_note_to_track(
     }
 
     // Please be careful with your ears and speakers! :)
-    let volume = DEFAULT_VOLUME;
+    let volume = DEFAULT_VOLUME * gain;
 
     // Add the waveform to the waveforms already present in the master track:
     for i in 0..sample_duration {
-        track[sample_position + i] += note[i] * volume;
+        let tremolo = voice.tremolo_factor(i as f32 / SAMPLE_RATE as f32);
+        track[sample_position + i] += note[i] * volume * tremolo;
     }
 }
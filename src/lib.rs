@@ -16,6 +16,17 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use std::io::{self, Write};
+use std::path::Path;
+
+pub mod hum_abc;
+pub mod hum_export;
+pub mod hum_flac;
+pub mod hum_fmt;
+pub mod hum_import;
+pub mod hum_include;
+pub mod hum_midi;
+pub mod hum_musicxml;
 pub mod hum_parse;
 pub mod hum_process;
 
@@ -34,14 +45,120 @@ pub const AUTHOR: &str = "Connor Bulakites <connor@bulakites.net>";
 /// A brief description of the Hum library.
 pub const ABOUT: &str = "Hum is a music notation language and synthesizer.";
 
-fn parse_score_contents(score_contents: &str) -> Result<Vec<f32>, hum_error::HumError> {
-    // Parse the score file and use the derived commands to generate the waveform.
-    let score_commands = hum_parse::hum_grammar::score(score_contents)?;
-    Ok(hum_process::run_commands(score_commands)?)
+fn score_path(infname: &str) -> &Path {
+    // A score read from stdin has no file of its own, so errors call it
+    // `<stdin>` and includes are resolved relative to the current directory.
+    if infname == hum_io::STDIN_FILENAME {
+        Path::new(hum_io::STDIN_NAME)
+    } else {
+        Path::new(infname)
+    }
+}
+
+fn parse_score_commands(
+    score_contents: &str,
+    score_path: Option<&Path>,
+) -> Result<Vec<hum_parse::LocatedCommand>, hum_error::HumError> {
+    // Parse the score file, pulling in any included files and expanding motifs.
+    let score_commands = hum_include::parse_with_includes(score_contents, score_path)?;
+    Ok(hum_process::expand_motifs(score_commands)?)
+}
+
+fn parse_score_contents(
+    score_contents: &str,
+    score_path: Option<&Path>,
+) -> Result<Vec<f32>, hum_error::HumError> {
+    // Use the commands derived from the score to generate the waveform.
+    let score_commands = parse_score_commands(score_contents, score_path)?;
+    Ok(hum_process::run_located_commands(
+        score_commands,
+        hum_process::hum_tuning::Tuning::default(),
+    )?)
+}
+
+fn stream_score_contents(
+    score_contents: &str,
+    score_path: Option<&Path>,
+) -> Result<hum_process::StreamingRenderer, hum_error::HumError> {
+    // Use the commands derived from the score to render it a chunk at a time.
+    let score_commands = parse_score_commands(score_contents, score_path)?;
+    Ok(hum_process::stream_located_commands(
+        score_commands,
+        hum_process::hum_tuning::Tuning::default(),
+    )?)
+}
+
+fn save_score_as(
+    score_contents: &str,
+    score_path: Option<&Path>,
+    outfname: &str,
+    format: hum_io::AudioFormat,
+) -> Result<(), hum_error::HumError> {
+    // Save the score's waveform to a file, or stream it to stdout as it is
+    // rendered. A player that stops reading early isn't an error.
+    if outfname != hum_io::STDOUT_FILENAME {
+        let waveform = parse_score_contents(score_contents, score_path)?;
+        return hum_io::save_as(&waveform, outfname, format);
+    }
+    let renderer = stream_score_contents(score_contents, score_path)?;
+    let sample_count = renderer.sample_count();
+    match hum_io::write_audio_stream(renderer, sample_count, format, &mut io::stdout().lock()) {
+        Err(hum_error::HumError::FileWriteError(error))
+            if error.kind() == io::ErrorKind::BrokenPipe =>
+        {
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+fn check_score_contents(
+    score_contents: &str,
+    score_path: Option<&Path>,
+) -> Result<Vec<hum_process::hum_check::Diagnostic>, hum_error::HumError> {
+    // Check the commands derived from the score without rendering them. A
+    // score that can't be read is reported as a single error.
+    let diagnostic = match parse_score_commands(score_contents, score_path) {
+        Ok(score_commands) => {
+            return Ok(hum_process::hum_check::check_located_commands(
+                score_commands,
+                hum_process::hum_tuning::Tuning::default(),
+            ));
+        }
+        Err(hum_error::HumError::HumParseError(error)) => {
+            let file = score_path.map(|path| path.display().to_string());
+            hum_process::hum_check::Diagnostic {
+                severity: hum_process::hum_check::Severity::Error,
+                message: format!("Expected {}", error.expected),
+                location: Some(hum_error::SourceLocation::new(
+                    file.as_deref(),
+                    error.location,
+                )),
+            }
+        }
+        Err(hum_error::HumError::IncludeError(error)) => error.into(),
+        Err(hum_error::HumError::GenerateError(error)) => error.into(),
+        Err(error) => return Err(error),
+    };
+    Ok(vec![diagnostic])
+}
+
+fn perform_score_contents(
+    score_contents: &str,
+    score_path: Option<&Path>,
+) -> Result<hum_process::Performance, hum_error::HumError> {
+    // Use the commands derived from the score to time its notes.
+    let score_commands = parse_score_commands(score_contents, score_path)?;
+    Ok(hum_process::perform_located_commands(
+        score_commands,
+        hum_process::hum_tuning::Tuning::default(),
+    )?)
 }
 
 /// Converts a Hum notation string into a WAV file.
 ///
+/// Includes are resolved relative to the current directory.
+///
 /// # Arguments
 ///
 /// * `score_contents` - A string slice containing the Hum notation.
@@ -52,6 +169,452 @@ fn parse_score_contents(score_contents: &str) -> Result<Vec<f32>, hum_error::Hum
 /// A `Result` indicating success or containing a `HumError`.
 pub fn convert_to_wav(score_contents: &str, outfname: &str) -> Result<(), hum_error::HumError> {
     // Generate the waveform and save it to a WAV file.
-    let waveform = parse_score_contents(score_contents)?;
+    let waveform = parse_score_contents(score_contents, None)?;
+    Ok(hum_io::save(waveform, outfname)?)
+}
+
+/// Converts the Hum notation of a score file into a WAV file.
+///
+/// Includes are resolved relative to `score_path`, and errors name it.
+///
+/// # Arguments
+///
+/// * `score_contents` - A string slice containing the Hum notation.
+/// * `score_path` - The path of the file that the notation was read from.
+/// * `outfname` - The path where the output WAV file should be saved.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn convert_score_to_wav(
+    score_contents: &str,
+    score_path: &str,
+    outfname: &str,
+) -> Result<(), hum_error::HumError> {
+    // Generate the waveform and save it to a WAV file.
+    let waveform = parse_score_contents(score_contents, Some(Path::new(score_path)))?;
     Ok(hum_io::save(waveform, outfname)?)
 }
+
+/// Reads a Hum score file and converts it into a WAV file.
+///
+/// # Arguments
+///
+/// * `infname` - The path of the score file, or `-` to read the score from stdin.
+/// * `outfname` - The path where the output WAV file should be saved.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn convert_file_to_wav(infname: &str, outfname: &str) -> Result<(), hum_error::HumError> {
+    // Read a score file and save its waveform to a WAV file.
+    let score_contents = hum_io::read(infname)?;
+    let waveform = parse_score_contents(&score_contents, Some(score_path(infname)))?;
+    Ok(hum_io::save(waveform, outfname)?)
+}
+
+/// Converts a Hum notation string into an audio file in the given format.
+///
+/// If `outfname` is `-`, the audio is streamed to stdout as it is rendered.
+/// Includes are resolved relative to the current directory.
+///
+/// # Arguments
+///
+/// * `score_contents` - A string slice containing the Hum notation.
+/// * `outfname` - The path where the output file should be saved, or `-` for stdout.
+/// * `format` - The format of the audio, e.g. WAV, FLAC, or raw PCM.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn convert_to_audio(
+    score_contents: &str,
+    outfname: &str,
+    format: hum_io::AudioFormat,
+) -> Result<(), hum_error::HumError> {
+    // Generate the waveform and save it in the given format, or stream it to
+    // stdout if the output is `-`.
+    save_score_as(score_contents, None, outfname, format)
+}
+
+/// Renders a Hum notation string a chunk at a time and writes it to a writer.
+///
+/// Each chunk is written as soon as it is ready, so a player reading the
+/// output can start before the whole score is rendered. Includes are
+/// resolved relative to the current directory.
+///
+/// # Arguments
+///
+/// * `score_contents` - A string slice containing the Hum notation.
+/// * `format` - The format of the audio, e.g. WAV, FLAC, or raw PCM.
+/// * `writer` - Where the audio is written.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn stream_audio<W: Write>(
+    score_contents: &str,
+    format: hum_io::AudioFormat,
+    writer: &mut W,
+) -> Result<(), hum_error::HumError> {
+    // Render the score a chunk at a time, writing each chunk in the given
+    // format as soon as it is ready.
+    let renderer = stream_score_contents(score_contents, None)?;
+    let sample_count = renderer.sample_count();
+    hum_io::write_audio_stream(renderer, sample_count, format, writer)
+}
+
+/// Reads a Hum score file and converts it into an audio file in the given format.
+///
+/// # Arguments
+///
+/// * `infname` - The path of the score file, or `-` to read the score from stdin.
+/// * `outfname` - The path where the output file should be saved, or `-` for stdout.
+/// * `format` - The format of the audio, e.g. WAV, FLAC, or raw PCM.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn convert_file_to_audio(
+    infname: &str,
+    outfname: &str,
+    format: hum_io::AudioFormat,
+) -> Result<(), hum_error::HumError> {
+    // Read a score file and save its waveform in the given format.
+    let score_contents = hum_io::read(infname)?;
+    save_score_as(&score_contents, Some(score_path(infname)), outfname, format)
+}
+
+/// Formats a Hum score file in place.
+///
+/// A score read from stdin is written to stdout instead.
+///
+/// # Arguments
+///
+/// * `infname` - The path of the score file, or `-` to read the score from stdin.
+///
+/// # Returns
+///
+/// A `Result` containing whether the formatting changed the score, or a `HumError`.
+pub fn format_file(infname: &str) -> Result<bool, hum_error::HumError> {
+    // Format a score file in place, returning whether it changed. A score
+    // read from stdin is written to stdout.
+    let score_contents = hum_io::read(infname)?;
+    let formatted = hum_fmt::format_score(&score_contents);
+    if infname == hum_io::STDIN_FILENAME {
+        io::stdout()
+            .write_all(formatted.as_bytes())
+            .map_err(hum_error::HumError::FileWriteError)?;
+    } else if formatted != score_contents {
+        std::fs::write(infname, &formatted).map_err(hum_error::HumError::FileWriteError)?;
+    }
+    Ok(formatted != score_contents)
+}
+
+/// Compares a Hum score file with its formatted version without changing it.
+///
+/// # Arguments
+///
+/// * `infname` - The path of the score file, or `-` to read the score from stdin.
+///
+/// # Returns
+///
+/// A `Result` containing a unified diff, which is empty if the file is already
+/// formatted, or a `HumError`. A file that differs only in its line endings
+/// is reported without a diff.
+pub fn check_file_format(infname: &str) -> Result<String, hum_error::HumError> {
+    // Diff a score file against its formatted version without changing it.
+    // The diff is empty if the file is already formatted.
+    let score_contents = hum_io::read(infname)?;
+    let formatted = hum_fmt::format_score(&score_contents);
+    let name = score_path(infname).display().to_string();
+    let diff = hum_fmt::diff(&name, &score_contents, &formatted);
+    if diff.is_empty() && formatted != score_contents {
+        return Ok(format!("{}: line endings differ\n", name));
+    }
+    Ok(diff)
+}
+
+/// Finds the problems in a Hum notation string without rendering it.
+///
+/// Includes are resolved relative to the current directory.
+///
+/// # Arguments
+///
+/// * `score_contents` - A string slice containing the Hum notation.
+///
+/// # Returns
+///
+/// A `Result` containing the `Diagnostic`s that were found or a `HumError`.
+pub fn check_score(
+    score_contents: &str,
+) -> Result<Vec<hum_process::hum_check::Diagnostic>, hum_error::HumError> {
+    // Find the problems in a score without rendering it.
+    check_score_contents(score_contents, None)
+}
+
+/// Reads a Hum score file and finds its problems without rendering it.
+///
+/// # Arguments
+///
+/// * `infname` - The path of the score file, or `-` to read the score from stdin.
+///
+/// # Returns
+///
+/// A `Result` containing the `Diagnostic`s that were found or a `HumError`.
+pub fn check_file(
+    infname: &str,
+) -> Result<Vec<hum_process::hum_check::Diagnostic>, hum_error::HumError> {
+    // Read a score file and find its problems without rendering it.
+    let score_contents = hum_io::read(infname)?;
+    check_score_contents(&score_contents, Some(score_path(infname)))
+}
+
+/// Summarizes the length, meter, voices, and blocks of a Hum notation string.
+///
+/// Includes are resolved relative to the current directory.
+///
+/// # Arguments
+///
+/// * `score_contents` - A string slice containing the Hum notation.
+///
+/// # Returns
+///
+/// A `Result` containing the `ScoreInfo` or a `HumError`.
+pub fn describe_score(
+    score_contents: &str,
+) -> Result<hum_process::hum_info::ScoreInfo, hum_error::HumError> {
+    // Summarize the score without rendering it.
+    let score_commands = parse_score_commands(score_contents, None)?;
+    Ok(hum_process::hum_info::describe_located_commands(
+        score_commands,
+        hum_process::hum_tuning::Tuning::default(),
+    )?)
+}
+
+/// Reads a Hum score file and summarizes its length, meter, voices, and blocks.
+///
+/// # Arguments
+///
+/// * `infname` - The path of the score file, or `-` to read the score from stdin.
+///
+/// # Returns
+///
+/// A `Result` containing the `ScoreInfo` or a `HumError`.
+pub fn describe_file(
+    infname: &str,
+) -> Result<hum_process::hum_info::ScoreInfo, hum_error::HumError> {
+    // Read a score file and summarize it without rendering it.
+    let score_contents = hum_io::read(infname)?;
+    let score_commands = parse_score_commands(&score_contents, Some(score_path(infname)))?;
+    Ok(hum_process::hum_info::describe_located_commands(
+        score_commands,
+        hum_process::hum_tuning::Tuning::default(),
+    )?)
+}
+
+/// Converts a Hum notation string into a Standard MIDI File.
+///
+/// Includes are resolved relative to the current directory.
+///
+/// # Arguments
+///
+/// * `score_contents` - A string slice containing the Hum notation.
+/// * `outfname` - The path where the output MIDI file should be saved.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn convert_to_midi(score_contents: &str, outfname: &str) -> Result<(), hum_error::HumError> {
+    // Time the notes of the score and save them to a Standard MIDI File.
+    let performance = perform_score_contents(score_contents, None)?;
+    hum_midi::save(&performance, outfname).map_err(hum_error::HumError::FileWriteError)
+}
+
+/// Reads a Hum score file and converts it into a Standard MIDI File.
+///
+/// # Arguments
+///
+/// * `infname` - The path of the score file, or `-` to read the score from stdin.
+/// * `outfname` - The path where the output MIDI file should be saved.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn convert_file_to_midi(infname: &str, outfname: &str) -> Result<(), hum_error::HumError> {
+    // Read a score file and save its notes to a Standard MIDI File.
+    let score_contents = hum_io::read(infname)?;
+    let performance = perform_score_contents(&score_contents, Some(score_path(infname)))?;
+    hum_midi::save(&performance, outfname).map_err(hum_error::HumError::FileWriteError)
+}
+
+/// Converts a Hum notation string into a MusicXML file for engraving.
+///
+/// Includes are resolved relative to the current directory.
+///
+/// # Arguments
+///
+/// * `score_contents` - A string slice containing the Hum notation.
+/// * `outfname` - The path where the output MusicXML file should be saved.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn convert_to_musicxml(
+    score_contents: &str,
+    outfname: &str,
+) -> Result<(), hum_error::HumError> {
+    // Engrave the score as it is written and save it as a MusicXML file.
+    let score_commands = parse_score_commands(score_contents, None)?;
+    let musicxml = hum_musicxml::encode(&score_commands)?;
+    std::fs::write(outfname, musicxml).map_err(hum_error::HumError::FileWriteError)
+}
+
+/// Reads a Hum score file and converts it into a MusicXML file.
+///
+/// # Arguments
+///
+/// * `infname` - The path of the score file, or `-` to read the score from stdin.
+/// * `outfname` - The path where the output MusicXML file should be saved.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn convert_file_to_musicxml(infname: &str, outfname: &str) -> Result<(), hum_error::HumError> {
+    // Read a score file and save it as a MusicXML file.
+    let score_contents = hum_io::read(infname)?;
+    let score_commands = parse_score_commands(&score_contents, Some(score_path(infname)))?;
+    let musicxml = hum_musicxml::encode(&score_commands)?;
+    std::fs::write(outfname, musicxml).map_err(hum_error::HumError::FileWriteError)
+}
+
+/// Converts a Hum notation string into an ABC tune.
+///
+/// Includes are resolved relative to the current directory.
+///
+/// # Arguments
+///
+/// * `score_contents` - A string slice containing the Hum notation.
+/// * `outfname` - The path where the output ABC file should be saved.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn convert_to_abc(score_contents: &str, outfname: &str) -> Result<(), hum_error::HumError> {
+    // Write the score as it is written as an ABC tune.
+    let score_commands = parse_score_commands(score_contents, None)?;
+    let abc = hum_abc::encode(&score_commands)?;
+    std::fs::write(outfname, abc).map_err(hum_error::HumError::FileWriteError)
+}
+
+/// Reads a Hum score file and converts it into an ABC tune.
+///
+/// # Arguments
+///
+/// * `infname` - The path of the score file, or `-` to read the score from stdin.
+/// * `outfname` - The path where the output ABC file should be saved.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn convert_file_to_abc(infname: &str, outfname: &str) -> Result<(), hum_error::HumError> {
+    // Read a score file and save it as an ABC tune.
+    let score_contents = hum_io::read(infname)?;
+    let score_commands = parse_score_commands(&score_contents, Some(score_path(infname)))?;
+    let abc = hum_abc::encode(&score_commands)?;
+    std::fs::write(outfname, abc).map_err(hum_error::HumError::FileWriteError)
+}
+
+/// Converts the contents of a Standard MIDI File into Hum notation.
+///
+/// # Arguments
+///
+/// * `bytes` - The contents of the MIDI file.
+///
+/// # Returns
+///
+/// A `Result` containing the Hum notation or a `HumError`.
+pub fn import_midi(bytes: &[u8]) -> Result<String, hum_error::HumError> {
+    // Read the notes of a Standard MIDI File and write them as Hum text.
+    let score = hum_midi::decode(bytes).map_err(|message| hum_error::ImportError { message })?;
+    Ok(hum_import::write_score(&score))
+}
+
+/// Reads a Standard MIDI File and saves it as a Hum score file.
+///
+/// # Arguments
+///
+/// * `infname` - The path of the MIDI file.
+/// * `outfname` - The path where the output score file should be saved.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn convert_midi_to_hum(infname: &str, outfname: &str) -> Result<(), hum_error::HumError> {
+    // Read a Standard MIDI File and save it as a Hum score.
+    let score_contents = import_midi(&std::fs::read(infname)?)?;
+    std::fs::write(outfname, score_contents).map_err(hum_error::HumError::FileWriteError)
+}
+
+/// Converts an uncompressed MusicXML score into Hum notation.
+///
+/// # Arguments
+///
+/// * `musicxml` - A string slice containing the MusicXML document.
+///
+/// # Returns
+///
+/// A `Result` containing the Hum notation or a `HumError`.
+pub fn import_musicxml(musicxml: &str) -> Result<String, hum_error::HumError> {
+    // Read the parts of an uncompressed MusicXML score and write them as Hum text.
+    let score =
+        hum_musicxml::decode(musicxml).map_err(|message| hum_error::ImportError { message })?;
+    Ok(hum_import::write_score(&score))
+}
+
+/// Reads an uncompressed MusicXML file and saves it as a Hum score file.
+///
+/// # Arguments
+///
+/// * `infname` - The path of the MusicXML file.
+/// * `outfname` - The path where the output score file should be saved.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn convert_musicxml_to_hum(infname: &str, outfname: &str) -> Result<(), hum_error::HumError> {
+    // Read an uncompressed MusicXML file and save it as a Hum score.
+    let score_contents = import_musicxml(&hum_io::read(infname)?)?;
+    std::fs::write(outfname, score_contents).map_err(hum_error::HumError::FileWriteError)
+}
+
+/// Converts the first tune of an ABC file into Hum notation.
+///
+/// # Arguments
+///
+/// * `abc` - A string slice containing the ABC notation.
+///
+/// # Returns
+///
+/// A `Result` containing the Hum notation or a `HumError`.
+pub fn import_abc(abc: &str) -> Result<String, hum_error::HumError> {
+    // Read the first tune of an ABC file and write it as Hum text.
+    let score = hum_abc::decode(abc).map_err(|message| hum_error::ImportError { message })?;
+    Ok(hum_import::write_score(&score))
+}
+
+/// Reads an ABC file and saves its first tune as a Hum score file.
+///
+/// # Arguments
+///
+/// * `infname` - The path of the ABC file.
+/// * `outfname` - The path where the output score file should be saved.
+///
+/// # Returns
+///
+/// A `Result` indicating success or containing a `HumError`.
+pub fn convert_abc_to_hum(infname: &str, outfname: &str) -> Result<(), hum_error::HumError> {
+    // Read an ABC file and save its first tune as a Hum score.
+    let score_contents = import_abc(&hum_io::read(infname)?)?;
+    std::fs::write(outfname, score_contents).map_err(hum_error::HumError::FileWriteError)
+}
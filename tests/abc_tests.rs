@@ -0,0 +1,186 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+mod common;
+
+use common::{note_lines, performed_notes};
+use hum::hum_abc;
+use hum::hum_error::HumError;
+use hum::hum_parse::parse_located_score;
+
+fn encode(score: &str) -> String {
+    hum_abc::encode(&parse_located_score(score).unwrap()).unwrap()
+}
+
+#[test]
+fn test_export_header_and_notes() {
+    let abc = encode(
+        "~ Song\n\
+         [ 90_bpm ] [ 6/8 ]\n\
+         *\n\
+         % square\n\
+         | (Cn_4 1/4) (Cs_4 1/8) (Cs_4 1/8) (Cn_4 1/8) (Rest 1/8) | (Bf_5 1/4)+ (Dn_3 1/12) (En_2 1/12)> (Fn_6 1/12). ;\n\
+         *\n\
+         [ 3/4 ]\n\
+         | (Gn_4 1/2)+ ;\n",
+    );
+
+    assert!(abc.starts_with("X:1\nT:Song\nM:6/8\nL:1/8\nQ:1/8=90\nV:1 name=\"square\"\nK:C\n"));
+    let lines: Vec<&str> = abc.lines().filter(|line| line.starts_with("[V:")).collect();
+    assert_eq!(
+        lines,
+        vec![
+            // Accidentals last until the end of the measure:
+            "[V:1] C2 ^C C =C z | _b3 D,2/3 !accent!E,,2/3 .f'2/3 |",
+            // Hum counts the tempo in beats of the new time signature:
+            "[V:1] [M:3/4] [Q:1/4=90] G6 |]",
+        ]
+    );
+}
+
+#[test]
+fn test_export_repeats_endings_and_lyrics() {
+    let notes = "|: (Cn_5 1/2) (Dn_5 1/2) |1 (En_5 1/1)^ :| |2 (Fn_5 1/2) g(Gn_5 1/16) (An_5 1/2) \
+                 | (Bn_5 1/1) [ fine ] ;";
+    // Each syllable starts in the column of its note, and F has none:
+    let mut lyrics = String::new();
+    for (note, syllable) in [
+        ("(Cn_5", "Hel-"),
+        ("(Dn_5", "lo,"),
+        ("(En_5", "world"),
+        ("(An_5", "two words"),
+        ("(Bn_5", "end"),
+    ] {
+        let column = notes.find(note).unwrap();
+        lyrics.push_str(&" ".repeat(column - lyrics.len()));
+        lyrics.push_str(&format!("~ {} ", syllable));
+    }
+    let abc = encode(&format!("{}\n{}\n", notes, lyrics));
+
+    let body: Vec<&str> = abc
+        .lines()
+        .skip_while(|line| !line.starts_with("K:"))
+        .collect();
+    assert_eq!(
+        body,
+        vec![
+            "K:C",
+            "[V:1] |: c4 d4 |1 !fermata!e8 :|2 f4 {g/}a4 | !fine!b8 |]",
+            "w:Hel-lo, world * two~words end",
+        ]
+    );
+}
+
+#[test]
+fn test_round_trip() {
+    let score = std::fs::read_to_string("daisy.hum").unwrap();
+    let abc = hum_abc::encode(&parse_located_score(&score).unwrap()).unwrap();
+    let imported = hum::import_abc(&abc).unwrap();
+
+    assert!(imported.starts_with("~ DAISY BELL by Harry Dacre\n[ 180_bpm ] [ 3/4 ]\n"));
+    // Every checkpoint block of the original is a line of the tune, and so a block:
+    assert_eq!(
+        imported
+            .lines()
+            .filter(|line| line.starts_with('*'))
+            .count(),
+        score.lines().filter(|line| line.starts_with('*')).count()
+    );
+    assert!(imported.contains("% square\n"));
+    assert!(imported.contains("  ~ Dai-                                ~ sy!\n"));
+
+    let (original, reimported) = (performed_notes(&score), performed_notes(&imported));
+    assert_eq!(original.len(), reimported.len());
+    for (a, b) in original.iter().zip(&reimported) {
+        assert!((a.start - b.start).abs() < 1e-3);
+        assert!((a.duration - b.duration).abs() < 1e-3);
+        assert!((a.frequency - b.frequency).abs() < 1e-2);
+    }
+}
+
+#[test]
+fn test_import_keys_octaves_and_lengths() {
+    let imported = hum::import_abc(
+        "X:1\n\
+         T:Scale\n\
+         M:C\n\
+         L:1/4\n\
+         Q:1/2=60\n\
+         K:Bb % two flats\n\
+         B, e _e =e | c'/ c'// c'// D,3/2 z/ [CEG] :|\n",
+    )
+    .unwrap();
+
+    assert!(imported.starts_with("~ Scale\n[ 120_bpm ] [ 4/4 ]\n"));
+    assert_eq!(
+        note_lines(&imported),
+        vec![
+            // Accidentals last until the end of the measure:
+            "| (Bf_3 1/4) (Ef_5 1/4) (Ef_5 1/4) (En_5 1/4) | (Cn_6 1/8) (Cn_6 1/16) (Cn_6 1/16) \
+             (Dn_3 1/4)+ (Rest 1/8) (Cn_4 1/4) :| ;",
+            "| (Rest 1/1) | (Rest 1/2)+ (Ef_4 1/4) :| ;",
+            "| (Rest 1/1) | (Rest 1/2)+ (Gn_4 1/4) :| ;",
+        ]
+    );
+}
+
+#[test]
+fn test_import_repeats_pickups_and_rhythms() {
+    let imported = hum::import_abc(
+        "X:1\n\
+         M:3/4\n\
+         K:D\n\
+         |: A | d>c (3Bcd e2- | [1 e3 :|2 e2 z |]\n\
+         w:Oh, sing * to me_ so-\n\
+         f3 |\n\
+         \n\
+         X:2\n\
+         K:C\n\
+         C4 |\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        note_lines(&imported),
+        vec![
+            // The pickup is filled with rests, and the tied E crosses the bar line:
+            "|: (Rest 5/8) (An_4 1/8) | (Dn_5 1/8)+ (Cs_5 1/16) (Bn_4 1/12) (Cs_5 1/12) \
+             (Dn_5 1/12) (En_5 1/4) |1 (En_5 1/4)+ (Rest 1/4)+ :| |2 (En_5 1/4) (Rest 1/2) ;",
+            "| (Fs_5 1/4)+ (Rest 1/4)+ ;",
+        ]
+    );
+    let lines: Vec<&str> = imported.lines().collect();
+    let notes = lines.iter().position(|line| line.starts_with('|')).unwrap();
+    assert_eq!(lines[notes + 1].find("~ Oh,"), lines[notes].find("(An_4"));
+    assert_eq!(lines[notes + 1].find("~ to"), lines[notes].find("(Bn_4"));
+    assert_eq!(lines[notes + 1].find("~ so-"), lines[notes].find("(En_5"));
+}
+
+#[test]
+fn test_invalid_tunes_are_import_errors() {
+    for tune in [
+        "X:1\nT:No key\nC D E F|\n",
+        "X:1\nM:3/0\nK:C\nC|\n",
+        "X:1\nK:Q\nC|\n",
+    ] {
+        assert!(matches!(
+            hum::import_abc(tune),
+            Err(HumError::ImportError(_))
+        ));
+    }
+}
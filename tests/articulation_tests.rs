@@ -0,0 +1,94 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use hum::SAMPLE_RATE;
+use hum::hum_parse::hum_grammar;
+use hum::hum_process;
+
+fn render(score: &str) -> Vec<f32> {
+    let commands = hum_grammar::score(score).unwrap();
+    hum_process::run_commands(commands).unwrap()
+}
+
+fn peak(wave: &[f32]) -> f32 {
+    wave.iter().fold(0.0, |max, sample| max.max(sample.abs()))
+}
+
+#[test]
+fn test_staccato_keeps_written_rhythm() {
+    let staccato = render("[ 60_bpm ] | (An_4 1/4). (An_4 1/4)");
+    let second = SAMPLE_RATE as usize;
+
+    // The first note sounds for half a beat, then leaves silence until the next note:
+    assert_eq!(staccato.len(), 2 * second);
+    assert!(peak(&staccato[..second / 2 - 100]) > 0.0);
+    assert_eq!(peak(&staccato[second / 2 + 100..second - 100]), 0.0);
+    assert!(peak(&staccato[second + 100..]) > 0.0);
+
+    // A staccato note at the end of the score still takes up its written length:
+    assert_eq!(render("[ 60_bpm ] | (An_4 1/4).").len(), second);
+}
+
+#[test]
+fn test_tenuto_and_portato() {
+    let second = SAMPLE_RATE as usize;
+    let plain = render("[ 60_bpm ] | (An_4 1/4) (Rest 1/4)");
+    let tenuto = render("[ 60_bpm ] | (An_4 1/4)_ (Rest 1/4)");
+    let portato = render("[ 60_bpm ] | (An_4 1/4)._ (Rest 1/4)");
+
+    // Notes already sound for their full length, so tenuto alone changes nothing:
+    assert_eq!(tenuto, plain);
+
+    // Together with staccato, tenuto lengthens the note to three quarters:
+    assert_eq!(peak(&portato[second * 3 / 4 + 100..second]), 0.0);
+    assert!(peak(&portato[second * 3 / 4 - 300..second * 3 / 4 - 100]) > 0.0);
+}
+
+#[test]
+fn test_accent_boosts_gain() {
+    let plain = render("[ 60_bpm ] | (An_4 1/4)");
+    let accent = render("[ 60_bpm ] | (An_4 1/4)>");
+
+    assert_eq!(plain.len(), accent.len());
+    assert!((peak(&accent) / peak(&plain) - 1.5).abs() < 1e-3);
+}
+
+#[test]
+fn test_glide_is_not_accented() {
+    let plain = render("[ 60_bpm ] | (An_4 1/4) (Cn_5 1/4)");
+    let glide = render("[ 60_bpm ] | (An_4 1/4)/> (Cn_5 1/4)");
+
+    assert_eq!(plain.len(), glide.len());
+    assert!((peak(&glide) - peak(&plain)).abs() < 1e-6);
+}
+
+#[test]
+fn test_fermata_stretches_time() {
+    let second = SAMPLE_RATE as usize;
+    let plain = render("[ 60_bpm ] | (An_4 1/4) (An_4 1/4)");
+    let fermata = render("[ 60_bpm ] | (An_4 1/4)^ (An_4 1/4)");
+
+    assert_eq!(plain.len(), 2 * second);
+    assert_eq!(fermata.len(), 3 * second);
+
+    // Every voice holds the fermata, and it only stretches time once:
+    let chord =
+        render("[ 60_bpm ] | (An_4 1/4)^ (An_4 1/4) ; | (Cn_4 1/4)^ (Cn_4 1/4) ; | (En_4 1/2) ;");
+    assert_eq!(chord.len(), 3 * second);
+    assert!(peak(&chord[second * 3 / 2..second * 2 - 100]) > 0.0);
+}
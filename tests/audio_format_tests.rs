@@ -0,0 +1,261 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use hum::hum_flac;
+use hum::hum_io::{self, AudioFormat};
+
+// Reads values most significant bit first, as FLAC stores them.
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize, // In bits
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, count: usize) -> u64 {
+        (0..count).fold(0, |value, _| {
+            let bit = self.bytes[self.position / 8] >> (7 - self.position % 8) & 1;
+            self.position += 1;
+            (value << 1) | bit as u64
+        })
+    }
+
+    fn read_signed(&mut self, count: usize) -> i32 {
+        let value = self.read(count) as i64;
+        (value - ((value >> (count - 1)) << count)) as i32
+    }
+
+    fn align(&mut self) {
+        self.position = self.position.div_ceil(8) * 8;
+    }
+}
+
+fn crc(bytes: &[u8], polynomial: u16, width: u32) -> u16 {
+    let top = 1 << (width - 1);
+    let mask = ((1_u32 << width) - 1) as u16;
+    bytes.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ ((byte as u16) << (width - 8)), |crc, _| {
+            let shifted = (crc << 1) & mask;
+            if crc & top != 0 {
+                shifted ^ polynomial
+            } else {
+                shifted
+            }
+        })
+    })
+}
+
+// Decodes a mono 16-bit FLAC stream with fixed and verbatim subframes,
+// checking the CRC of every frame. Returns the sample rate and the samples.
+fn decode_flac(bytes: &[u8]) -> (u32, Vec<i16>) {
+    assert_eq!(&bytes[..4], b"fLaC");
+    let mut reader = BitReader {
+        bytes,
+        position: 32,
+    };
+    assert_eq!(
+        reader.read(1),
+        1,
+        "STREAMINFO should be the last metadata block"
+    );
+    assert_eq!(reader.read(7), 0);
+    assert_eq!(reader.read(24), 34);
+    reader.read(16 + 16 + 24 + 24);
+    let sample_rate = reader.read(20) as u32;
+    assert_eq!(reader.read(3), 0, "The stream should have one channel");
+    assert_eq!(reader.read(5), 15, "The stream should have 16-bit samples");
+    let total = reader.read(36) as usize;
+    reader.read(128); // MD5
+
+    let mut samples = Vec::new();
+    while samples.len() < total {
+        let frame_start = reader.position / 8;
+        assert_eq!(reader.read(16), 0xFFF8);
+        assert_eq!(reader.read(4), 0b0111);
+        reader.read(4 + 4 + 3 + 1);
+        let first = reader.read(8);
+        let continuation = (first as u8).leading_ones().saturating_sub(1);
+        reader.read(8 * continuation as usize);
+        let block_size = reader.read(16) as usize + 1;
+        let header_end = reader.position / 8;
+        assert_eq!(
+            reader.read(8) as u16,
+            crc(&bytes[frame_start..header_end], 0x07, 8)
+        );
+
+        assert_eq!(reader.read(1), 0);
+        let kind = reader.read(6);
+        assert_eq!(reader.read(1), 0);
+        let mut block: Vec<i32> = Vec::with_capacity(block_size);
+        match kind {
+            0b000000 => block.resize(block_size, reader.read_signed(16)),
+            0b000001 => block.extend((0..block_size).map(|_| reader.read_signed(16))),
+            0b001000..=0b001100 => {
+                let order = (kind & 0b111) as usize;
+                block.extend((0..order).map(|_| reader.read_signed(16)));
+                assert_eq!(reader.read(2), 0);
+                let partition_order = reader.read(4);
+                let partitions = 1 << partition_order;
+                let mut residual = Vec::new();
+                for partition in 0..partitions {
+                    let parameter = reader.read(4) as usize;
+                    let count =
+                        (block_size >> partition_order) - if partition == 0 { order } else { 0 };
+                    for _ in 0..count {
+                        let mut quotient = 0;
+                        while reader.read(1) == 0 {
+                            quotient += 1;
+                        }
+                        let value = (quotient << parameter) | reader.read(parameter) as u32;
+                        residual.push((value >> 1) as i32 ^ -((value & 1) as i32));
+                    }
+                }
+                // Undo the differences, one order at a time:
+                for value in residual {
+                    let predicted = match order {
+                        0 => 0,
+                        1 => block[block.len() - 1],
+                        2 => 2 * block[block.len() - 1] - block[block.len() - 2],
+                        3 => {
+                            3 * block[block.len() - 1] - 3 * block[block.len() - 2]
+                                + block[block.len() - 3]
+                        }
+                        _ => {
+                            4 * block[block.len() - 1] - 6 * block[block.len() - 2]
+                                + 4 * block[block.len() - 3]
+                                - block[block.len() - 4]
+                        }
+                    };
+                    block.push(predicted + value);
+                }
+            }
+            _ => panic!("Unexpected subframe type {:06b}", kind),
+        }
+
+        reader.align();
+        let frame_end = reader.position / 8;
+        assert_eq!(
+            reader.read(16) as u16,
+            crc(&bytes[frame_start..frame_end], 0x8005, 16)
+        );
+        samples.extend(block.into_iter().map(|sample| sample as i16));
+    }
+
+    assert_eq!(reader.position / 8, bytes.len());
+    (sample_rate, samples)
+}
+
+#[test]
+fn test_flac_round_trip() {
+    // A quiet start, a sweep, some noise, and a short last block:
+    let mut seed: u32 = 1;
+    let mut noise = || {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        (seed >> 16) as i16
+    };
+    let samples: Vec<i16> = (0..5000)
+        .map(|_| 7)
+        .chain((0..6000).map(|i| ((i as f32 * i as f32 / 40_000.0).sin() * 30_000.0) as i16))
+        .chain((0..4100).map(|_| noise()))
+        .chain([i16::MIN, i16::MAX, 0, -1])
+        .collect();
+
+    let flac = hum_flac::encode(&samples, 44_100);
+    let (sample_rate, decoded) = decode_flac(&flac);
+    assert_eq!(sample_rate, 44_100);
+    assert_eq!(decoded, samples);
+    // The tone and silence compress well, and noise costs little more than it takes:
+    assert!(flac.len() < samples.len() * 2 * 3 / 4);
+}
+
+#[test]
+fn test_flac_empty_and_tiny() {
+    for samples in [vec![], vec![42], vec![1, -1, 1]] {
+        let (_, decoded) = decode_flac(&hum_flac::encode(&samples, 8_000));
+        assert_eq!(decoded, samples);
+    }
+}
+
+#[test]
+fn test_raw_formats() {
+    let waveform = [0.0, 0.5, -1.0, 2.0];
+
+    let mut s16le = Vec::new();
+    hum_io::write_audio(&waveform, AudioFormat::S16le, &mut s16le).unwrap();
+    let samples: Vec<i16> = s16le
+        .chunks(2)
+        .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+        .collect();
+    // Samples out of range are clipped:
+    assert_eq!(samples, vec![0, 16383, -32767, 32767]);
+
+    let mut f32le = Vec::new();
+    hum_io::write_audio(&waveform, AudioFormat::F32le, &mut f32le).unwrap();
+    let samples: Vec<f32> = f32le
+        .chunks(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect();
+    assert_eq!(samples, waveform);
+
+    let mut wav = Vec::new();
+    hum_io::write_audio(&waveform, AudioFormat::Wav, &mut wav).unwrap();
+    let reader = hound::WavReader::new(std::io::Cursor::new(wav)).unwrap();
+    assert_eq!(reader.len(), 4);
+}
+
+#[test]
+fn test_formats_from_names_and_extensions() {
+    assert_eq!(AudioFormat::from_filename("song.flac"), AudioFormat::Flac);
+    assert_eq!(AudioFormat::from_filename("song.FLAC"), AudioFormat::Flac);
+    assert_eq!(AudioFormat::from_filename("song.s16le"), AudioFormat::S16le);
+    assert_eq!(AudioFormat::from_filename("song.pcm"), AudioFormat::S16le);
+    assert_eq!(AudioFormat::from_filename("song.f32le"), AudioFormat::F32le);
+    assert_eq!(AudioFormat::from_filename("song.wav"), AudioFormat::Wav);
+    assert_eq!(AudioFormat::from_filename("song"), AudioFormat::Wav);
+    assert_eq!(AudioFormat::from_filename("-"), AudioFormat::Wav);
+    for name in AudioFormat::NAMES {
+        assert!(AudioFormat::from_name(name).is_some());
+    }
+    assert_eq!(AudioFormat::from_name("mp3"), None);
+}
+
+#[test]
+fn test_convert_to_flac() {
+    let path = std::env::temp_dir().join("hum_audio_format_test.flac");
+    let path = path.to_str().unwrap();
+    hum::convert_to_audio(
+        "[ 120_bpm ] [ 4/4 ]\n| (An_4 1/4) (Rest 1/4) ;\n",
+        path,
+        AudioFormat::Flac,
+    )
+    .unwrap();
+
+    let (sample_rate, samples) = decode_flac(&std::fs::read(path).unwrap());
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(sample_rate, hum::SAMPLE_RATE);
+    assert_eq!(samples.len(), hum::SAMPLE_RATE as usize); // Two half-second beats
+    assert!(
+        samples[..samples.len() / 2]
+            .iter()
+            .any(|&sample| sample != 0)
+    );
+    assert!(
+        samples[samples.len() / 2..]
+            .iter()
+            .all(|&sample| sample == 0)
+    );
+}
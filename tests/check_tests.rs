@@ -0,0 +1,123 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use hum::hum_process::hum_check::{Diagnostic, Severity};
+
+// Checks a score, describing each problem as "line:column: severity: message".
+fn check(score: &str) -> Vec<String> {
+    hum::check_score(score)
+        .unwrap()
+        .iter()
+        .map(Diagnostic::to_string)
+        .collect()
+}
+
+#[test]
+fn test_scores_without_problems() {
+    assert!(check("[ 90_bpm ] [ 3/4 ]\n| (Cn_4 1/4) (Dn_4 1/2) | (En_4 1/2+) ;\n").is_empty());
+    for file in ["daisy.hum", "simple_sample.hum"] {
+        assert_eq!(hum::check_file(file).unwrap(), vec![]);
+    }
+}
+
+#[test]
+fn test_every_error_is_reported() {
+    assert_eq!(
+        check(
+            "[ 120_bpm ] [ 4/4 ]\n\
+             % sine vibrato=6\n\
+             | (Qn_4 1/4) (Cn_4 1/4) (Xs_2 1/2) ;\n"
+        ),
+        vec![
+            "2:1: error: Invalid vibrato value: 6",
+            "3:3: error: There is no note named Qn_4",
+            "3:25: error: There is no note named Xs_2",
+        ]
+    );
+}
+
+#[test]
+fn test_unknown_voices() {
+    assert_eq!(
+        check("% kazoo\n| (Cn_4 1/1) ;\n% square\n| (Cn_4 1/1) ;\n"),
+        vec!["1:1: warning: There is no voice named kazoo, so it is played as sine"]
+    );
+}
+
+#[test]
+fn test_measure_and_line_lengths() {
+    assert_eq!(
+        check(
+            "[ 3/4 ]\n\
+             *\n\
+             | (Cn_4 1/4) (Cn_4 1/4) | (Cn_4 1/2) (Cn_4 1/8) (Cn_4 1/8) ;\n\
+             | (Cn_3 1/2+) | (Cn_3 1/2) ;\n\
+             *\n\
+             | (Cn_4 1/12) (Cn_4 1/12) (Cn_4 1/12) (Cn_4 1/2) ;\n\
+             | g(Dn_4 1/16) (Cn_4 1/2+) ;\n"
+        ),
+        vec![
+            "3:1: warning: Measure 1 has 2 beats in line 1 of its block, but 3/4 needs 3",
+            "4:15: warning: Measure 2 has 2 beats in line 2 of its block, but 3/4 needs 3",
+        ]
+    );
+}
+
+#[test]
+fn test_lines_of_different_lengths() {
+    let problems = check(
+        "[ 2/4 ]\n\
+         *\n\
+         | (Cn_4 1/2) | (Cn_4 1/2) ;\n\
+         | (Cn_3 1/2) ;\n\
+         | (Cn_3 1/2) | (Cn_3 1/2) | (Cn_3 1/2) ;\n",
+    );
+    assert_eq!(
+        problems,
+        vec![
+            "4:1: warning: Line 2 of this block has 2 quarter notes, but line 1 has 4",
+            "5:1: warning: Line 3 of this block has 6 quarter notes, but line 1 has 4",
+        ]
+    );
+}
+
+#[test]
+fn test_tempo_and_time_signature_problems() {
+    assert_eq!(
+        check(
+            "[ 0_bpm ] [ 3/0 ]\n\
+             [ 60_bpm ] [ 4/4 ]\n\
+             | (Cn_4 1/2) [ 2/4 ] (Cn_4 1/4) ;\n"
+        ),
+        vec![
+            "1:1: error: The tempo must be more than 0 bpm: 0",
+            "1:11: error: The time signature must have a numerator and denominator above 0: 3/0",
+            "3:1: warning: Measure 1 has 3 beats in line 1 of its block, but 4/4 needs 4",
+            "3:14: warning: The time signature changes in the middle of measure 1",
+        ]
+    );
+}
+
+#[test]
+fn test_unreadable_scores_are_one_error() {
+    let problems = hum::check_score("| (Cn_4 1/4 ;\n").unwrap();
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].severity, Severity::Error);
+    let location = problems[0].location.as_ref().unwrap();
+    assert_eq!((location.line, location.column), (1, 13));
+}
@@ -0,0 +1,225 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+// Runs the hum binary with the given arguments, writing `stdin` to it.
+fn run_hum(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_hum"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_score_from_stdin_to_stdout() {
+    let score = "[ 120_bpm ] [ 4/4 ]\n| (An_4 1/4) (Rest 1/4) ;\n";
+    let output = run_hum(&["-", "-"], score);
+    assert!(output.status.success());
+
+    let mut expected = Vec::new();
+    hum::stream_audio(score, hum::hum_io::AudioFormat::Wav, &mut expected).unwrap();
+    assert_eq!(output.stdout, expected);
+}
+
+#[test]
+fn test_score_from_stdin_to_file() {
+    let path = std::env::temp_dir().join("hum_cli_stdin_test.abc");
+    let path = path.to_str().unwrap();
+    let output = run_hum(&["-", path], "[ 120_bpm ] [ 4/4 ]\n| (Cn_4 1/4) ;\n");
+    assert!(output.status.success());
+
+    let abc = std::fs::read_to_string(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert!(abc.starts_with("X:1\n"));
+}
+
+#[test]
+fn test_errors_from_stdin_keep_their_location() {
+    let output = run_hum(
+        &["-", "-"],
+        "[ 120_bpm ] [ 4/4 ]\n| (An_4 1/4) (Qn_4 1/4) ;\n",
+    );
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("There is no note named Qn_4"));
+    assert!(stderr.contains("<stdin>"));
+    assert!(stderr.contains("line: 2, column: 14"));
+}
+
+const UNFORMATTED: &str = "[ 120_bpm ] [ 4/4 ]
+*
+% sine
+| (Cn_4 1/4) (Dn_4 1/4) (En_4 1/2) ;
+% square
+| (Cn_3 1/2) (Gn_3 1/2) ;
+*
+";
+
+#[test]
+fn test_fmt_rewrites_files_in_place() {
+    let path = std::env::temp_dir().join("hum_cli_fmt_test.hum");
+    let path = path.to_str().unwrap();
+    std::fs::write(path, UNFORMATTED).unwrap();
+
+    let output = run_hum(&["fmt", path], "");
+    assert!(output.status.success());
+    let formatted = std::fs::read_to_string(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(formatted, hum::hum_fmt::format_score(UNFORMATTED));
+    assert!(formatted.contains("| (Cn_3 1/2) ------------ (Gn_3 1/2)  ;\n"));
+}
+
+#[test]
+fn test_fmt_check_prints_a_diff() {
+    let path = std::env::temp_dir().join("hum_cli_fmt_check_test.hum");
+    let path = path.to_str().unwrap();
+    std::fs::write(path, UNFORMATTED).unwrap();
+
+    let output = run_hum(&["fmt", "--check", "daisy.hum", path], "");
+    let unchanged = std::fs::read_to_string(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert!(!output.status.success());
+    assert_eq!(unchanged, UNFORMATTED);
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with(&format!("--- {}\n+++ {}\n@@ -1,7 +1,7 @@\n", path, path)));
+    assert!(stdout.contains("\n-| (Cn_3 1/2) (Gn_3 1/2) ;\n"));
+    assert!(stdout.contains("\n+| (Cn_3 1/2) ------------ (Gn_3 1/2)  ;\n"));
+    // Formatted files aren't mentioned:
+    assert!(!stdout.contains("daisy.hum"));
+
+    let output = run_hum(&["fmt", "--check", "daisy.hum", "complex.hum"], "");
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    // Files with Windows line endings are not formatted either:
+    let formatted = hum::hum_fmt::format_score(UNFORMATTED);
+    let output = run_hum(&["fmt", "--check", "-"], &formatted.replace('\n', "\r\n"));
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "<stdin>: line endings differ\n"
+    );
+}
+
+#[test]
+fn test_fmt_stdin_to_stdout() {
+    let output = run_hum(&["fmt", "-"], UNFORMATTED);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        hum::hum_fmt::format_score(UNFORMATTED)
+    );
+}
+
+#[test]
+fn test_check_reports_problems() {
+    let score = "% kazoo\n| (Cn_4 1/4) (Qn_4 1/4) ;\n";
+    let output = run_hum(&["check", "-"], score);
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "<stdin>:1:1: warning: There is no voice named kazoo, so it is played as sine\n\
+         <stdin>:2:1: warning: Measure 1 has 2 beats in line 1 of its block, but 4/4 needs 4\n\
+         <stdin>:2:14: error: There is no note named Qn_4\n"
+    );
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("1 error(s), 2 warning(s)")
+    );
+
+    let output = run_hum(&["check", "--format", "json", "-"], score);
+    assert!(!output.status.success());
+    let json = String::from_utf8(output.stdout).unwrap();
+    assert!(json.starts_with("[\n  {\"severity\": \"warning\", "));
+    assert!(json.contains(
+        "{\"severity\": \"error\", \"message\": \"There is no note named Qn_4\", \
+         \"file\": \"<stdin>\", \"line\": 2, \"column\": 14}\n]\n"
+    ));
+
+    // Warnings alone don't fail the check:
+    let output = run_hum(
+        &["check", "--format", "json", "-"],
+        "% kazoo\n| (Cn_4 1/1) ;\n",
+    );
+    assert!(output.status.success());
+    let output = run_hum(&["check", "daisy.hum"], "");
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_info_prints_a_summary() {
+    let output = run_hum(&["info", "daisy.hum"], "");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with(
+        "Duration: 0:32.000 rendered, 0:32.000 notated\nMeasures: 32 written, 32 played\n"
+    ));
+    assert!(stdout.contains("  sawtooth: 164 notes from Cn_1 to En_4\n"));
+    assert!(stdout.contains("  16              31-32     0:30.000  0:02.000\n"));
+
+    let output = run_hum(&["info", "-"], "| (Qn_4 1/4) ;\n");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_closed_stdout_is_not_an_error() {
+    // The reader stops reading before the score is printed, like `head`:
+    for args in [
+        &["info", "-"][..],
+        &["check", "-"],
+        &["fmt", "--check", "-"],
+    ] {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_hum"))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        drop(child.stdout.take());
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(b"% kazoo\n| (Cn_4 1/4) ;\n")
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(
+            !String::from_utf8(output.stderr)
+                .unwrap()
+                .contains("panicked")
+        );
+    }
+}
@@ -0,0 +1,65 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Helpers shared by the import tests.
+
+use hum::hum_parse::parse_located_score;
+use hum::hum_process;
+use hum::hum_process::hum_tuning::Tuning;
+
+// Returns the lines of a score that hold notes, without the formatter's padding.
+pub fn note_lines(score: &str) -> Vec<String> {
+    score
+        .lines()
+        .filter(|line| line.trim_start().starts_with('|'))
+        .map(|line| {
+            let mut depth = 0;
+            let unpadded: String = line
+                .chars()
+                .map(|c| {
+                    depth += match c {
+                        '(' => 1,
+                        ')' => -1,
+                        _ => 0,
+                    };
+                    if c == '-' && depth == 0 { ' ' } else { c }
+                })
+                .collect();
+            unpadded
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .replace("+|", "+ |")
+                .replace("+;", "+ ;")
+        })
+        .collect()
+}
+
+// Performs a score and returns its notes, without rests, in the order they start.
+pub fn performed_notes(score: &str) -> Vec<hum_process::PerformedNote> {
+    let commands = parse_located_score(score).unwrap();
+    let performance = hum_process::perform_located_commands(commands, Tuning::default()).unwrap();
+    let mut notes = performance.notes;
+    notes.retain(|note| !note.frequency.is_nan()); // Rests have no frequency
+    notes.sort_by(|a, b| {
+        (a.start, a.frequency)
+            .partial_cmp(&(b.start, b.frequency))
+            .unwrap()
+    });
+    notes
+}
@@ -58,3 +58,15 @@ fn test_dotted_note_duration() {
         "Double dot duration incorrect"
     );
 }
+
+#[test]
+fn test_tempo_without_time_signature() {
+    // Each bar line starts a full 4/4 measure of 2 seconds at 120 bpm, so the
+    // second measure starts at 2 seconds and its quarter note ends at 2.5:
+    let commands = hum_grammar::score("[ 120_bpm ] | (An_4 1/4) | (An_4 1/4)").unwrap();
+    let audio = hum_process::run_commands(commands).unwrap();
+    assert_eq!(
+        audio.len(),
+        SAMPLE_RATE as usize * 2 + SAMPLE_RATE as usize / 2
+    );
+}
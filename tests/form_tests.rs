@@ -0,0 +1,95 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use hum::hum_parse::{hum_grammar, parse_located_score};
+use hum::hum_process;
+use hum::hum_process::hum_tuning::Tuning;
+
+fn render(score: &str) -> Vec<f32> {
+    let commands = hum_grammar::score(score).unwrap();
+    hum_process::run_commands(commands).unwrap()
+}
+
+#[test]
+fn test_repeat_expands_like_written_out_music() {
+    assert_eq!(
+        render("[ 60_bpm ] [ 1/4 ] |: (Cn_4 1/4) | (Dn_4 1/4) :| | (En_4 1/4)"),
+        render(
+            "[ 60_bpm ] [ 1/4 ] | (Cn_4 1/4) | (Dn_4 1/4) | (Cn_4 1/4) | (Dn_4 1/4) | (En_4 1/4)"
+        )
+    );
+}
+
+#[test]
+fn test_endings_across_lines() {
+    let score = "[ 60_bpm ] [ 1/4 ]
+|: (Cn_4 1/4) |1 (Dn_4 1/4) :| |2 (En_4 1/4) ;
+|: (Cn_3 1/4) |1 (Dn_3 1/4) :| |2 (En_3 1/4) ;";
+    let written_out = "[ 60_bpm ] [ 1/4 ]
+| (Cn_4 1/4) | (Dn_4 1/4) | (Cn_4 1/4) | (En_4 1/4) ;
+| (Cn_3 1/4) | (Dn_3 1/4) | (Cn_3 1/4) | (En_3 1/4) ;";
+    assert_eq!(render(score), render(written_out));
+}
+
+#[test]
+fn test_dal_segno_al_coda() {
+    let score = "[ 60_bpm ] [ 1/4 ] | (Cn_4 1/4) | [ segno ] (Dn_4 1/4) [ to coda ] \
+                 | (En_4 1/4) [ D.S. al coda ] | [ coda ] (Fn_4 1/4)";
+    let written_out = "[ 60_bpm ] [ 1/4 ] | (Cn_4 1/4) | (Dn_4 1/4) | (En_4 1/4) \
+                       | (Dn_4 1/4) | (Fn_4 1/4)";
+    assert_eq!(render(score), render(written_out));
+}
+
+#[test]
+fn test_errors_point_at_the_source() {
+    let score = "[ 60_bpm ]\n|: (Cn_4 1/4) :|\n|: (Xx_4 1/4) :|\n";
+    let commands = parse_located_score(score).unwrap();
+    let error = hum_process::run_located_commands(commands, Tuning::default()).unwrap_err();
+    let location = error
+        .location
+        .as_ref()
+        .expect("the error should have a location");
+    assert_eq!((location.line, location.column), (3, 4));
+    assert!(error.to_string().ends_with("at 3:4"));
+
+    let commands = parse_located_score("[ D.S. ] | (Cn_4 1/4)").unwrap();
+    assert!(hum_process::run_located_commands(commands, Tuning::default()).is_err());
+}
+
+#[test]
+fn test_form_arranges_named_sections() {
+    let score = "[ 60_bpm ] [ 1/4 ] [ form verse chorus verse ]
+*** verse ***
+| (Cn_4 1/4) | (Dn_4 1/4) ;
+*****
+| (En_4 1/4) ;
+*** chorus ***
+| (Gn_4 1/4) ;";
+    let written_out = "[ 60_bpm ] [ 1/4 ]
+| (Cn_4 1/4) | (Dn_4 1/4) | (En_4 1/4) | (Gn_4 1/4) | (Cn_4 1/4) | (Dn_4 1/4) | (En_4 1/4)";
+    assert_eq!(render(score), render(written_out));
+}
+
+#[test]
+fn test_form_with_unknown_section() {
+    let score = "[ 60_bpm ]\n[ form verse bridge ]\n*** verse ***\n| (Cn_4 1/4) ;";
+    let commands = parse_located_score(score).unwrap();
+    let error = hum_process::run_located_commands(commands, Tuning::default()).unwrap_err();
+    assert_eq!(error.message, "There is no section named bridge");
+    assert_eq!(error.location.map(|location| location.line), Some(2));
+}
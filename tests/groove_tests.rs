@@ -0,0 +1,77 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use hum::hum_parse::hum_grammar;
+use hum::hum_process;
+
+fn render(score: &str) -> Vec<f32> {
+    let commands = hum_grammar::score(score).unwrap();
+    hum_process::run_commands(commands).unwrap()
+}
+
+#[test]
+fn test_swing_plays_eighth_note_pairs_long_short() {
+    assert_eq!(
+        render("[ 60_bpm ] [ 2/4 ] [ swing 75% ] | (Cn_4 1/8) (Dn_4 1/8) (En_4 1/8) (Fn_4 1/8)"),
+        render("[ 60_bpm ] [ 2/4 ] | (Cn_4 3/16) (Dn_4 1/16) (En_4 3/16) (Fn_4 1/16)")
+    );
+}
+
+#[test]
+fn test_swing_leaves_beats_alone() {
+    assert_eq!(
+        render("[ 60_bpm ] [ 2/4 ] [ swing 66% ] | (Cn_4 1/4) (Dn_4 1/4)"),
+        render("[ 60_bpm ] [ 2/4 ] | (Cn_4 1/4) (Dn_4 1/4)")
+    );
+    assert_eq!(
+        render("[ 60_bpm ] [ 2/4 ] [ swing 66% ] [ swing off ] | (Cn_4 1/8) (Dn_4 1/8)"),
+        render("[ 60_bpm ] [ 2/4 ] | (Cn_4 1/8) (Dn_4 1/8)")
+    );
+}
+
+#[test]
+fn test_humanize_is_repeatable() {
+    let score = |seed: u32| {
+        format!(
+            "[ 60_bpm ] [ 4/4 ] [ humanize timing=20ms velocity=10% seed={} ] \
+             | (Cn_4 1/4) (Dn_4 1/4) (En_4 1/4) (Fn_4 1/4)",
+            seed
+        )
+    };
+    let straight = render("[ 60_bpm ] [ 4/4 ] | (Cn_4 1/4) (Dn_4 1/4) (En_4 1/4) (Fn_4 1/4)");
+
+    assert_eq!(render(&score(1)), render(&score(1)));
+    assert_ne!(render(&score(1)), render(&score(2)));
+    assert_ne!(render(&score(1)), straight);
+}
+
+#[test]
+fn test_invalid_groove() {
+    for score in [
+        "[ swing 100% ]",
+        "[ swing fast ]",
+        "[ humanize timing=20 ]",
+        "[ humanize velocity=150% ]",
+        "[ humanize timing=infms ]",
+        "[ humanize timing=NaNms ]",
+        "[ swing NaN% ]",
+    ] {
+        let commands = hum_grammar::score(score).unwrap();
+        assert!(hum_process::run_commands(commands).is_err(), "{}", score);
+    }
+}
@@ -0,0 +1,133 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use hum::hum_error::HumError;
+use hum::hum_include::parse_with_includes;
+use hum::hum_parse::hum_grammar;
+use hum::hum_process;
+use hum::hum_process::hum_tuning::Tuning;
+
+// Writes the given files into a fresh directory under the system temp dir.
+fn write_files(dir_name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(dir_name);
+    let _ = fs::remove_dir_all(&dir);
+    for (name, contents) in files {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+    dir
+}
+
+fn render_file(path: &Path) -> Result<Vec<f32>, HumError> {
+    let contents = fs::read_to_string(path).unwrap();
+    let commands = parse_with_includes(&contents, Some(path))?;
+    Ok(hum_process::run_located_commands(
+        commands,
+        Tuning::default(),
+    )?)
+}
+
+fn render(score: &str) -> Vec<f32> {
+    hum_process::run_commands(hum_grammar::score(score).unwrap()).unwrap()
+}
+
+#[test]
+fn test_nested_includes_resolve_relative_to_including_file() {
+    let dir = write_files(
+        "hum_include_tests_nested",
+        &[
+            (
+                "song.hum",
+                "[ 60_bpm ] [ 1/4 ]\n[ include \"parts/verse.hum\" ]\n| (Gn_4 1/4) ;\n",
+            ),
+            (
+                "parts/verse.hum",
+                "| (Cn_4 1/4) ;\n[ include \"drums.hum\" ]\n",
+            ),
+            ("parts/drums.hum", "| (En_4 1/4) ;\n"),
+        ],
+    );
+
+    assert_eq!(
+        render_file(&dir.join("song.hum")).unwrap(),
+        render("[ 60_bpm ] [ 1/4 ]\n| (Cn_4 1/4) ;\n| (En_4 1/4) ;\n| (Gn_4 1/4) ;\n")
+    );
+}
+
+#[test]
+fn test_include_cycle_is_an_error() {
+    let dir = write_files(
+        "hum_include_tests_cycle",
+        &[
+            ("a.hum", "[ 60_bpm ]\n[ include \"b.hum\" ]\n"),
+            ("b.hum", "| (Cn_4 1/4) ;\n[ include \"a.hum\" ]\n"),
+        ],
+    );
+
+    match render_file(&dir.join("a.hum")) {
+        Err(HumError::IncludeError(error)) => {
+            assert_eq!(error.message, "Include cycle through \"a.hum\"");
+            let location = error.location.unwrap();
+            assert!(location.file.unwrap().ends_with("b.hum"));
+            assert_eq!((location.line, location.column), (2, 1));
+        }
+        other => panic!("expected an include cycle error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_missing_include_points_at_directive() {
+    let dir = write_files(
+        "hum_include_tests_missing",
+        &[("song.hum", "[ 60_bpm ]\n  [ include \"nowhere.hum\" ]\n")],
+    );
+
+    match render_file(&dir.join("song.hum")) {
+        Err(HumError::IncludeError(error)) => {
+            let location = error.location.unwrap();
+            assert!(location.file.unwrap().ends_with("song.hum"));
+            assert_eq!((location.line, location.column), (2, 3));
+        }
+        other => panic!("expected a missing include error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_errors_point_into_included_file() {
+    let dir = write_files(
+        "hum_include_tests_errors",
+        &[
+            ("song.hum", "[ 60_bpm ] [ 1/4 ]\n[ include \"bad.hum\" ]\n"),
+            ("bad.hum", "| (Cn_4 1/4) ;\n| (Xx_4 1/4) ;\n"),
+        ],
+    );
+
+    match render_file(&dir.join("song.hum")) {
+        Err(HumError::GenerateError(error)) => {
+            assert!(error.to_string().ends_with("bad.hum:2:3"));
+            let location = error.location.unwrap();
+            assert!(location.file.unwrap().ends_with("bad.hum"));
+            assert_eq!((location.line, location.column), (2, 3));
+        }
+        other => panic!("expected an error in the included file, got {:?}", other),
+    }
+}
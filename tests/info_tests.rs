@@ -0,0 +1,185 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use hum::SAMPLE_RATE;
+use hum::hum_parse::hum_grammar;
+use hum::hum_process;
+use hum::hum_process::hum_info::{BlockTiming, Change, VoiceRange};
+
+const SCORE: &str = "[ 120_bpm ] [ 4/4 ]
+*** intro ***
+% sine
+|: (Cn_4 1/2) (En_4 1/2) :| ;
+% square
+|: (Rest 1/1) :| ;
+*
+[ 60_bpm ] [ 3/4 ]
+% sawtooth vibrato=6:30
+| (Gn_2 1/4) (Cs_5 1/4) (An_3 1/4)^ | (Rest 1/2+) ;
+% sine
+| (Cn_5 1/2) (Bn_3 1/4) | (Rest 1/2+) ;
+";
+
+#[test]
+fn test_score_info() {
+    let info = hum::describe_score(SCORE).unwrap();
+
+    // The first measure is repeated, and the fermata holds the third measure
+    // for an extra beat:
+    assert_eq!(info.duration, 2.0 * 2.0 + 4.0 + 3.0);
+    assert_eq!(info.notated_duration, info.duration);
+    assert_eq!((info.written_measures, info.played_measures), (3, 4));
+    assert_eq!(
+        info.tempos,
+        vec![
+            Change {
+                measure: 1,
+                start: Some(0.0),
+                value: 120.0
+            },
+            Change {
+                measure: 2,
+                start: Some(4.0),
+                value: 60.0
+            },
+        ]
+    );
+    assert_eq!(
+        info.time_signatures
+            .iter()
+            .map(|change| (change.measure, change.value))
+            .collect::<Vec<_>>(),
+        vec![(1, (4, 4)), (2, (3, 4))]
+    );
+    assert_eq!(
+        info.voices,
+        vec![
+            VoiceRange {
+                name: "sine".to_string(),
+                notes: 4,
+                range: Some(("Bn_3".to_string(), "Cn_5".to_string())),
+            },
+            VoiceRange {
+                name: "square".to_string(),
+                notes: 0,
+                range: None,
+            },
+            VoiceRange {
+                name: "sawtooth".to_string(),
+                notes: 3,
+                range: Some(("Gn_2".to_string(), "Cs_5".to_string())),
+            },
+        ]
+    );
+    assert_eq!(
+        info.blocks,
+        vec![
+            BlockTiming {
+                name: Some("intro".to_string()),
+                measures: (1, 1),
+                start: Some(0.0),
+                duration: 4.0,
+            },
+            BlockTiming {
+                name: None,
+                measures: (2, 3),
+                start: Some(4.0),
+                duration: 7.0,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_score_info_report() {
+    assert_eq!(
+        hum::describe_score(SCORE).unwrap().to_string(),
+        "Duration: 0:11.000 rendered, 0:11.000 notated
+Measures: 3 written, 4 played
+Tempo:
+  measure 1 at 0:00.000: 120 bpm
+  measure 2 at 0:04.000: 60 bpm
+Time signatures:
+  measure 1 at 0:00.000: 4/4
+  measure 2 at 0:04.000: 3/4
+Voices:
+  sine: 4 notes from Bn_3 to Cn_5
+  square: rests only
+  sawtooth: 3 notes from Gn_2 to Cs_5
+Blocks:
+  Block  Section  Measures  Start     Duration
+  1      intro    1         0:00.000  0:04.000
+  2               2-3       0:04.000  0:07.000
+"
+    );
+}
+
+#[test]
+fn test_duration_matches_rendered_audio() {
+    for score in [SCORE, "[ 60_bpm ]\n| (Cn_4 1/4) ;\n"] {
+        let info = hum::describe_score(score).unwrap();
+        let audio = hum_process::run_commands(hum_grammar::score(score).unwrap()).unwrap();
+        assert_eq!(info.duration, audio.len() as f32 / SAMPLE_RATE as f32);
+    }
+
+    // The last measure isn't filled, so the audio ends before it does:
+    let info = hum::describe_score("[ 60_bpm ]\n| (Cn_4 1/4) ;\n").unwrap();
+    assert_eq!((info.duration, info.notated_duration), (1.0, 4.0));
+    assert!(
+        info.to_string()
+            .starts_with("Duration: 0:01.000 rendered, 0:04.000 notated\n")
+    );
+}
+
+#[test]
+fn test_measures_left_out_by_the_form() {
+    let info = hum::describe_score(
+        "[ 60_bpm ] [ 1/4 ] [ form chorus ]
+*** verse ***
+| (Cn_4 1/4) ;
+*** chorus ***
+[ 90_bpm ]
+| (En_4 1/4) | (Gn_4 1/4) ;
+",
+    )
+    .unwrap();
+
+    assert_eq!((info.written_measures, info.played_measures), (3, 2));
+    assert_eq!(
+        info.tempos
+            .iter()
+            .map(|change| (change.measure, change.start))
+            .collect::<Vec<_>>(),
+        vec![(1, None), (2, Some(0.0))]
+    );
+    assert_eq!(info.blocks[0].start, None);
+    assert_eq!(info.blocks[0].duration, 0.0);
+    assert!(
+        info.to_string()
+            .contains("  measure 1 (not played): 60 bpm\n")
+    );
+    assert!(
+        info.to_string()
+            .contains("  1      verse    1         -         0:00.000\n")
+    );
+}
+
+#[test]
+fn test_errors_stop_the_report() {
+    assert!(hum::describe_score("| (Qn_4 1/4) ;").is_err());
+}
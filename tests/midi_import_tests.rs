@@ -0,0 +1,223 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+mod common;
+
+use common::{note_lines, performed_notes};
+use hum::hum_error::HumError;
+use hum::hum_midi;
+use hum::hum_parse::parse_located_score;
+use hum::hum_process;
+use hum::hum_process::hum_tuning::Tuning;
+
+const DIVISION: u16 = 96;
+
+fn export(score: &str) -> Vec<u8> {
+    let commands = parse_located_score(score).unwrap();
+    let performance = hum_process::perform_located_commands(commands, Tuning::default()).unwrap();
+    hum_midi::encode(&performance)
+}
+
+// Builds a Type 0 MIDI file from one track's events (delta times included).
+fn single_track_file(events: &[u8]) -> Vec<u8> {
+    let mut bytes = b"MThd".to_vec();
+    bytes.extend_from_slice(&6u32.to_be_bytes());
+    bytes.extend_from_slice(&0u16.to_be_bytes());
+    bytes.extend_from_slice(&1u16.to_be_bytes());
+    bytes.extend_from_slice(&DIVISION.to_be_bytes());
+    bytes.extend_from_slice(b"MTrk");
+    bytes.extend_from_slice(&(events.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(events);
+    bytes
+}
+
+#[test]
+fn test_export_round_trip() {
+    let score = "[ 90_bpm ] [ 3/4 ]\n\
+                 *\n\
+                 % square\n\
+                 | (Cn_4 1/4) (Ds_4 1/8) (En_4 1/8) (Gn_4 1/4) | (Cn_5 1/2)+ ;\n\
+                 % sine\n\
+                 | (Cn_3 1/2)+ | (Gn_3 1/2) (Rest 1/4) ;\n";
+
+    let imported = hum::import_midi(&export(score)).unwrap();
+
+    assert!(imported.starts_with("[ 90_bpm ] [ 3/4 ]\n"));
+    assert!(imported.contains("% square\n"));
+    assert_eq!(
+        note_lines(&imported),
+        vec![
+            "| (Cn_4 1/4) (Ds_4 1/8) (En_4 1/8) (Gn_4 1/4) | (Cn_5 1/2)+ ;",
+            "| (Cn_3 1/2)+ | (Gn_3 1/2) (Rest 1/4) ;",
+        ]
+    );
+
+    // The imported score renders the same notes as the original:
+    let (original, reimported) = (performed_notes(score), performed_notes(&imported));
+    assert_eq!(original.len(), reimported.len());
+    for (a, b) in original.iter().zip(&reimported) {
+        assert!((a.start - b.start).abs() < 1e-4);
+        assert!((a.duration - b.duration).abs() < 1e-4);
+        assert!((a.frequency - b.frequency).abs() < 1e-2);
+    }
+}
+
+#[test]
+fn test_notes_are_quantized_with_triplets() {
+    // Running status, note on with velocity 0 as note off, and times a few
+    // ticks away from the grid. Eighth note triplets are 32 ticks long.
+    let events = [
+        0x00, 0x90, 60, 100, // C4 on
+        0x21, 60, 0, // off after 33 ticks, with running status
+        0x00, 62, 100, // D4 on
+        0x1F, 62, 0, // off after 31 ticks
+        0x00, 64, 100, // E4 on
+        0x20, 64, 0, // off after 32 ticks
+        0x00, 65, 100, // F4 on
+        0x82, 0x1F, 0x80, 65, 0, // note off after 287 ticks
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+
+    let imported = hum::import_midi(&single_track_file(&events)).unwrap();
+
+    assert_eq!(
+        note_lines(&imported),
+        vec!["| (Cn_4 1/12) (Dn_4 1/12) (En_4 1/12) (Fn_4 1/2)+ ;"]
+    );
+}
+
+#[test]
+fn test_humanized_notes_snap_to_the_nearest_grid() {
+    // Quarter notes that start and end a few ticks away from the beat:
+    let events = [
+        0x03, 0x90, 60, 100, // C4 on at tick 3
+        0x5A, 60, 0, // off at tick 93
+        0x05, 62, 100, // D4 on at tick 98
+        0x5C, 62, 0, // off at tick 190
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+
+    let imported = hum::import_midi(&single_track_file(&events)).unwrap();
+
+    assert_eq!(
+        note_lines(&imported),
+        vec!["| (Cn_4 1/4) (Dn_4 1/4) (Rest 1/2) ;"]
+    );
+}
+
+#[test]
+fn test_zero_tempo_and_empty_time_signature_are_ignored() {
+    let events = [
+        0x00, 0xFF, 0x51, 0x03, 0x00, 0x00, 0x00, // tempo of 0 microseconds per quarter
+        0x00, 0xFF, 0x58, 0x04, 0x00, 0x02, 0x18, 0x08, // 0/4 time
+        0x00, 0x90, 60, 100, // C4 on
+        0x60, 0x80, 60, 0, // off after a quarter note
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+
+    let imported = hum::import_midi(&single_track_file(&events)).unwrap();
+
+    assert!(imported.starts_with("[ 60_bpm ] [ 4/4 ]\n"));
+    assert_eq!(note_lines(&imported), vec!["| (Cn_4 1/4) (Rest 1/2)+ ;"]);
+}
+
+#[test]
+fn test_key_signature_spells_flats_and_channels_split_parts() {
+    let events = [
+        0x00, 0xFF, 0x03, 0x04, b'L', b'e', b'a', b'd', // track name
+        0x00, 0xFF, 0x59, 0x02, 0xFD, 0x00, // three flats
+        0x00, 0x90, 63, 100, // Eb4 on channel 1
+        0x00, 0x91, 46, 100, // Bb2 on channel 2
+        0x81, 0x40, 0x80, 63, 64, // both off after a half note
+        0x00, 0x81, 46, 64, //
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+
+    let imported = hum::import_midi(&single_track_file(&events)).unwrap();
+
+    assert!(imported.contains("~ Lead (channel 1)\n"));
+    assert!(imported.contains("~ Lead (channel 2)\n"));
+    assert_eq!(
+        note_lines(&imported),
+        vec!["| (Ef_4 1/2) (Rest 1/2) ;", "| (Bf_2 1/2) (Rest 1/2) ;",]
+    );
+}
+
+#[test]
+fn test_notes_cross_bar_lines_and_lyrics_follow_notes() {
+    let events = [
+        0x00, 0xFF, 0x05, 0x03, b'l', b'a', b'-', // lyric
+        0x00, 0x90, 60, 100, // C4 on
+        0x83, 0x60, 0x80, 60, 0, // off after 480 ticks (a whole note and a quarter)
+        0x00, 0xFF, 0x05, 0x02, b'l', b'a', // lyric
+        0x00, 0x90, 67, 100, // G4 on
+        0x60, 0x80, 67, 0, // off after a quarter note
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+
+    let imported = hum::import_midi(&single_track_file(&events)).unwrap();
+
+    assert_eq!(
+        note_lines(&imported),
+        vec!["| (Cn_4 1/1) | (Cn_4 1/4) (Gn_4 1/4) (Rest 1/2) ;"]
+    );
+    let lines: Vec<&str> = imported.lines().collect();
+    let notes = lines.iter().position(|line| line.starts_with('|')).unwrap();
+    let lyrics = lines[notes + 1];
+    assert_eq!(lyrics.find("~ la-"), lines[notes].find("(Cn_4"));
+    assert_eq!(lyrics.rfind("~ la"), lines[notes].find("(Gn_4"));
+}
+
+#[test]
+fn test_invalid_file_is_an_import_error() {
+    assert!(matches!(
+        hum::import_midi(b"RIFF...."),
+        Err(HumError::ImportError(_))
+    ));
+
+    let truncated = single_track_file(&[0x00, 0x90, 60]);
+    assert!(matches!(
+        hum::import_midi(&truncated),
+        Err(HumError::ImportError(_))
+    ));
+
+    // Times past the end of what a score can hold are too long:
+    let too_long = single_track_file(&[
+        0xFF, 0xFF, 0xFF, 0x7F, 0x90, 60, 100, // C4 on after the longest delta time
+        0xFF, 0xFF, 0xFF, 0x7F, 0x80, 60, 0, // and off after another
+        0x00, 0xFF, 0x2F, 0x00,
+    ]);
+    assert!(matches!(
+        hum::import_midi(&too_long),
+        Err(HumError::ImportError(error)) if error.message == "The MIDI file is too long"
+    ));
+
+    // Oversized lengths end the track instead of overflowing:
+    for events in [
+        &[0x00, 0xFF, 0x01, 0x8F, 0xFF, 0xFF, 0x7F, b'x'][..],
+        &[0x00, 0xF0, 0x8F, 0xFF, 0xFF, 0x7F, 0xF7],
+        &[
+            0x00, 0xFF, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F,
+        ],
+    ] {
+        assert!(matches!(
+            hum::import_midi(&single_track_file(events)),
+            Err(HumError::ImportError(_))
+        ));
+    }
+}
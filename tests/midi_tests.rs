@@ -0,0 +1,159 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use hum::hum_midi;
+use hum::hum_parse::parse_located_score;
+use hum::hum_process;
+use hum::hum_process::hum_tuning::Tuning;
+
+// An event in a track: its absolute tick and its bytes (without the delta time).
+type TrackEvent = (u64, Vec<u8>);
+
+fn encode(score: &str) -> Vec<u8> {
+    let commands = parse_located_score(score).unwrap();
+    let performance = hum_process::perform_located_commands(commands, Tuning::default()).unwrap();
+    hum_midi::encode(&performance)
+}
+
+fn read_variable_length(bytes: &[u8], index: &mut usize) -> u64 {
+    let mut value = 0;
+    loop {
+        let byte = bytes[*index];
+        *index += 1;
+        value = (value << 7) | (byte & 0x7F) as u64;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+    }
+}
+
+// Splits a MIDI file into the events of each track. Only handles the events
+// that Hum writes (meta events and note on/off without running status).
+fn read_tracks(bytes: &[u8]) -> Vec<Vec<TrackEvent>> {
+    assert_eq!(&bytes[0..4], b"MThd");
+    let mut tracks = Vec::new();
+    let mut index = 14;
+
+    while index < bytes.len() {
+        assert_eq!(&bytes[index..index + 4], b"MTrk");
+        let length = u32::from_be_bytes(bytes[index + 4..index + 8].try_into().unwrap()) as usize;
+        let end = index + 8 + length;
+        index += 8;
+
+        let mut events = Vec::new();
+        let mut tick = 0;
+        while index < end {
+            tick += read_variable_length(bytes, &mut index);
+            let start = index;
+            if bytes[index] == 0xFF {
+                index += 2;
+                let length = read_variable_length(bytes, &mut index) as usize;
+                index += length;
+            } else {
+                index += 3;
+            }
+            events.push((tick, bytes[start..index].to_vec()));
+        }
+        tracks.push(events);
+    }
+
+    tracks
+}
+
+#[test]
+fn test_header_and_tracks() {
+    let bytes = encode(
+        "[ 120_bpm ] [ 3/4 ]
+*
+% square
+| (Cn_4 1/4) (En_4 1/2) ;
+% sine
+| (Cn_3 1/2)+ ;",
+    );
+
+    assert_eq!(&bytes[8..10], &[0, 1]); // Type 1
+    assert_eq!(&bytes[10..12], &[0, 3]); // Conductor track plus two lines
+    assert_eq!(&bytes[12..14], &480u16.to_be_bytes());
+
+    let tracks = read_tracks(&bytes);
+    assert_eq!(tracks.len(), 3);
+
+    // 120 beats per minute in 3/4 is 500,000 microseconds per quarter note:
+    assert!(tracks[0].contains(&(0, vec![0xFF, 0x51, 3, 0x07, 0xA1, 0x20])));
+    assert!(tracks[0].contains(&(0, vec![0xFF, 0x58, 4, 3, 2, 24, 8])));
+
+    assert_eq!(
+        tracks[1][0],
+        (0, [&[0xFF, 0x03, 6][..], b"square"].concat())
+    );
+    assert_eq!(tracks[2][0], (0, [&[0xFF, 0x03, 4][..], b"sine"].concat()));
+
+    let notes: Vec<&TrackEvent> = tracks[1].iter().filter(|(_, e)| e[0] < 0xF0).collect();
+    assert_eq!(
+        notes,
+        vec![
+            &(0, vec![0x90, 60, 80]),
+            &(480, vec![0x80, 60, 0]),
+            &(480, vec![0x90, 64, 80]),
+            &(1440, vec![0x80, 64, 0]),
+        ]
+    );
+    assert!(tracks[2].contains(&(0, vec![0x91, 48, 80])));
+    assert!(tracks[2].contains(&(1440, vec![0x81, 48, 0])));
+}
+
+#[test]
+fn test_tempo_and_time_changes_follow_the_performance() {
+    let bytes = encode(
+        "[ 60_bpm ] [ 4/4 ]
+|: (Cn_4 1/1) :|
+[ 180_bpm ] [ 6/8 ]
+| (Rest 1/4) (Dn_4 1/8)+ (Rest 1/4)+ (En_4 1/16)",
+    );
+    let tracks = read_tracks(&bytes);
+
+    // The repeated measure plays twice, so the change comes after two measures:
+    let conductor: Vec<&TrackEvent> = tracks[0].iter().filter(|(_, e)| e[1] != 0x2F).collect();
+    assert_eq!(
+        conductor,
+        vec![
+            &(0, vec![0xFF, 0x51, 3, 0x0F, 0x42, 0x40]),
+            &(0, vec![0xFF, 0x58, 4, 4, 2, 24, 8]),
+            &(3840, vec![0xFF, 0x51, 3, 0x0A, 0x2C, 0x2B]),
+            &(3840, vec![0xFF, 0x58, 4, 6, 3, 24, 8]),
+        ]
+    );
+
+    let note_ons: Vec<(u64, u8)> = tracks[1]
+        .iter()
+        .filter(|(_, e)| e[0] & 0xF0 == 0x90)
+        .map(|(tick, e)| (*tick, e[1]))
+        .collect();
+    assert_eq!(note_ons, vec![(0, 60), (1920, 60), (4320, 62), (5400, 64)]);
+}
+
+#[test]
+fn test_accents_are_louder() {
+    let tracks = read_tracks(&encode("[ 60_bpm ] | (Cn_4 1/4)> (Cn_4 1/4)"));
+    let velocities: Vec<u8> = tracks[1]
+        .iter()
+        .filter(|(_, e)| e[0] == 0x90)
+        .map(|(_, e)| e[2])
+        .collect();
+    assert_eq!(velocities, vec![120, 80]);
+}
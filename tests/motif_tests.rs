@@ -0,0 +1,75 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use hum::hum_error::GenerateError;
+use hum::hum_parse::parse_located_score;
+use hum::hum_process;
+use hum::hum_process::hum_tuning::Tuning;
+
+fn render(score: &str) -> Result<Vec<f32>, GenerateError> {
+    let commands = hum_process::expand_motifs(parse_located_score(score).unwrap())?;
+    hum_process::run_located_commands(commands, Tuning::default())
+}
+
+#[test]
+fn test_motif_expands_like_written_out_music() {
+    let score = "[ 60_bpm ] [ 1/4 ]
+{ motif riff = (Cn_4 1/8) (En_4 1/8) }
+| @riff (Gn_4 1/4) | @riff+5 @riff-12 ;";
+    let written_out = "[ 60_bpm ] [ 1/4 ]
+| (Cn_4 1/8) (En_4 1/8) (Gn_4 1/4) | (Fn_4 1/8) (An_4 1/8) (Cn_3 1/8) (En_3 1/8) ;";
+    assert_eq!(render(score).unwrap(), render(written_out).unwrap());
+}
+
+#[test]
+fn test_motif_with_bars_and_nested_calls() {
+    let score = "[ 60_bpm ] [ 1/4 ]
+{ motif step = (Cn_4 1/8)+ (Df_4 1/16) }
+{ motif phrase =
+  | @step (Rest 1/4) | @step+2 (En_4 1/4)tr
+}
+@phrase @phrase-1 ;";
+    let written_out = "[ 60_bpm ] [ 1/4 ]
+| (Cn_4 1/8)+ (Df_4 1/16) (Rest 1/4) | (Dn_4 1/8)+ (Ef_4 1/16) (En_4 1/4)tr
+| (Bn_3 1/8)+ (Cn_4 1/16) (Rest 1/4) | (Cs_4 1/8)+ (Dn_4 1/16) (Ds_4 1/4)tr ;";
+    assert_eq!(render(score).unwrap(), render(written_out).unwrap());
+}
+
+#[test]
+fn test_motif_errors() {
+    let error = render("[ 60_bpm ]\n| @riff ;").unwrap_err();
+    assert_eq!(error.message, "There is no motif named riff");
+    let location = error.location.unwrap();
+    assert_eq!((location.line, location.column), (2, 3));
+
+    // Errors inside a motif point at the note in its definition:
+    let error = render("[ 60_bpm ]\n{ motif riff =\n(Xx_4 1/4) }\n| @riff ;").unwrap_err();
+    assert_eq!(error.location.map(|location| location.line), Some(3));
+
+    // Transpositions that don't fit are errors at the call:
+    for call in ["@riff+99999999999", "@riff+2147483647"] {
+        let score = format!("{{ motif riff = (Cn_4 1/4) }}\n| {} ;", call);
+        let error = render(&score).unwrap_err();
+        let location = error.location.unwrap();
+        assert_eq!((location.line, location.column), (2, 3));
+    }
+
+    assert!(render("{ motif riff = [ 90_bpm ] }").is_err());
+    assert!(render("{ motif riff = (Cn_4 1/4)").is_err());
+    assert!(render("{ motif riff = } { motif riff = }").is_err());
+}
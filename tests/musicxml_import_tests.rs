@@ -0,0 +1,219 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+mod common;
+
+use common::{note_lines, performed_notes};
+use hum::hum_error::HumError;
+use hum::hum_musicxml;
+use hum::hum_parse::parse_located_score;
+
+// Wraps the measures of one part in a partwise MusicXML document.
+fn single_part_score(measures: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 4.0 Partwise//EN\" \
+         \"http://www.musicxml.org/dtds/partwise.dtd\">\n\
+         <score-partwise version=\"4.0\">\n\
+         <work><work-title>Test &amp; Song</work-title></work>\n\
+         <part-list><score-part id=\"P1\"><part-name>Flute</part-name></score-part></part-list>\n\
+         <part id=\"P1\">{}</part>\n\
+         </score-partwise>\n",
+        measures
+    )
+}
+
+fn note(step: &str, alter: &str, octave: i32, duration: u32, extra: &str) -> String {
+    format!(
+        "<note><pitch><step>{}</step>{}<octave>{}</octave></pitch>\
+         <duration>{}</duration>{}</note>",
+        step, alter, octave, duration, extra
+    )
+}
+
+#[test]
+fn test_export_round_trip() {
+    let score = std::fs::read_to_string("daisy.hum").unwrap();
+    let commands = parse_located_score(&score).unwrap();
+    let musicxml = hum_musicxml::encode(&commands).unwrap();
+
+    let imported = hum::import_musicxml(&musicxml).unwrap();
+
+    assert!(imported.starts_with("~ DAISY BELL by Harry Dacre\n[ 180_bpm ] [ 3/4 ]\n"));
+    // Every checkpoint block of the original starts a new system, and so a new block:
+    assert_eq!(
+        imported
+            .lines()
+            .filter(|line| line.starts_with('*'))
+            .count(),
+        score.lines().filter(|line| line.starts_with('*')).count()
+    );
+    assert!(imported.contains("% square\n"));
+    assert!(imported.contains("  ~ Dai-                                ~ sy!\n"));
+
+    let (original, reimported) = (performed_notes(&score), performed_notes(&imported));
+    assert_eq!(original.len(), reimported.len());
+    for (a, b) in original.iter().zip(&reimported) {
+        assert!((a.start - b.start).abs() < 1e-3);
+        assert!((a.duration - b.duration).abs() < 1e-3);
+        assert!((a.frequency - b.frequency).abs() < 1e-2);
+    }
+}
+
+#[test]
+fn test_ties_chords_and_voices() {
+    let measures = format!(
+        "<measure number=\"1\">\
+           <attributes><divisions>2</divisions>\
+             <time><beats>3</beats><beat-type>4</beat-type></time></attributes>\
+           <direction><direction-type><words>Lively</words></direction-type>\
+             <sound tempo=\"120\"/></direction>\
+           {}{}{}\
+           <backup><duration>6</duration></backup>\
+           {}\
+         </measure>\
+         <measure number=\"2\">{}{}</measure>",
+        note("C", "", 4, 2, ""),
+        note("E", "", 4, 2, "<chord/>"),
+        note("G", "<alter>1</alter>", 4, 4, "<tie type=\"start\"/>"),
+        note("C", "<alter>-1</alter>", 3, 6, ""),
+        note("G", "<alter>1</alter>", 4, 4, "<tie type=\"stop\"/>"),
+        "<note><rest/><duration>2</duration></note>",
+    );
+
+    let imported = hum::import_musicxml(&single_part_score(&measures)).unwrap();
+
+    assert!(imported.starts_with("~ Test & Song\n[ 120_bpm ] [ 3/4 ]\n"));
+    assert!(imported.contains("~ Flute\n"));
+    assert_eq!(
+        note_lines(&imported),
+        vec![
+            "| (Cn_4 1/4) (Gs_4 1/2) | (Gs_4 1/2) (Rest 1/4) ;",
+            "| (En_4 1/4) (Rest 1/2) | (Rest 1/2)+ ;",
+            "| (Cf_3 1/2)+ | (Rest 1/2)+ ;",
+        ]
+    );
+}
+
+#[test]
+fn test_pickups_lyrics_and_microtones() {
+    let measures = format!(
+        "<measure number=\"0\" implicit=\"yes\">\
+           <attributes><divisions>1</divisions>\
+             <time><beats>2</beats><beat-type>4</beat-type></time></attributes>\
+           {}\
+         </measure>\
+         <measure number=\"1\">{}{}</measure>",
+        note(
+            "A",
+            "",
+            4,
+            1,
+            "<lyric><syllabic>begin</syllabic><text>hel</text></lyric>"
+        ),
+        note(
+            "B",
+            "<alter>-0.5</alter>",
+            4,
+            1,
+            "<lyric><syllabic>end</syllabic><text>lo</text></lyric>"
+        ),
+        note("C", "<alter>0.5</alter>", 5, 1, ""),
+    );
+
+    let imported = hum::import_musicxml(&single_part_score(&measures)).unwrap();
+
+    assert_eq!(
+        note_lines(&imported),
+        vec!["| (Rest 1/4) (An_4 1/4) | (Bn_4-50c 1/4) (Cn_5+50c 1/4) ;"]
+    );
+    let lines: Vec<&str> = imported.lines().collect();
+    let notes = lines.iter().position(|line| line.starts_with('|')).unwrap();
+    assert_eq!(lines[notes + 1].find("~ hel-"), lines[notes].find("(An_4"));
+    assert_eq!(lines[notes + 1].find("~ lo"), lines[notes].find("(Bn_4"));
+}
+
+#[test]
+fn test_system_breaks_start_blocks() {
+    let measure = |number: u32, print: &str| {
+        format!(
+            "<measure number=\"{}\">{}{}</measure>",
+            number,
+            print,
+            note("C", "", 4, 4, "")
+        )
+    };
+    let measures = format!(
+        "{}{}{}",
+        measure(1, "<attributes><divisions>1</divisions></attributes>"),
+        measure(2, "<print new-system=\"yes\"/>"),
+        measure(3, "")
+    );
+
+    let imported = hum::import_musicxml(&single_part_score(&measures)).unwrap();
+
+    assert_eq!(
+        note_lines(&imported),
+        vec!["| (Cn_4 1/1) ;", "| (Cn_4 1/1) | (Cn_4 1/1) ;"]
+    );
+}
+
+#[test]
+fn test_invalid_documents_are_import_errors() {
+    for document in [
+        "<score-timewise version=\"4.0\"></score-timewise>",
+        "<score-partwise><part id=\"P1\"></score-partwise>",
+        "not xml",
+    ] {
+        assert!(matches!(
+            hum::import_musicxml(document),
+            Err(HumError::ImportError(_))
+        ));
+    }
+
+    // Time signatures with no beats, or measures too long to count, are rejected:
+    for beats in ["0", "99999999", "4294967295+1"] {
+        let document = single_part_score(&format!(
+            "<measure number=\"1\"><attributes><divisions>1</divisions>\
+             <time><beats>{}</beats><beat-type>4</beat-type></time></attributes>{}</measure>",
+            beats,
+            note("C", "", 4, 1, "")
+        ));
+        assert!(matches!(
+            hum::import_musicxml(&document),
+            Err(HumError::ImportError(_))
+        ));
+    }
+
+    // Notes that end past the longest time a score can hold are too long:
+    let tie = |kind: &str| format!("<tie type=\"{}\"/>", kind);
+    for notes in [
+        note("C", "", 4, 4473924, "") + &note("D", "", 4, 1, ""),
+        note("C", "", 4, 3000000, &tie("start")) + &note("C", "", 4, 3000000, &tie("stop")),
+        "<forward><duration>1e30</duration></forward>".to_string() + &note("C", "", 4, 1, ""),
+    ] {
+        let document = single_part_score(&format!(
+            "<measure number=\"1\"><attributes><divisions>1</divisions></attributes>{}</measure>",
+            notes
+        ));
+        assert!(matches!(
+            hum::import_musicxml(&document),
+            Err(HumError::ImportError(error)) if error.message == "The MusicXML score is too long"
+        ));
+    }
+}
@@ -0,0 +1,201 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use hum::hum_error::GenerateError;
+use hum::hum_musicxml;
+use hum::hum_parse::parse_located_score;
+
+fn encode(score: &str) -> Result<String, GenerateError> {
+    hum_musicxml::encode(&parse_located_score(score).unwrap())
+}
+
+// Returns the text of each element with the given name, in order.
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    xml.match_indices(&open)
+        .map(|(start, _)| {
+            let start = start + open.len();
+            let end = start + xml[start..].find(&close).unwrap();
+            &xml[start..end]
+        })
+        .collect()
+}
+
+// Returns the part with the given id.
+fn part<'a>(xml: &'a str, id: &str) -> &'a str {
+    let start = xml.find(&format!("<part id=\"{}\">", id)).unwrap();
+    let end = start + xml[start..].find("</part>").unwrap();
+    &xml[start..end]
+}
+
+#[test]
+fn test_lines_become_parts_with_measures() {
+    let xml = encode(
+        "~ Song\n\
+         [ 90_bpm ] [ 3/4 ]\n\
+         *\n\
+         % square\n\
+         | (Cn_4 1/4) (Dn_4 1/4) (En_4 1/4) | (Fs_4 1/2)+ ;\n\
+         % sawtooth\n\
+         | (Cn_3 1/2)+ ;\n\
+         *\n\
+         [ 6/8 ]\n\
+         | (Bf_4 1/8) (Rest 1/8) (Gn_4 1/4)+ ;\n",
+    )
+    .unwrap();
+
+    assert_eq!(elements(&xml, "work-title"), vec!["Song"]);
+    assert_eq!(elements(&xml, "part-name"), vec!["square", "sawtooth"]);
+
+    let top = part(&xml, "P1");
+    assert_eq!(top.matches("<measure ").count(), 3);
+    assert_eq!(elements(top, "beats"), vec!["3", "6"]);
+    assert_eq!(elements(top, "beat-type"), vec!["4", "8"]);
+    assert_eq!(elements(top, "step"), vec!["C", "D", "E", "F", "B", "G"]);
+    assert_eq!(elements(top, "alter"), vec!["1", "-1"]);
+
+    // Hum counts the tempo in beats of the time signature:
+    assert_eq!(elements(top, "beat-unit"), vec!["quarter", "eighth"]);
+    assert_eq!(elements(top, "per-minute"), vec!["90", "90"]);
+    assert!(top.contains("<sound tempo=\"45\"/>"));
+
+    // The lower part rests where it has no notes, and has no tempo marks:
+    let bottom = part(&xml, "P2");
+    assert_eq!(bottom.matches("<measure ").count(), 3);
+    assert_eq!(bottom.matches("<rest measure=\"yes\"/>").count(), 2);
+    assert!(!bottom.contains("<metronome>"));
+    assert!(bottom.contains("<sign>F</sign>"));
+}
+
+#[test]
+fn test_durations_dots_and_tuplets() {
+    let xml = encode(
+        "[ 4/4 ]\n\
+         | (Cn_4 1/4)+ (Dn_4 1/8) (En_4 1/12) (En_4 1/12) (En_4 1/12) (Fn_4 1/4) ;\n\
+         | (Gn_4 1/2)++ (An_4 1/8) ;\n",
+    )
+    .unwrap();
+
+    assert_eq!(elements(&xml, "divisions"), vec!["6", "6"]);
+    let top = part(&xml, "P1");
+    assert_eq!(
+        elements(top, "duration"),
+        vec!["9", "3", "2", "2", "2", "6"]
+    );
+    assert_eq!(
+        elements(top, "type"),
+        vec!["quarter", "eighth", "eighth", "eighth", "eighth", "quarter"]
+    );
+    assert_eq!(top.matches("<dot/>").count(), 1);
+    assert_eq!(elements(top, "actual-notes"), vec!["3", "3", "3"]);
+    assert_eq!(elements(top, "normal-notes"), vec!["2", "2", "2"]);
+
+    let bottom = part(&xml, "P2");
+    assert_eq!(elements(bottom, "duration"), vec!["21", "3"]);
+    assert_eq!(bottom.matches("<dot/>").count(), 2);
+}
+
+#[test]
+fn test_lyrics_follow_their_notes() {
+    let xml = encode(
+        "[ 3/4 ]\n\
+         % square\n\
+         | (Dn_5 1/2)+ ------- | (Bn_4 1/4)  (Gn_4 1/4)  (Rest 1/4) ;\n\
+         \x20 ~ Dai-              ~ sy,       ~ give\n\
+         % sine\n\
+         | (Gn_3 1/2)+ ------- | (Dn_3 1/2)+ ;\n\
+         \n\
+         ~ Not lyrics, since there is a blank line above\n\
+         *\n\
+         | (Dn_5 1/4)  (Dn_5 1/2) ;\n\
+         \x20             ~ me\n",
+    )
+    .unwrap();
+
+    let top = part(&xml, "P1");
+    assert_eq!(elements(top, "text"), vec!["Dai", "sy,", "give", "me"]);
+    assert_eq!(
+        elements(top, "syllabic"),
+        vec!["begin", "end", "single", "single"]
+    );
+    assert!(!part(&xml, "P2").contains("<lyric"));
+
+    // The syllable under the second note of the last measure belongs to it:
+    let last_measure = &top[top.find("<measure number=\"3\">").unwrap()..];
+    let second_note = &last_measure[last_measure.rfind("<note>").unwrap()..];
+    assert!(second_note.contains("<text>me</text>"));
+}
+
+#[test]
+fn test_repeats_endings_and_signs() {
+    let xml =
+        encode("|: (Cn_4 1/1) |1 (Dn_4 1/1) :| |2 (En_4 1/1) | (Fn_4 1/1)^ [ fine ] ;\n").unwrap();
+
+    assert!(xml.contains("<repeat direction=\"forward\"/>"));
+    assert!(xml.contains("<repeat direction=\"backward\"/>"));
+    assert!(xml.contains("<ending number=\"1\" type=\"start\"/>"));
+    assert!(xml.contains("<ending number=\"1\" type=\"stop\"/>"));
+    assert!(xml.contains("<ending number=\"2\" type=\"discontinue\"/>"));
+    assert_eq!(elements(&xml, "words"), vec!["fine"]);
+    assert!(xml.contains("<fermata type=\"upright\"/>"));
+    assert_eq!(xml.matches("<measure ").count(), 4);
+}
+
+#[test]
+fn test_glides_are_not_accents() {
+    let xml = encode("| (Cn_4 1/4)/> (En_4 1/4) (Gn_4 1/4)/>> (Cn_5 1/4) ;\n").unwrap();
+
+    assert_eq!(elements(&xml, "articulations").len(), 1);
+    assert_eq!(xml.matches("<accent/>").count(), 1);
+}
+
+#[test]
+fn test_unknown_notes_are_errors_with_locations() {
+    let error = encode("| (Cn_4 1/4)\n  (Hn_4 1/4) ;\n").unwrap_err();
+    assert_eq!(error.message, "There is no note named Hn_4");
+    let location = error.location.unwrap();
+    assert_eq!((location.line, location.column), (2, 3));
+}
+
+#[test]
+fn test_converts_score_files() {
+    let dir = std::env::temp_dir().join("hum_musicxml_tests");
+    std::fs::create_dir_all(&dir).unwrap();
+    let output = dir.join("daisy.musicxml");
+    let output = output.to_str().unwrap();
+
+    hum::convert_file_to_musicxml("daisy.hum", output).unwrap();
+
+    let xml = std::fs::read_to_string(output).unwrap();
+    assert!(xml.starts_with("<?xml"));
+    assert!(xml.ends_with("</score-partwise>\n"));
+    assert_eq!(
+        elements(&xml, "work-title"),
+        vec!["DAISY BELL by Harry Dacre"]
+    );
+    assert!(elements(&xml, "text").starts_with(&["Dai", "sy!"]));
+}
+
+#[test]
+fn test_cents_offsets_are_microtonal_alterations() {
+    let xml = encode("| (An_4-14c 1/4) (Cs_4+50c 1/4) (440Hz 1/2) ;\n").unwrap();
+
+    assert_eq!(elements(&xml, "step"), vec!["A", "C", "A"]);
+    assert_eq!(elements(&xml, "alter"), vec!["-0.14", "1.50"]);
+}
@@ -0,0 +1,99 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use hum::SAMPLE_RATE;
+use hum::hum_parse::hum_grammar;
+use hum::hum_process;
+
+fn render(score: &str) -> Vec<f32> {
+    let commands = hum_grammar::score(score).unwrap();
+    hum_process::run_commands(commands).unwrap()
+}
+
+fn assert_renders_like(score: &str, written_out: &str) {
+    let ornamented = render(score);
+    let expected = render(written_out);
+
+    assert_eq!(ornamented.len(), expected.len());
+    let max_difference = ornamented
+        .iter()
+        .zip(&expected)
+        .fold(0.0f32, |max, (a, b)| max.max((a - b).abs()));
+    assert!(
+        max_difference < 1e-4,
+        "{} differs by {}",
+        score,
+        max_difference
+    );
+}
+
+#[test]
+fn test_grace_note_steals_time() {
+    assert_renders_like(
+        "[ 60_bpm ] | g(Dn_5 1/16) (Cn_5 1/4)",
+        "[ 60_bpm ] | (Dn_5 1/16) (Cn_5 3/16)",
+    );
+    assert_renders_like(
+        "[ 60_bpm ] | g(En_5 1/16) g(Dn_5 1/16) (Cn_5 1/4) (Gn_4 1/4)",
+        "[ 60_bpm ] | (En_5 1/16) (Dn_5 1/16) (Cn_5 1/8) (Gn_4 1/4)",
+    );
+}
+
+#[test]
+fn test_grace_notes_take_at_most_half_the_note() {
+    assert_renders_like(
+        "[ 60_bpm ] | g(Dn_5 1/4) (Cn_5 1/4)",
+        "[ 60_bpm ] | (Dn_5 1/8) (Cn_5 1/8)",
+    );
+}
+
+#[test]
+fn test_trailing_grace_note_plays_on_its_own() {
+    let audio = render("[ 60_bpm ] | (Cn_5 1/4) g(Dn_5 1/16)");
+    assert_eq!(audio.len(), (SAMPLE_RATE as f32 * 1.25) as usize);
+}
+
+#[test]
+fn test_ornaments() {
+    assert_renders_like(
+        "[ 60_bpm ] | (Cn_5 1/8)tr",
+        "[ 60_bpm ] | (Cn_5 1/32) (Dn_5 1/32) (Cn_5 1/32) (Dn_5 1/32)",
+    );
+    assert_renders_like(
+        "[ 60_bpm ] | (Cn_5 1/4)mord",
+        "[ 60_bpm ] | (Cn_5 1/32) (Bn_4 1/32) (Cn_5 3/16)",
+    );
+    assert_renders_like(
+        "[ 60_bpm ] | (Cn_5 1/4)turn",
+        "[ 60_bpm ] | (Dn_5 1/16) (Cn_5 1/16) (Bn_4 1/16) (Cn_5 1/16)",
+    );
+}
+
+#[test]
+fn test_ornament_neighbors_follow_the_tuning() {
+    assert_renders_like(
+        "[ 60_bpm ] [ tuning just Cn ] | (Cn_5 1/8)tr (En_5 1/4)mord",
+        "[ 60_bpm ] [ tuning just Cn ] | (Cn_5 1/32) (Dn_5 1/32) (Cn_5 1/32) (Dn_5 1/32) \
+         (En_5 1/32) (Ds_5 1/32) (En_5 3/16)",
+    );
+    // A whole tone is three steps of 19-EDO, and a semitone is two:
+    assert_renders_like(
+        "[ 60_bpm ] [ tuning 19edo ] | (s0_4 1/4)turn",
+        "[ 60_bpm ] [ tuning 19edo ] | (s3_4 1/16) (s0_4 1/16) (s17_3 1/16) (s0_4 1/16)",
+    );
+}
@@ -0,0 +1,170 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use hum::hum_parse::hum_grammar;
+use hum::hum_process;
+use hum::hum_process::hum_tuning::{Temperament, Tuning};
+
+fn render(score: &str) -> Result<Vec<f32>, hum::hum_error::GenerateError> {
+    let commands = hum_grammar::score(score).unwrap();
+    hum_process::run_commands(commands)
+}
+
+#[test]
+fn test_octaves_beyond_piano_range() {
+    assert!(render("[ 60_bpm ] | (Cn_8 1/4) ;").is_ok());
+    assert!(render("[ 60_bpm ] | (Cn_-1 1/4) ;").is_ok());
+}
+
+#[test]
+fn test_pitch_above_nyquist() {
+    let err = render("[ 60_bpm ] | (Cn_11 1/4) ;").unwrap_err();
+    assert!(
+        err.message.contains("Nyquist"),
+        "Unexpected error message: {}",
+        err.message
+    );
+}
+
+#[test]
+fn test_tuning_directive() {
+    let standard = render("[ 60_bpm ] | (An_4 1/4) ;").unwrap();
+    let retuned = render("[ 60_bpm ] [ tuning A4=432 ] | (An_4 1/4) ;").unwrap();
+    assert_eq!(standard.len(), retuned.len());
+    assert_ne!(standard, retuned);
+
+    let err = render("[ tuning slendro ] | (An_4 1/4) ;").unwrap_err();
+    assert!(err.message.contains("slendro"));
+}
+
+#[test]
+fn test_tuning_api() {
+    let commands = hum_grammar::score("[ 60_bpm ] | (An_4 1/4) ;").unwrap();
+    let tuning = Tuning::new(432.0, Temperament::Equal);
+    let retuned = hum_process::run_commands_with_tuning(commands, tuning).unwrap();
+    let directive = render("[ 60_bpm ] [ tuning A4=432 ] | (An_4 1/4) ;").unwrap();
+    assert_eq!(retuned, directive);
+}
+
+#[test]
+fn test_scala_tuning() {
+    let scl_path = std::env::temp_dir().join("hum_pitch_tests_pentatonic.scl");
+    std::fs::write(
+        &scl_path,
+        "! test scale\nPentatonic\n5\n9/8\n5/4\n3/2\n5/3\n2/1\n",
+    )
+    .unwrap();
+
+    let score = format!(
+        "[ 60_bpm ] [ tuning scala \"{}\" ] | (s2_4 1/4) (s7_3 1/4) ;",
+        scl_path.display()
+    );
+    assert!(render(&score).is_ok());
+
+    let err = render("[ tuning scala \"missing.scl\" ] | (s0_4 1/4) ;").unwrap_err();
+    assert!(err.message.contains("missing.scl"));
+}
+
+#[test]
+fn test_scala_files_resolve_relative_to_the_score() {
+    let dir = std::env::temp_dir().join("hum_pitch_tests_scala_dir");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("penta.scl"),
+        "Pentatonic\n5\n9/8\n5/4\n3/2\n5/3\n2/1\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("keys.kbm"),
+        "5\n60\n72\n60\n69\n440.0\n5\n0\n1\n2\n3\n4\n",
+    )
+    .unwrap();
+    let score = dir.join("song.hum");
+    std::fs::write(
+        &score,
+        "[ 60_bpm ] [ tuning scala \"penta.scl\" kbm \"keys.kbm\" ] | (An_4 1/4) ;\n",
+    )
+    .unwrap();
+
+    let output = dir.join("song.wav");
+    hum::convert_file_to_wav(score.to_str().unwrap(), output.to_str().unwrap()).unwrap();
+    assert!(hum::describe_file(score.to_str().unwrap()).is_ok());
+}
+
+#[test]
+fn test_edo_tuning() {
+    assert!(render("[ 60_bpm ] [ tuning 19edo ] | (s7_4 1/4) (Cs_4 1/4) ;").is_ok());
+}
+
+#[test]
+fn test_raw_frequency_and_cents_notes() {
+    let named = render("[ 60_bpm ] | (An_4 1/4) ;").unwrap();
+    let raw = render("[ 60_bpm ] | (440Hz 1/4) ;").unwrap();
+    assert_eq!(named, raw);
+
+    let detuned = render("[ 60_bpm ] | (An_4-14c 1/4) ;").unwrap();
+    assert_eq!(named.len(), detuned.len());
+    assert_ne!(named, detuned);
+
+    assert!(render("[ 60_bpm ] | (30000Hz 1/4) ;").is_err());
+}
+
+#[test]
+fn test_glide_between_notes() {
+    let plain = render("[ 60_bpm ] | (Cn_4 1/4) (Gn_4 1/4) ;").unwrap();
+    let glide = render("[ 60_bpm ] | (Cn_4 1/4)/>(Gn_4 1/4) ;").unwrap();
+    assert_eq!(plain.len(), glide.len());
+    assert_ne!(plain, glide);
+
+    // The glide can cross a bar line, but not the end of a line of music.
+    assert!(render("[ 60_bpm ] [ 1/4 ] | (Cn_4 1/4)/> | (Gn_4 1/4) ;").is_ok());
+    assert!(render("[ 60_bpm ] | (Cn_4 1/4)/> ; (Gn_4 1/4) ;").is_err());
+    assert!(render("[ 60_bpm ] | (Cn_4 1/4)/>(Rest 1/4) ;").is_err());
+}
+
+#[test]
+fn test_voice_modulation() {
+    let plain = render("[ 60_bpm ] % sine | (An_4 1/4)").unwrap();
+    let vibrato = render("[ 60_bpm ] % sine vibrato=6:50 | (An_4 1/4)").unwrap();
+    let tremolo = render("[ 60_bpm ] % sine tremolo=4:0.5 | (An_4 1/4)").unwrap();
+
+    assert_eq!(plain.len(), vibrato.len());
+    assert_eq!(plain.len(), tremolo.len());
+    assert_ne!(plain, vibrato);
+
+    // Tremolo only ever lowers the amplitude of the note:
+    assert!(
+        plain
+            .iter()
+            .zip(&tremolo)
+            .all(|(p, t)| t.abs() <= p.abs() + 1e-6)
+    );
+    let peak = |wave: &[f32]| wave.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+    assert!(peak(&tremolo[5000..6000]) < peak(&plain[5000..6000]));
+
+    // Depths out of range, and numbers so large they parse as infinity, are rejected:
+    let huge = "9".repeat(40);
+    for voice in [
+        "tremolo=4:1.5".to_string(),
+        format!("vibrato={}:30", huge),
+        format!("vibrato=6:{}", huge),
+    ] {
+        let score = format!("[ 60_bpm ] % sine {} | (An_4 1/4)", voice);
+        assert!(render(&score).is_err(), "{}", voice);
+    }
+}
@@ -0,0 +1,111 @@
+/*
+Hum: A Music Markup Language Synthesizer
+Copyright (C) 2026 Connor R. Bulakites
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use hum::hum_io::{self, AudioFormat};
+use hum::hum_parse::parse_located_score;
+use hum::hum_process;
+use hum::hum_process::hum_tuning::Tuning;
+
+// Two voices with held, short, and overlapping notes, long enough to take
+// many chunks:
+const SCORE: &str = "[ 90_bpm ] [ 3/4 ]
+*
+% sine
+| (Cn_4 1/2)+ (En_4 1/8). (Gn_4 1/8) | (Cn_5 3/4) |
+% square
+| (Cn_3 1/4) (Rest 1/4) (Gn_2 1/4)> | (Cn_3 1/8) (Dn_3 1/8) (En_3 1/2)+ ;
+*
+[ 140_bpm ]
+% sine
+| (An_4 1/12) (Bn_4 1/12) (Cn_5 1/12) (Dn_5 1/4) (Rest 1/4) ;
+";
+
+fn stream(score: &str, format: AudioFormat) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    hum::stream_audio(score, format, &mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn test_chunks_match_whole_waveform() {
+    let commands = || parse_located_score(SCORE).unwrap();
+    let waveform = hum_process::run_located_commands(commands(), Tuning::default()).unwrap();
+    let renderer = hum_process::stream_located_commands(commands(), Tuning::default()).unwrap();
+    assert_eq!(renderer.sample_count(), waveform.len());
+
+    let chunks: Vec<Vec<f32>> = renderer.collect();
+    assert!(chunks.len() > 10);
+    assert!(
+        chunks
+            .iter()
+            .all(|chunk| !chunk.is_empty() && chunk.len() <= 4096)
+    );
+    assert_eq!(chunks.concat(), waveform);
+}
+
+#[test]
+fn test_streams_match_saved_audio() {
+    let commands = parse_located_score(SCORE).unwrap();
+    let waveform = hum_process::run_located_commands(commands, Tuning::default()).unwrap();
+
+    for format in [
+        AudioFormat::Wav,
+        AudioFormat::Flac,
+        AudioFormat::S16le,
+        AudioFormat::F32le,
+    ] {
+        let mut saved = Vec::new();
+        hum_io::write_audio(&waveform, format, &mut saved).unwrap();
+        let streamed = stream(SCORE, format);
+        if format == AudioFormat::Wav {
+            // The header is written before the samples, so it must already
+            // hold the right length:
+            let streamed = hound::WavReader::new(std::io::Cursor::new(streamed)).unwrap();
+            let saved = hound::WavReader::new(std::io::Cursor::new(saved)).unwrap();
+            assert_eq!(streamed.spec(), saved.spec());
+            assert_eq!(streamed.len() as usize, waveform.len());
+            let samples = |reader: hound::WavReader<_>| {
+                reader
+                    .into_samples::<i16>()
+                    .map(Result::unwrap)
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(samples(streamed), samples(saved));
+        } else {
+            assert_eq!(streamed, saved);
+        }
+    }
+}
+
+#[test]
+fn test_empty_score_streams_empty_wav() {
+    let reader = hound::WavReader::new(std::io::Cursor::new(stream("", AudioFormat::Wav))).unwrap();
+    assert_eq!(reader.len(), 0);
+}
+
+#[test]
+fn test_errors_come_before_any_audio() {
+    let mut bytes = Vec::new();
+    let result = hum::stream_audio(
+        "[ 60_bpm ] | (Cn_4 1/4) (Xn_4 1/4) ;",
+        AudioFormat::Wav,
+        &mut bytes,
+    );
+    assert!(result.is_err());
+    assert!(bytes.is_empty());
+}